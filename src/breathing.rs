@@ -0,0 +1,274 @@
+use std::fmt;
+
+use crate::csi::{CsiQuantity, CsiSettings};
+use crate::data::DataContainer;
+use crate::filter::{sample_rate, Biquads};
+use crate::spectrum::{amplitude_spectrum, resample, Window};
+
+// the trend is shortened to this number of estimates
+const MAX_TREND: usize = 3600;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RateMethod {
+    PeakCounting,
+    FftPeak,
+}
+
+impl fmt::Display for RateMethod {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RateMethod::PeakCounting => write!(f, "Peak Counting"),
+            RateMethod::FftPeak => write!(f, "FFT Peak"),
+        }
+    }
+}
+
+/// How the subcarriers used for the estimate are ranked.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SubcarrierRanking {
+    /// Variance in the pass band.
+    Variance,
+    /// Variance in the pass band relative to the variance outside of it.
+    Snr,
+}
+
+impl fmt::Display for SubcarrierRanking {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SubcarrierRanking::Variance => write!(f, "Variance"),
+            SubcarrierRanking::Snr => write!(f, "SNR"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BreathingSettings {
+    pub enabled: bool,
+    /// Pass band in Hz.
+    pub low: f64,
+    pub high: f64,
+    /// Analysed time span in seconds.
+    pub span: f64,
+    /// Number of subcarriers used for the estimate.
+    pub subcarriers: usize,
+    pub ranking: SubcarrierRanking,
+    pub method: RateMethod,
+}
+
+impl Default for BreathingSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            low: 0.1,
+            high: 0.5,
+            span: 30.0,
+            subcarriers: 5,
+            ranking: SubcarrierRanking::Variance,
+            method: RateMethod::FftPeak,
+        }
+    }
+}
+
+/// Rate estimation from the amplitude of the best subcarriers, updated once per second.
+#[derive(Clone, Debug, Default)]
+pub struct BreathingEstimator {
+    /// Latest rate in breaths (or beats) per minute.
+    pub bpm: Option<f64>,
+    /// Datasets used for the latest estimate.
+    pub subcarriers: Vec<usize>,
+    /// Estimated rate over time as (time [s], rate [1/min]).
+    pub trend: Vec<[f64; 2]>,
+    last_update: f64,
+}
+
+impl BreathingEstimator {
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    pub fn update(
+        &mut self,
+        data: &DataContainer,
+        csi_settings: &CsiSettings,
+        settings: &BreathingSettings,
+    ) {
+        let Some(now) = data.time.last().copied() else {
+            self.reset();
+            return;
+        };
+        if now < self.last_update {
+            // the data has been cleared
+            self.reset();
+        }
+        if now - self.last_update < 1000.0 {
+            return;
+        }
+        self.last_update = now;
+
+        let (time, series) = amplitude_series(data, csi_settings, now - settings.span * 1000.0);
+        let rate = sample_rate(&time);
+        // the pass band has to be resolved and the span has to cover a full period
+        if rate < 2.0 * settings.high || time.len() < 2 || now - time[0] < 1000.0 / settings.low {
+            self.bpm = None;
+            return;
+        }
+
+        let mut ranked: Vec<(usize, f64, Vec<f32>)> = series
+            .iter()
+            .enumerate()
+            .map(|(i, set)| {
+                let uniform = resample(&time, set, rate);
+                let filtered =
+                    Biquads::band_pass(2, settings.low, settings.high, rate).apply(&uniform);
+                let in_band = variance(&filtered);
+                let score = match settings.ranking {
+                    SubcarrierRanking::Variance => in_band,
+                    SubcarrierRanking::Snr => {
+                        in_band / (variance(&uniform) - in_band).max(f64::EPSILON)
+                    }
+                };
+                (i, score, filtered)
+            })
+            .filter(|(_, score, _)| score.is_finite())
+            .collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+        ranked.truncate(settings.subcarriers.max(1));
+        if ranked.is_empty() {
+            self.bpm = None;
+            return;
+        }
+
+        let signals: Vec<Vec<f32>> = ranked.iter().map(|(_, _, s)| s.clone()).collect();
+        self.subcarriers = ranked.iter().map(|(i, _, _)| *i).collect();
+        self.bpm = match settings.method {
+            RateMethod::FftPeak => fft_peak(&signals, rate, settings),
+            RateMethod::PeakCounting => peak_counting(&signals, rate, settings),
+        }
+        .map(|frequency| frequency * 60.0);
+
+        if let Some(bpm) = self.bpm {
+            self.trend.push([now / 1000.0, bpm]);
+            if self.trend.len() > MAX_TREND {
+                self.trend.remove(0);
+            }
+        }
+    }
+}
+
+/// Time stamps in ms and amplitude series since `start`, computed from the CSI frames
+/// if available and otherwise taken from the datasets.
+fn amplitude_series(
+    data: &DataContainer,
+    csi_settings: &CsiSettings,
+    start: f64,
+) -> (Vec<f64>, Vec<Vec<f32>>) {
+    if data.csi_frames.is_empty() {
+        let first = data.time.partition_point(|t| *t < start);
+        let series = data
+            .dataset
+            .iter()
+            .filter(|set| set.len() == data.time.len())
+            .map(|set| set[first..].to_vec())
+            .collect();
+        return (data.time[first..].to_vec(), series);
+    }
+    let amplitude = CsiSettings {
+        quantity: CsiQuantity::Amplitude,
+        ..*csi_settings
+    };
+    let first = data.csi_frames.partition_point(|f| f.relative_time < start);
+    let mut time = vec![];
+    let mut series: Vec<Vec<f32>> = vec![];
+    for frame in data.csi_frames[first..].iter() {
+        let Some((_, values)) = amplitude.compute(frame) else {
+            continue;
+        };
+        // frames with a different layout would mix up the subcarriers
+        if !series.is_empty() && values.len() != series.len() {
+            continue;
+        }
+        series.resize(values.len(), vec![]);
+        for (set, value) in series.iter_mut().zip(values) {
+            set.push(value);
+        }
+        time.push(frame.relative_time);
+    }
+    (time, series)
+}
+
+fn variance(values: &[f32]) -> f64 {
+    let n = values.len().max(1) as f64;
+    let mean = values.iter().map(|v| *v as f64).sum::<f64>() / n;
+    values
+        .iter()
+        .map(|v| (*v as f64 - mean).powi(2))
+        .sum::<f64>()
+        / n
+}
+
+/// Frequency of the highest peak of the mean power spectrum inside the pass band,
+/// refined by parabolic interpolation.
+fn fft_peak(signals: &[Vec<f32>], rate: f64, settings: &BreathingSettings) -> Option<f64> {
+    let mut power: Vec<f64> = vec![];
+    let mut fft_len = 0;
+    for signal in signals {
+        let (amplitudes, n) = amplitude_spectrum(signal, Window::Hann);
+        fft_len = n;
+        power.resize(amplitudes.len(), 0.0);
+        for (p, a) in power.iter_mut().zip(amplitudes) {
+            *p += (a as f64).powi(2);
+        }
+    }
+    let resolution = rate / fft_len as f64;
+    let first = ((settings.low / resolution).ceil() as usize).max(1);
+    let last = ((settings.high / resolution).floor() as usize).min(power.len().saturating_sub(2));
+    let peak = (first..=last).max_by(|a, b| power[*a].total_cmp(&power[*b]))?;
+    let (left, center, right) = (power[peak - 1], power[peak], power[peak + 1]);
+    let denominator = left - 2.0 * center + right;
+    let offset = if denominator.abs() > f64::EPSILON {
+        (0.5 * (left - right) / denominator).clamp(-0.5, 0.5)
+    } else {
+        0.0
+    };
+    Some((peak as f64 + offset) * resolution)
+}
+
+/// Mean interval between the maxima of the combined signal, maxima closer than the
+/// shortest period of the pass band are merged.
+fn peak_counting(signals: &[Vec<f32>], rate: f64, settings: &BreathingSettings) -> Option<f64> {
+    let reference = signals.first()?;
+    let len = signals.iter().map(|s| s.len()).min()?;
+    // normalize the signals and align their sign to the best subcarrier before averaging
+    let mut combined = vec![0.0; len];
+    for signal in signals {
+        let std = variance(signal).sqrt().max(f64::EPSILON);
+        let correlation: f64 = signal
+            .iter()
+            .zip(reference)
+            .map(|(a, b)| *a as f64 * *b as f64)
+            .sum();
+        let sign = if correlation < 0.0 { -1.0 } else { 1.0 };
+        for (c, v) in combined.iter_mut().zip(signal) {
+            *c += sign * *v as f64 / std;
+        }
+    }
+    let min_distance = (rate / settings.high) as usize;
+    let mut peaks: Vec<usize> = vec![];
+    for i in 1..len.saturating_sub(1) {
+        if combined[i] > 0.0 && combined[i] >= combined[i - 1] && combined[i] > combined[i + 1] {
+            match peaks.last() {
+                Some(last) if i - last < min_distance => {
+                    if combined[i] > combined[*last] {
+                        *peaks.last_mut()? = i;
+                    }
+                }
+                _ => peaks.push(i),
+            }
+        }
+    }
+    let (first, last) = (peaks.first()?, peaks.last()?);
+    if peaks.len() < 2 {
+        return None;
+    }
+    Some((peaks.len() - 1) as f64 * rate / (last - first) as f64)
+}
@@ -0,0 +1,832 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+
+use crate::data::{Packet, SerialDirection};
+
+/// Line ending expected by the esp-csi CLI.
+pub const LINE_ENDING: &str = "\r\n";
+
+const MAX_CONNECTIONS: u8 = 10;
+const MAX_SSID_LEN: usize = 32;
+const MIN_PASSWORD_LEN: usize = 8;
+const MAX_PASSWORD_LEN: usize = 63;
+
+/// Shown instead of a Wi-Fi password.
+pub const REDACTED: &str = "********";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TrafficType {
+    #[default]
+    Icmp,
+    Udp,
+}
+
+impl TrafficType {
+    pub const ALL: [TrafficType; 2] = [TrafficType::Icmp, TrafficType::Udp];
+
+    pub fn arg(&self) -> &'static str {
+        match *self {
+            TrafficType::Icmp => "icmp",
+            TrafficType::Udp => "udp",
+        }
+    }
+}
+
+impl fmt::Display for TrafficType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TrafficType::Icmp => write!(f, "icmp"),
+            TrafficType::Udp => write!(f, "UDP"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NetworkArchitecture {
+    Rsta,
+    Rapsta,
+    Apsta,
+    #[default]
+    Sniff,
+}
+
+impl NetworkArchitecture {
+    pub const ALL: [NetworkArchitecture; 4] = [
+        NetworkArchitecture::Rsta,
+        NetworkArchitecture::Rapsta,
+        NetworkArchitecture::Apsta,
+        NetworkArchitecture::Sniff,
+    ];
+
+    pub fn arg(&self) -> &'static str {
+        match *self {
+            NetworkArchitecture::Rsta => "rsta",
+            NetworkArchitecture::Rapsta => "rapsta",
+            NetworkArchitecture::Apsta => "apsta",
+            NetworkArchitecture::Sniff => "sniff",
+        }
+    }
+}
+
+impl fmt::Display for NetworkArchitecture {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.arg())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WifiMode {
+    Ap,
+    Station,
+    #[default]
+    Sniffer,
+    ApStation,
+}
+
+impl WifiMode {
+    pub const ALL: [WifiMode; 4] = [
+        WifiMode::Ap,
+        WifiMode::Station,
+        WifiMode::Sniffer,
+        WifiMode::ApStation,
+    ];
+
+    pub fn arg(&self) -> &'static str {
+        match *self {
+            WifiMode::Ap => "ap",
+            WifiMode::Station => "station",
+            WifiMode::Sniffer => "sniffer",
+            WifiMode::ApStation => "ap-station",
+        }
+    }
+
+    pub fn has_ap(&self) -> bool {
+        matches!(*self, WifiMode::Ap | WifiMode::ApStation)
+    }
+
+    pub fn has_station(&self) -> bool {
+        matches!(*self, WifiMode::Station | WifiMode::ApStation)
+    }
+}
+
+impl fmt::Display for WifiMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.arg())
+    }
+}
+
+/// CSI fields that can be disabled with `set-csi`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CsiFlags {
+    pub disable_lltf: bool,
+    pub disable_htltf: bool,
+    pub disable_stbc_htltf: bool,
+    pub disable_ltf_merge: bool,
+}
+
+impl CsiFlags {
+    pub fn any(&self) -> bool {
+        self.disable_lltf || self.disable_htltf || self.disable_stbc_htltf || self.disable_ltf_merge
+    }
+}
+
+/// A single option of `set-wifi`, the firmware takes them one command at a time.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WifiOption {
+    Mode(WifiMode),
+    MaxConnections(u8),
+    HideSsid,
+    ApSsid(String),
+    ApPassword(String),
+    StaSsid(String),
+    StaPassword(String),
+}
+
+/// Commands of the esp-csi CLI.
+///
+/// `Display` writes the command line without the line ending, `FromStr` parses it back.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EspCommand {
+    SetTraffic {
+        traffic_type: TrafficType,
+        /// Interval in ms, the firmware default is used if not set.
+        interval: Option<u32>,
+    },
+    SetNetwork(NetworkArchitecture),
+    SetCsi(CsiFlags),
+    SetWifi(WifiOption),
+    ShowConfig,
+    ResetConfig,
+    Start,
+}
+
+fn validate_text(name: &str, value: &str, min_len: usize, max_len: usize) -> Result<(), String> {
+    if value.len() < min_len || value.len() > max_len {
+        return Err(format!(
+            "the {name} needs {min_len} to {max_len} characters"
+        ));
+    }
+    // arguments are separated by spaces on the device
+    if value.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return Err(format!("the {name} must not contain spaces"));
+    }
+    Ok(())
+}
+
+impl EspCommand {
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            EspCommand::SetTraffic {
+                interval: Some(0), ..
+            } => Err("the traffic interval must be larger than 0 ms".to_string()),
+            EspCommand::SetWifi(option) => match option {
+                WifiOption::MaxConnections(max) if *max == 0 || *max > MAX_CONNECTIONS => Err(
+                    format!("the maximum number of connections must be 1 to {MAX_CONNECTIONS}"),
+                ),
+                WifiOption::ApSsid(ssid) => validate_text("AP SSID", ssid, 1, MAX_SSID_LEN),
+                WifiOption::StaSsid(ssid) => validate_text("STA SSID", ssid, 1, MAX_SSID_LEN),
+                WifiOption::ApPassword(password) => {
+                    validate_text("AP password", password, MIN_PASSWORD_LEN, MAX_PASSWORD_LEN)
+                }
+                WifiOption::StaPassword(password) => {
+                    validate_text("STA password", password, MIN_PASSWORD_LEN, MAX_PASSWORD_LEN)
+                }
+                _ => Ok(()),
+            },
+            _ => Ok(()),
+        }
+    }
+
+    /// `start` is answered by the CSI stream instead of a reply.
+    pub fn expects_reply(&self) -> bool {
+        *self != EspCommand::Start
+    }
+
+    /// The command as sent to the device, including the line ending.
+    pub fn to_line(&self) -> String {
+        format!("{self}{LINE_ENDING}")
+    }
+}
+
+impl fmt::Display for EspCommand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EspCommand::SetTraffic {
+                traffic_type,
+                interval,
+            } => {
+                write!(f, "set-traffic --enable --type={}", traffic_type.arg())?;
+                if let Some(interval) = interval {
+                    write!(f, " --interval={interval}")?;
+                }
+                Ok(())
+            }
+            EspCommand::SetNetwork(arch) => write!(f, "set-network --arch={}", arch.arg()),
+            EspCommand::SetCsi(flags) => {
+                write!(f, "set-csi")?;
+                for (disabled, arg) in [
+                    (flags.disable_lltf, "--disable-lltf"),
+                    (flags.disable_htltf, "--disable-htltf"),
+                    (flags.disable_stbc_htltf, "--disable-stbc-htltf"),
+                    (flags.disable_ltf_merge, "--disable-ltf-merge"),
+                ] {
+                    if disabled {
+                        write!(f, " {arg}")?;
+                    }
+                }
+                Ok(())
+            }
+            EspCommand::SetWifi(option) => match option {
+                WifiOption::Mode(mode) => write!(f, "set-wifi --mode={}", mode.arg()),
+                WifiOption::MaxConnections(max) => write!(f, "set-wifi --max-connections={max}"),
+                WifiOption::HideSsid => write!(f, "set-wifi --hide-ssid"),
+                WifiOption::ApSsid(ssid) => write!(f, "set-wifi --ap-ssid={ssid}"),
+                WifiOption::ApPassword(password) => write!(f, "set-wifi --ap-password={password}"),
+                WifiOption::StaSsid(ssid) => write!(f, "set-wifi --sta-ssid={ssid}"),
+                WifiOption::StaPassword(password) => {
+                    write!(f, "set-wifi --sta-password={password}")
+                }
+            },
+            EspCommand::ShowConfig => write!(f, "show-config"),
+            EspCommand::ResetConfig => write!(f, "reset-config"),
+            EspCommand::Start => write!(f, "start"),
+        }
+    }
+}
+
+fn parse_arg<T: Copy + fmt::Display>(all: &[T], arg: &str, value: &str) -> Result<T, String> {
+    all.iter()
+        .find(|item| item.to_string().eq_ignore_ascii_case(value))
+        .copied()
+        .ok_or_else(|| format!("invalid value for {arg}: {value}"))
+}
+
+fn parse_number<T: FromStr>(arg: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid value for {arg}: {value}"))
+}
+
+impl FromStr for EspCommand {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let mut words = line.split_whitespace();
+        let name = words.next().ok_or_else(|| "empty command".to_string())?;
+        let args: Vec<(&str, Option<&str>)> = words
+            .map(|word| match word.split_once('=') {
+                Some((arg, value)) => (arg, Some(value)),
+                None => (word, None),
+            })
+            .collect();
+
+        let command = match name {
+            "set-traffic" => {
+                let mut traffic_type = TrafficType::default();
+                let mut interval = None;
+                for (arg, value) in args {
+                    match (arg, value) {
+                        ("--enable", None) => {}
+                        ("--type", Some(value)) => {
+                            traffic_type = parse_arg(&TrafficType::ALL, arg, value)?
+                        }
+                        ("--interval", Some(value)) => interval = Some(parse_number(arg, value)?),
+                        _ => return Err(format!("unknown argument of {name}: {arg}")),
+                    }
+                }
+                EspCommand::SetTraffic {
+                    traffic_type,
+                    interval,
+                }
+            }
+            "set-network" => match args.as_slice() {
+                [("--arch", Some(value))] => {
+                    EspCommand::SetNetwork(parse_arg(&NetworkArchitecture::ALL, "--arch", value)?)
+                }
+                _ => return Err(format!("{name} needs exactly one --arch")),
+            },
+            "set-csi" => {
+                let mut flags = CsiFlags::default();
+                for (arg, value) in args {
+                    match (arg, value) {
+                        ("--disable-lltf", None) => flags.disable_lltf = true,
+                        ("--disable-htltf", None) => flags.disable_htltf = true,
+                        ("--disable-stbc-htltf", None) => flags.disable_stbc_htltf = true,
+                        ("--disable-ltf-merge", None) => flags.disable_ltf_merge = true,
+                        _ => return Err(format!("unknown argument of {name}: {arg}")),
+                    }
+                }
+                EspCommand::SetCsi(flags)
+            }
+            "set-wifi" => {
+                let [(arg, value)] = args.as_slice() else {
+                    return Err(format!("{name} takes one option at a time"));
+                };
+                let option = match (*arg, *value) {
+                    ("--mode", Some(value)) => {
+                        WifiOption::Mode(parse_arg(&WifiMode::ALL, arg, value)?)
+                    }
+                    ("--max-connections", Some(value)) => {
+                        WifiOption::MaxConnections(parse_number(arg, value)?)
+                    }
+                    ("--hide-ssid", None) => WifiOption::HideSsid,
+                    ("--ap-ssid", Some(value)) => WifiOption::ApSsid(value.to_string()),
+                    ("--ap-password", Some(value)) => WifiOption::ApPassword(value.to_string()),
+                    ("--sta-ssid", Some(value)) => WifiOption::StaSsid(value.to_string()),
+                    ("--sta-password", Some(value)) => WifiOption::StaPassword(value.to_string()),
+                    _ => return Err(format!("unknown argument of {name}: {arg}")),
+                };
+                EspCommand::SetWifi(option)
+            }
+            "show-config" | "reset-config" | "start" if !args.is_empty() => {
+                return Err(format!("{name} takes no arguments"))
+            }
+            "show-config" => EspCommand::ShowConfig,
+            "reset-config" => EspCommand::ResetConfig,
+            "start" => EspCommand::Start,
+            _ => return Err(format!("unknown command: {name}")),
+        };
+        command.validate()?;
+        Ok(command)
+    }
+}
+
+fn password_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        // `--sta-password=secret` as sent, `password: secret` as the firmware may print it
+        Regex::new(r#"(?i)(--(?:ap|sta)-password(?:=|\s+)|password\s*[:=]\s*)("[^"]*"|\S+)"#)
+            .unwrap()
+    })
+}
+
+/// Replaces the Wi-Fi passwords in a line sent to or received from the device, such that they
+/// are neither shown in the console nor written to a log.
+pub fn redact(line: &str) -> Cow<'_, str> {
+    password_regex().replace_all(line, |caps: &Captures| format!("{}{REDACTED}", &caps[1]))
+}
+
+/// Configuration reported by `show-config`, settings missing in the reply are `None`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DeviceConfig {
+    /// Absolute time `show-config` was sent, such that every reply is distinct.
+    pub time: f64,
+    pub wifi_mode: Option<WifiMode>,
+    pub network_architecture: Option<NetworkArchitecture>,
+    pub traffic_enable: Option<bool>,
+    pub traffic_type: Option<TrafficType>,
+    pub traffic_interval: Option<u32>,
+    pub disable_lltf: Option<bool>,
+    pub disable_htltf: Option<bool>,
+    pub disable_stbc_htltf: Option<bool>,
+    pub disable_ltf_merge: Option<bool>,
+    pub max_connections: Option<u8>,
+    pub hide_ssid: Option<bool>,
+    pub ap_ssid: Option<String>,
+    pub sta_ssid: Option<String>,
+}
+
+/// Lowercase letters and digits only, such that `Wi-Fi Mode` matches `wifi_mode`.
+fn normalize(text: &str) -> String {
+    text.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match normalize(value).as_str() {
+        "true" | "yes" | "on" | "1" | "enable" | "enabled" => Some(true),
+        "false" | "no" | "off" | "0" | "disable" | "disabled" => Some(false),
+        _ => None,
+    }
+}
+
+fn parse_choice<T: Copy>(all: &[T], arg: fn(&T) -> &'static str, value: &str) -> Option<T> {
+    let value = normalize(value);
+    all.iter()
+        .find(|item| normalize(arg(item)) == value)
+        .copied()
+}
+
+fn parse_wifi_mode(value: &str) -> Option<WifiMode> {
+    match normalize(value).as_str() {
+        "sta" => Some(WifiMode::Station),
+        "apsta" => Some(WifiMode::ApStation),
+        "sniff" => Some(WifiMode::Sniffer),
+        _ => parse_choice(&WifiMode::ALL, WifiMode::arg, value),
+    }
+}
+
+fn parse_network_architecture(value: &str) -> Option<NetworkArchitecture> {
+    match normalize(value).as_str() {
+        "sniffer" => Some(NetworkArchitecture::Sniff),
+        _ => parse_choice(&NetworkArchitecture::ALL, NetworkArchitecture::arg, value),
+    }
+}
+
+/// Leading number of values like `100 ms`.
+fn parse_leading_number<T: FromStr>(value: &str) -> Option<T> {
+    let end = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    value[..end].parse().ok()
+}
+
+fn parse_ssid(value: &str) -> Option<String> {
+    let value = value.trim_matches('"');
+    match normalize(value).as_str() {
+        "" | "none" | "notset" => Some(String::new()),
+        _ => Some(value.to_string()),
+    }
+}
+
+/// Keys that only name a setting together with their section, e.g. `mode` below `Wi-Fi:`.
+const SECTION_KEYS: [&str; 8] = [
+    "mode",
+    "type",
+    "interval",
+    "arch",
+    "architecture",
+    "enable",
+    "enabled",
+    "ssid",
+];
+
+/// Normalized name of a section header like `Wi-Fi:`, `[Traffic]` or `--- CSI ---`.
+fn section_header(line: &str) -> Option<String> {
+    let line = line.trim();
+    let name = if let Some(name) = line.strip_suffix(':') {
+        name
+    } else if let Some(name) = line
+        .strip_prefix('[')
+        .and_then(|name| name.strip_suffix(']'))
+    {
+        name
+    } else if line.starts_with(['-', '=']) && line.ends_with(['-', '=']) {
+        line.trim_matches(['-', '=', ' '])
+    } else {
+        return None;
+    };
+    if name.contains([':', '=']) {
+        return None;
+    }
+    let name = normalize(name);
+    let name = ["configuration", "config", "settings"]
+        .iter()
+        .find_map(|suffix| name.strip_suffix(suffix))
+        .unwrap_or(&name);
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+fn set<T>(field: &mut Option<T>, value: Option<T>) -> bool {
+    value.map(|value| *field = Some(value)).is_some()
+}
+
+impl DeviceConfig {
+    /// Reads one `key: value` line of the reply below the normalized `section` header, returns
+    /// false if it is not a known setting.
+    fn parse_line(&mut self, section: &str, line: &str) -> bool {
+        let Some((key, value)) = line.split_once([':', '=']) else {
+            return false;
+        };
+        let value = value.trim();
+        // the CSI fields are either reported as disabled or as enabled
+        let enabled = |value: &str| parse_bool(value).map(|enabled| !enabled);
+        let mut key = normalize(key);
+        if SECTION_KEYS.contains(&key.as_str()) {
+            key.insert_str(0, section);
+        }
+        match key.as_str() {
+            "wifimode" => set(&mut self.wifi_mode, parse_wifi_mode(value)),
+            "networkarchitecture" | "networkarch" => set(
+                &mut self.network_architecture,
+                parse_network_architecture(value),
+            ),
+            "traffic" | "trafficenable" | "trafficenabled" => {
+                set(&mut self.traffic_enable, parse_bool(value))
+            }
+            "traffictype" => set(
+                &mut self.traffic_type,
+                parse_choice(&TrafficType::ALL, TrafficType::arg, value),
+            ),
+            "trafficinterval" => set(&mut self.traffic_interval, parse_leading_number(value)),
+            "disablelltf" => set(&mut self.disable_lltf, parse_bool(value)),
+            "disablehtltf" => set(&mut self.disable_htltf, parse_bool(value)),
+            "disablestbchtltf" => set(&mut self.disable_stbc_htltf, parse_bool(value)),
+            "disableltfmerge" => set(&mut self.disable_ltf_merge, parse_bool(value)),
+            "lltf" | "lltfenabled" => set(&mut self.disable_lltf, enabled(value)),
+            "htltf" | "htltfenabled" => set(&mut self.disable_htltf, enabled(value)),
+            "stbchtltf" | "stbchtltfenabled" => set(&mut self.disable_stbc_htltf, enabled(value)),
+            "ltfmerge" | "ltfmergeenabled" => set(&mut self.disable_ltf_merge, enabled(value)),
+            "maxconnections" => set(&mut self.max_connections, parse_leading_number(value)),
+            "hidessid" | "ssidhidden" => set(&mut self.hide_ssid, parse_bool(value)),
+            "apssid" => set(&mut self.ap_ssid, parse_ssid(value)),
+            "stassid" | "stationssid" => set(&mut self.sta_ssid, parse_ssid(value)),
+            _ => false,
+        }
+    }
+}
+
+/// Whether the line is the bare prompt of the device CLI, e.g. `esp-csi>`.
+pub fn is_prompt(line: &str) -> bool {
+    let line = line.trim();
+    line.ends_with('>') && !line.contains(' ')
+}
+
+/// Time the reply to `show-config` is waited for, in ms.
+const CONFIG_REPLY_TIMEOUT: f64 = 2000.0;
+
+/// Collects the reply to a sent `show-config` from the received lines.
+#[derive(Clone, Debug, Default)]
+pub struct ConfigParser {
+    /// Absolute time `show-config` was sent.
+    sent: Option<f64>,
+    /// Normalized header of the section the reply is in, e.g. `wifi` or `traffic`.
+    section: String,
+    config: DeviceConfig,
+}
+
+impl ConfigParser {
+    /// Returns the configuration read so far if the packet is a line of the reply.
+    pub fn feed(&mut self, packet: &Packet) -> Option<&DeviceConfig> {
+        match packet.direction {
+            SerialDirection::Send => {
+                let show_config = matches!(packet.payload.parse(), Ok(EspCommand::ShowConfig));
+                *self = ConfigParser {
+                    sent: show_config.then_some(packet.absolute_time),
+                    section: String::new(),
+                    config: DeviceConfig {
+                        time: packet.absolute_time,
+                        ..Default::default()
+                    },
+                };
+                None
+            }
+            SerialDirection::Receive => {
+                let sent = self.sent?;
+                // the prompt ends the reply
+                if packet.absolute_time - sent > CONFIG_REPLY_TIMEOUT || is_prompt(&packet.payload)
+                {
+                    self.sent = None;
+                    return None;
+                }
+                self.parse_line(&packet.payload).then_some(&self.config)
+            }
+            _ => None,
+        }
+    }
+
+    /// Reads one line of the reply, returns false if it is not a known setting.
+    pub fn parse_line(&mut self, line: &str) -> bool {
+        if self.config.parse_line(&self.section, line) {
+            return true;
+        }
+        if let Some(section) = section_header(line) {
+            self.section = section;
+        }
+        false
+    }
+
+    pub fn reset(&mut self) {
+        *self = ConfigParser::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Checks the exact line sent to the device and that it is parsed back to `command`.
+    fn assert_round_trip(command: EspCommand, line: &str) {
+        assert_eq!(command.to_string(), line);
+        assert_eq!(command.to_line(), format!("{line}\r\n"));
+        assert_eq!(line.parse::<EspCommand>(), Ok(command));
+    }
+
+    fn wifi(option: WifiOption) -> EspCommand {
+        EspCommand::SetWifi(option)
+    }
+
+    #[test]
+    fn set_traffic_round_trip() {
+        assert_round_trip(
+            EspCommand::SetTraffic {
+                traffic_type: TrafficType::Icmp,
+                interval: None,
+            },
+            "set-traffic --enable --type=icmp",
+        );
+        assert_round_trip(
+            EspCommand::SetTraffic {
+                traffic_type: TrafficType::Udp,
+                interval: Some(100),
+            },
+            "set-traffic --enable --type=udp --interval=100",
+        );
+    }
+
+    #[test]
+    fn set_network_round_trip() {
+        for arch in NetworkArchitecture::ALL {
+            assert_round_trip(
+                EspCommand::SetNetwork(arch),
+                &format!("set-network --arch={}", arch.arg()),
+            );
+        }
+        assert_round_trip(
+            EspCommand::SetNetwork(NetworkArchitecture::Rapsta),
+            "set-network --arch=rapsta",
+        );
+    }
+
+    #[test]
+    fn set_csi_round_trip() {
+        assert_round_trip(EspCommand::SetCsi(CsiFlags::default()), "set-csi");
+        let flags = [
+            (
+                CsiFlags {
+                    disable_lltf: true,
+                    ..Default::default()
+                },
+                "set-csi --disable-lltf",
+            ),
+            (
+                CsiFlags {
+                    disable_htltf: true,
+                    ..Default::default()
+                },
+                "set-csi --disable-htltf",
+            ),
+            (
+                CsiFlags {
+                    disable_stbc_htltf: true,
+                    ..Default::default()
+                },
+                "set-csi --disable-stbc-htltf",
+            ),
+            (
+                CsiFlags {
+                    disable_ltf_merge: true,
+                    ..Default::default()
+                },
+                "set-csi --disable-ltf-merge",
+            ),
+        ];
+        for (flags, line) in flags {
+            assert_round_trip(EspCommand::SetCsi(flags), line);
+        }
+        assert_round_trip(
+            EspCommand::SetCsi(CsiFlags {
+                disable_lltf: true,
+                disable_htltf: true,
+                disable_stbc_htltf: true,
+                disable_ltf_merge: true,
+            }),
+            "set-csi --disable-lltf --disable-htltf --disable-stbc-htltf --disable-ltf-merge",
+        );
+    }
+
+    #[test]
+    fn set_wifi_round_trip() {
+        for mode in WifiMode::ALL {
+            assert_round_trip(
+                wifi(WifiOption::Mode(mode)),
+                &format!("set-wifi --mode={}", mode.arg()),
+            );
+        }
+        assert_round_trip(
+            wifi(WifiOption::Mode(WifiMode::ApStation)),
+            "set-wifi --mode=ap-station",
+        );
+        assert_round_trip(
+            wifi(WifiOption::MaxConnections(4)),
+            "set-wifi --max-connections=4",
+        );
+        assert_round_trip(wifi(WifiOption::HideSsid), "set-wifi --hide-ssid");
+        assert_round_trip(
+            wifi(WifiOption::ApSsid("esp-csi".to_string())),
+            "set-wifi --ap-ssid=esp-csi",
+        );
+        assert_round_trip(
+            wifi(WifiOption::ApPassword("ap-secret".to_string())),
+            "set-wifi --ap-password=ap-secret",
+        );
+        assert_round_trip(
+            wifi(WifiOption::StaSsid("home".to_string())),
+            "set-wifi --sta-ssid=home",
+        );
+        assert_round_trip(
+            wifi(WifiOption::StaPassword("sta-secret".to_string())),
+            "set-wifi --sta-password=sta-secret",
+        );
+    }
+
+    #[test]
+    fn commands_without_arguments_round_trip() {
+        assert_round_trip(EspCommand::ShowConfig, "show-config");
+        assert_round_trip(EspCommand::ResetConfig, "reset-config");
+        assert_round_trip(EspCommand::Start, "start");
+        assert!("start --now".parse::<EspCommand>().is_err());
+    }
+
+    #[test]
+    fn validate_ssid_length() {
+        let ssid = "a".repeat(MAX_SSID_LEN);
+        assert!(wifi(WifiOption::ApSsid(ssid.clone())).validate().is_ok());
+        assert!(wifi(WifiOption::StaSsid(ssid)).validate().is_ok());
+        let ssid = "a".repeat(MAX_SSID_LEN + 1);
+        assert!(wifi(WifiOption::ApSsid(ssid.clone())).validate().is_err());
+        assert!(wifi(WifiOption::StaSsid(ssid.clone())).validate().is_err());
+        assert!(format!("set-wifi --ap-ssid={ssid}")
+            .parse::<EspCommand>()
+            .is_err());
+        // the limit is in bytes, 17 umlauts take 34
+        let ssid = "ü".repeat(17);
+        assert!(wifi(WifiOption::StaSsid(ssid)).validate().is_err());
+    }
+
+    #[test]
+    fn validate_password_length() {
+        for len in [MIN_PASSWORD_LEN, MAX_PASSWORD_LEN] {
+            let password = "p".repeat(len);
+            assert!(wifi(WifiOption::ApPassword(password.clone()))
+                .validate()
+                .is_ok());
+            assert!(wifi(WifiOption::StaPassword(password)).validate().is_ok());
+        }
+        for len in [MIN_PASSWORD_LEN - 1, MAX_PASSWORD_LEN + 1] {
+            let password = "p".repeat(len);
+            assert!(wifi(WifiOption::ApPassword(password.clone()))
+                .validate()
+                .is_err());
+            assert!(wifi(WifiOption::StaPassword(password.clone()))
+                .validate()
+                .is_err());
+            assert!(format!("set-wifi --sta-password={password}")
+                .parse::<EspCommand>()
+                .is_err());
+        }
+    }
+
+    fn feed_reply(parser: &mut ConfigParser, lines: &[&str]) -> Vec<bool> {
+        let send = |payload: &str| Packet {
+            relative_time: 0.0,
+            absolute_time: 1000.0,
+            direction: SerialDirection::Send,
+            payload: payload.to_string(),
+        };
+        parser.feed(&send("show-config"));
+        lines
+            .iter()
+            .map(|line| {
+                parser
+                    .feed(&Packet {
+                        direction: SerialDirection::Receive,
+                        payload: line.to_string(),
+                        ..send("")
+                    })
+                    .is_some()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn config_reply_keys_need_their_section() {
+        let mut parser = ConfigParser::default();
+        let config_lines = feed_reply(
+            &mut parser,
+            &[
+                "mode: station",
+                "Wi-Fi Configuration:",
+                "Mode: station",
+                "STA SSID: lab",
+                "[Traffic]",
+                "Type: icmp",
+                "Interval: 100 ms",
+            ],
+        );
+        assert_eq!(config_lines, [false, false, true, true, false, true, true]);
+        let config = &parser.config;
+        assert_eq!(config.wifi_mode, Some(WifiMode::Station));
+        assert_eq!(config.sta_ssid.as_deref(), Some("lab"));
+        assert_eq!(config.traffic_type, Some(TrafficType::Icmp));
+        assert_eq!(config.traffic_interval, Some(100));
+    }
+
+    #[test]
+    fn config_reply_ends_at_prompt() {
+        let mut parser = ConfigParser::default();
+        let config_lines = feed_reply(
+            &mut parser,
+            &["Wi-Fi mode: station", "esp-csi>", "Wi-Fi mode: sniffer"],
+        );
+        assert_eq!(config_lines, [true, false, false]);
+        assert_eq!(parser.config.wifi_mode, Some(WifiMode::Station));
+    }
+}
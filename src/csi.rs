@@ -0,0 +1,830 @@
+use std::f32::consts::PI;
+use std::fmt;
+use std::sync::OnceLock;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::data::{Packet, SerialDirection};
+
+const RAW_DATA_KEY: &str = "csi raw data";
+const FRAME_HEADER: &str = "new csi data";
+/// Longest raw data array spread over multiple lines, longer ones are dropped as malformed.
+const MAX_RAW_DATA_LEN: usize = 8192;
+
+/// A single CSI frame as printed by the esp-csi CLI.
+///
+/// The firmware either prints all fields on one line or one `key: value` pair per line,
+/// in both cases terminated by the bracketed `csi raw data:[...]` array.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CsiFrame {
+    pub relative_time: f64,
+    pub absolute_time: f64,
+    pub mac: String,
+    pub rssi: i32,
+    pub rate: u32,
+    pub channel: u32,
+    pub secondary_channel: u32,
+    pub timestamp: u64,
+    pub noise_floor: i32,
+    pub len: usize,
+    pub data: Vec<i8>,
+    /// Sequence number of the transmitter, if the firmware reports it.
+    pub sequence: Option<u32>,
+}
+
+/// Formats the frame as a single line in the format of the esp-csi CLI, such that it can be
+/// parsed again by the [`CsiParser`].
+impl fmt::Display for CsiFrame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let data: Vec<String> = self.data.iter().map(|v| v.to_string()).collect();
+        if let Some(sequence) = self.sequence {
+            write!(f, "seq: {}, ", sequence)?;
+        }
+        write!(
+            f,
+            "mac: {}, rssi: {}, rate: {}, channel: {}, secondary channel: {}, timestamp: {}, noise floor: {}, len: {}, csi raw data: [{}]",
+            self.mac,
+            self.rssi,
+            self.rate,
+            self.channel,
+            self.secondary_channel,
+            self.timestamp,
+            self.noise_floor,
+            self.len,
+            data.join(",")
+        )
+    }
+}
+
+/// Outcome of feeding one received line to the [`CsiParser`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum CsiLine {
+    /// The line completed a frame.
+    Frame(CsiFrame),
+    /// The line belonged to a frame that is not complete yet.
+    Consumed,
+    /// The line is not part of the CSI output.
+    Ignored,
+}
+
+/// Statistics of the CSI frames parsed from the text output, shared with the GUI.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CsiStats {
+    pub frames: u64,
+    /// Frames dropped because their raw data could not be parsed or was never closed.
+    pub malformed: u64,
+}
+
+impl fmt::Display for CsiStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} CSI frames, {} malformed",
+            self.frames, self.malformed
+        )
+    }
+}
+
+/// Stateful parser turning received lines into [`CsiFrame`]s.
+#[derive(Clone, Debug, Default)]
+pub struct CsiParser {
+    pending: Option<CsiFrame>,
+    raw_data: Option<String>,
+    pub stats: CsiStats,
+}
+
+fn field_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        // longer keys first, such that "secondary channel" is not matched as "channel"
+        Regex::new(
+            r"(?i)\b(secondary channel|noise floor|timestamp|sequence|sig len|length|len|mac|rssi|rate|seq|channel)\s*[:=]\s*([^,\s]+)",
+        )
+        .unwrap()
+    })
+}
+
+/// Other `key: value` lines the firmware prints inside a frame (`rx state`, `sig_mode`, ...).
+fn is_metadata_line(line: &str) -> bool {
+    match line.split_once(':') {
+        Some((key, value)) => {
+            !key.is_empty()
+                && key
+                    .chars()
+                    .all(|c| c.is_ascii_alphabetic() || c == ' ' || c == '_')
+                && !value.trim().is_empty()
+                && !value.trim().contains([',', ' '])
+        }
+        None => false,
+    }
+}
+
+/// Whether the line can continue a raw data array, i.e. only holds numbers and separators.
+fn is_raw_data_line(line: &str) -> bool {
+    line.chars()
+        .all(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | ',' | ' ' | '[' | ']'))
+}
+
+impl CsiParser {
+    pub fn reset(&mut self) {
+        self.pending = None;
+        self.raw_data = None;
+        self.stats = CsiStats::default();
+    }
+
+    pub fn feed(&mut self, packet: &Packet) -> CsiLine {
+        if packet.direction != SerialDirection::Receive {
+            return CsiLine::Ignored;
+        }
+        let line = packet.payload.trim();
+
+        // continuation of a raw data array spread over multiple lines
+        if let Some(mut raw) = self.raw_data.take() {
+            if is_raw_data_line(line) && raw.len() + line.len() < MAX_RAW_DATA_LEN {
+                raw.push(',');
+                raw.push_str(line);
+                return self.finish_raw_data(raw, packet);
+            }
+            // the closing bracket was lost, e.g. dropped bytes or a reset of the device
+            self.pending = None;
+            self.drop_malformed("raw data array not closed");
+        }
+
+        if line.eq_ignore_ascii_case(FRAME_HEADER) {
+            self.pending = Some(CsiFrame::default());
+            return CsiLine::Consumed;
+        }
+
+        let lower = line.to_ascii_lowercase();
+        let (fields, raw) = match lower.find(RAW_DATA_KEY) {
+            Some(start) => {
+                let raw = line[start + RAW_DATA_KEY.len()..]
+                    .trim_start()
+                    .trim_start_matches([':', '='])
+                    .to_string();
+                (&line[..start], Some(raw))
+            }
+            None => (line, None),
+        };
+
+        // fields only belong to a frame after its header or on the line of its raw data
+        if self.pending.is_none() && raw.is_none() {
+            return CsiLine::Ignored;
+        }
+        let mut matched = false;
+        for caps in field_regex().captures_iter(fields) {
+            let frame = self.pending.get_or_insert_with(CsiFrame::default);
+            let value = caps[2].trim();
+            matched |= match caps[1].to_ascii_lowercase().as_str() {
+                "mac" => {
+                    frame.mac = value.to_string();
+                    true
+                }
+                "rssi" => value.parse().map(|v| frame.rssi = v).is_ok(),
+                "rate" => value.parse().map(|v| frame.rate = v).is_ok(),
+                "channel" => value.parse().map(|v| frame.channel = v).is_ok(),
+                "secondary channel" => value.parse().map(|v| frame.secondary_channel = v).is_ok(),
+                "timestamp" => value.parse().map(|v| frame.timestamp = v).is_ok(),
+                "noise floor" => value.parse().map(|v| frame.noise_floor = v).is_ok(),
+                "seq" | "sequence" => value.parse().map(|v| frame.sequence = Some(v)).is_ok(),
+                // length of the received packet, not of the CSI buffer
+                "sig len" => value.parse::<u32>().is_ok(),
+                _ => value.parse().map(|v| frame.len = v).is_ok(),
+            };
+        }
+
+        match raw {
+            Some(raw) => self.finish_raw_data(raw, packet),
+            None if matched || (self.pending.is_some() && is_metadata_line(line)) => {
+                CsiLine::Consumed
+            }
+            None => {
+                // a header without its fields and raw data, e.g. after a reset of the device
+                self.pending = None;
+                CsiLine::Ignored
+            }
+        }
+    }
+
+    fn finish_raw_data(&mut self, raw: String, packet: &Packet) -> CsiLine {
+        if !raw.contains(']') {
+            // the array continues on the next line
+            self.raw_data = Some(raw);
+            return CsiLine::Consumed;
+        }
+        let mut frame = self.pending.take().unwrap_or_default();
+        let values = raw
+            .trim_start_matches([',', ' ', '['])
+            .split(']')
+            .next()
+            .unwrap_or_default()
+            .split([',', ' '])
+            .map(|x| x.trim())
+            .filter(|x| !x.is_empty())
+            .map(|x| x.parse::<i8>())
+            .collect::<Result<Vec<i8>, _>>();
+        match values {
+            Ok(values) => {
+                frame.data = values;
+                if frame.len == 0 {
+                    frame.len = frame.data.len();
+                }
+                frame.relative_time = packet.relative_time;
+                frame.absolute_time = packet.absolute_time;
+                self.stats.frames += 1;
+                CsiLine::Frame(frame)
+            }
+            Err(err) => {
+                self.drop_malformed(&err.to_string());
+                CsiLine::Consumed
+            }
+        }
+    }
+
+    fn drop_malformed(&mut self, reason: &str) {
+        self.stats.malformed += 1;
+        log::warn!(
+            "dropping malformed CSI frame ({} so far): {reason}",
+            self.stats.malformed
+        );
+    }
+}
+
+/// A complex CSI value of a single subcarrier.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Complex {
+    pub re: f32,
+    pub im: f32,
+}
+
+impl Complex {
+    pub fn new(re: f32, im: f32) -> Self {
+        Self { re, im }
+    }
+
+    pub fn norm(&self) -> f32 {
+        self.re.hypot(self.im)
+    }
+
+    pub fn arg(&self) -> f32 {
+        self.im.atan2(self.re)
+    }
+
+    /// Product of `self` with the complex conjugate of `other`.
+    pub fn mul_conj(&self, other: &Complex) -> Complex {
+        Complex::new(
+            self.re * other.re + self.im * other.im,
+            self.im * other.re - self.re * other.im,
+        )
+    }
+}
+
+impl CsiFrame {
+    /// The ESP32 stores every subcarrier as two `int8`, imaginary part first.
+    pub fn subcarriers(&self) -> Vec<Complex> {
+        let len = if self.len > 0 && self.len <= self.data.len() {
+            self.len
+        } else {
+            self.data.len()
+        };
+        self.data[..len]
+            .chunks_exact(2)
+            .map(|pair| Complex::new(pair[1] as f32, pair[0] as f32))
+            .collect()
+    }
+
+    /// Splits the frame into its LTF segments according to the CSI configuration of the
+    /// device, the length of the frame and the secondary channel.
+    ///
+    /// Each segment only contains the data and pilot subcarriers, sorted by their index.
+    /// Frames that do not match any known layout are returned as a single segment
+    /// with the subcarriers numbered in buffer order.
+    pub fn segments(&self, config: &CsiConfig) -> Vec<CsiSegment> {
+        let subcarriers = self.subcarriers();
+        let Some(layout) = layouts(self.secondary_channel)
+            .into_iter()
+            .map(|layout| {
+                layout
+                    .into_iter()
+                    .filter(|(kind, _)| config.is_enabled(*kind))
+                    .collect::<Vec<_>>()
+            })
+            .find(|layout| {
+                !layout.is_empty()
+                    && layout
+                        .iter()
+                        .map(|(_, indices)| indices.len())
+                        .sum::<usize>()
+                        == subcarriers.len()
+            })
+        else {
+            return vec![CsiSegment {
+                kind: LtfSegment::Unknown,
+                indices: (0..subcarriers.len() as i32).collect(),
+                values: subcarriers,
+            }];
+        };
+
+        let mut offset = 0;
+        layout
+            .into_iter()
+            .map(|(kind, indices)| {
+                let values = &subcarriers[offset..offset + indices.len()];
+                offset += indices.len();
+                let band = Band::of(kind, indices.len(), self.secondary_channel);
+                let mut pairs: Vec<(i32, Complex)> = indices
+                    .into_iter()
+                    .zip(values.iter().copied())
+                    .filter(|(index, _)| band.is_used(*index))
+                    .collect();
+                pairs.sort_by_key(|(index, _)| *index);
+                CsiSegment {
+                    kind,
+                    indices: pairs.iter().map(|(index, _)| *index).collect(),
+                    values: pairs.into_iter().map(|(_, value)| value).collect(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// The long training fields the ESP32 can report CSI for.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LtfSegment {
+    #[default]
+    Lltf,
+    HtLtf,
+    StbcHtLtf,
+    /// The frame did not match any known layout.
+    Unknown,
+}
+
+impl LtfSegment {
+    pub const ALL: [LtfSegment; 3] = [LtfSegment::Lltf, LtfSegment::HtLtf, LtfSegment::StbcHtLtf];
+}
+
+impl fmt::Display for LtfSegment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LtfSegment::Lltf => write!(f, "LLTF"),
+            LtfSegment::HtLtf => write!(f, "HT-LTF"),
+            LtfSegment::StbcHtLtf => write!(f, "STBC-HT-LTF"),
+            LtfSegment::Unknown => write!(f, "Unknown"),
+        }
+    }
+}
+
+/// CSI configuration of the device, as set with `set-csi`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CsiConfig {
+    pub lltf: bool,
+    pub htltf: bool,
+    pub stbc_htltf: bool,
+    /// Merging averages LLTF and HT-LTF but does not change the layout.
+    pub ltf_merge: bool,
+}
+
+impl Default for CsiConfig {
+    fn default() -> Self {
+        Self {
+            lltf: true,
+            htltf: true,
+            stbc_htltf: true,
+            ltf_merge: true,
+        }
+    }
+}
+
+impl CsiConfig {
+    fn is_enabled(&self, kind: LtfSegment) -> bool {
+        match kind {
+            LtfSegment::Lltf => self.lltf,
+            LtfSegment::HtLtf => self.htltf,
+            LtfSegment::StbcHtLtf => self.htltf && self.stbc_htltf,
+            LtfSegment::Unknown => true,
+        }
+    }
+}
+
+/// The subcarriers of one LTF segment, sorted by subcarrier index.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CsiSegment {
+    pub kind: LtfSegment,
+    pub indices: Vec<i32>,
+    pub values: Vec<Complex>,
+}
+
+const SECONDARY_ABOVE: u32 = 1;
+const SECONDARY_BELOW: u32 = 2;
+
+fn range(ranges: &[(i32, i32)]) -> Vec<i32> {
+    ranges.iter().flat_map(|(a, b)| *a..=*b).collect()
+}
+
+/// All buffer layouts the ESP32 produces for the given secondary channel, listed as
+/// segments with their subcarrier indices in buffer order (see the ESP-IDF Wi-Fi guide).
+fn layouts(secondary_channel: u32) -> Vec<Vec<(LtfSegment, Vec<i32>)>> {
+    use LtfSegment::*;
+    let ht20 = range(&[(0, 31), (-32, -1)]);
+    let ht40 = range(&[(0, 63), (-64, -1)]);
+    let stbc40 = range(&[(0, 60), (-60, -1)]);
+    match secondary_channel {
+        SECONDARY_BELOW | SECONDARY_ABOVE => {
+            let (half, stbc20) = if secondary_channel == SECONDARY_BELOW {
+                (range(&[(0, 63)]), range(&[(0, 62)]))
+            } else {
+                (range(&[(-64, -1)]), range(&[(-62, -1)]))
+            };
+            vec![
+                vec![(Lltf, half.clone())],
+                vec![(Lltf, half.clone()), (HtLtf, half.clone())],
+                vec![
+                    (Lltf, half.clone()),
+                    (HtLtf, half.clone()),
+                    (StbcHtLtf, stbc20),
+                ],
+                vec![(Lltf, half.clone()), (HtLtf, ht40.clone())],
+                vec![(Lltf, half), (HtLtf, ht40), (StbcHtLtf, stbc40)],
+            ]
+        }
+        _ => vec![
+            vec![(Lltf, ht20.clone())],
+            vec![(Lltf, ht20.clone()), (HtLtf, ht20.clone())],
+            vec![
+                (Lltf, ht20.clone()),
+                (HtLtf, ht20.clone()),
+                (StbcHtLtf, ht20),
+            ],
+        ],
+    }
+}
+
+/// The occupied subcarriers of a segment, used to drop the guard and null subcarriers.
+struct Band {
+    center: i32,
+    min_offset: i32,
+    max_offset: i32,
+}
+
+impl Band {
+    fn of(kind: LtfSegment, len: usize, secondary_channel: u32) -> Band {
+        // a 20 MHz signal on a 40 MHz channel sits in the primary half
+        let center = match secondary_channel {
+            SECONDARY_BELOW => 32,
+            SECONDARY_ABOVE => -32,
+            _ => 0,
+        };
+        match kind {
+            LtfSegment::Lltf => Band {
+                center,
+                min_offset: 1,
+                max_offset: 26,
+            },
+            _ if len > 64 => Band {
+                center: 0,
+                min_offset: 2,
+                max_offset: 58,
+            },
+            _ => Band {
+                center,
+                min_offset: 1,
+                max_offset: 28,
+            },
+        }
+    }
+
+    fn is_used(&self, index: i32) -> bool {
+        let offset = (index - self.center).abs();
+        offset >= self.min_offset && offset <= self.max_offset
+    }
+}
+
+/// Quantity derived from the CSI frames that is written into the plotted dataset.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CsiQuantity {
+    Raw,
+    #[default]
+    Amplitude,
+    AmplitudeDb,
+    Phase,
+    PhaseUnwrapped,
+    PhaseSanitized,
+}
+
+impl CsiQuantity {
+    pub const ALL: [CsiQuantity; 6] = [
+        CsiQuantity::Raw,
+        CsiQuantity::Amplitude,
+        CsiQuantity::AmplitudeDb,
+        CsiQuantity::Phase,
+        CsiQuantity::PhaseUnwrapped,
+        CsiQuantity::PhaseSanitized,
+    ];
+
+    pub fn compute(
+        &self,
+        indices: &[i32],
+        subcarriers: &[Complex],
+        sanitization: &PhaseSanitization,
+    ) -> Vec<f32> {
+        match self {
+            CsiQuantity::Raw => subcarriers.iter().flat_map(|c| [c.im, c.re]).collect(),
+            CsiQuantity::Amplitude => amplitude(subcarriers),
+            CsiQuantity::AmplitudeDb => amplitude_db(subcarriers),
+            CsiQuantity::Phase => phase(subcarriers),
+            CsiQuantity::PhaseUnwrapped => unwrap_phase(&phase(subcarriers)),
+            CsiQuantity::PhaseSanitized => sanitization.apply(indices, subcarriers),
+        }
+    }
+
+    /// Label of the dataset column of subcarrier `index`.
+    pub fn column_name(&self, index: i32) -> String {
+        match self {
+            CsiQuantity::Raw => format!("Raw {index}"),
+            _ => format!("{self} SC {index}"),
+        }
+    }
+}
+
+impl fmt::Display for CsiQuantity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CsiQuantity::Raw => write!(f, "Raw I/Q"),
+            CsiQuantity::Amplitude => write!(f, "Amplitude"),
+            CsiQuantity::AmplitudeDb => write!(f, "Amplitude [dB]"),
+            CsiQuantity::Phase => write!(f, "Phase [rad]"),
+            CsiQuantity::PhaseUnwrapped => write!(f, "Unwrapped Phase [rad]"),
+            CsiQuantity::PhaseSanitized => write!(f, "Sanitized Phase [rad]"),
+        }
+    }
+}
+
+/// Removes the common phase offset of a frame before the phase is computed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PhaseNormalization {
+    #[default]
+    None,
+    /// Multiplies every subcarrier with the conjugate of the reference subcarrier.
+    ConjugateMultiplication,
+    /// Subtracts the sanitized phase of the reference subcarrier.
+    ReferenceSubcarrier,
+}
+
+impl PhaseNormalization {
+    pub const ALL: [PhaseNormalization; 3] = [
+        PhaseNormalization::None,
+        PhaseNormalization::ConjugateMultiplication,
+        PhaseNormalization::ReferenceSubcarrier,
+    ];
+}
+
+impl fmt::Display for PhaseNormalization {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PhaseNormalization::None => write!(f, "None"),
+            PhaseNormalization::ConjugateMultiplication => write!(f, "Conjugate Multiplication"),
+            PhaseNormalization::ReferenceSubcarrier => write!(f, "Reference Subcarrier"),
+        }
+    }
+}
+
+/// Processing chain turning the raw phase into the sanitized phase.
+///
+/// The raw phase of the ESP32 has a random offset (carrier frequency offset) and a slope
+/// over the subcarriers (symbol timing offset) in every frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PhaseSanitization {
+    pub unwrap: bool,
+    /// Removes the least squares line over the subcarrier index, i.e. both CFO and STO.
+    pub detrend: bool,
+    pub normalization: PhaseNormalization,
+    /// Subcarrier index used by the normalization, the closest subcarrier is used if the
+    /// segment does not contain it.
+    pub reference: i32,
+}
+
+impl Default for PhaseSanitization {
+    fn default() -> Self {
+        Self {
+            unwrap: true,
+            detrend: true,
+            normalization: PhaseNormalization::None,
+            reference: 1,
+        }
+    }
+}
+
+impl PhaseSanitization {
+    pub fn apply(&self, indices: &[i32], subcarriers: &[Complex]) -> Vec<f32> {
+        let reference = indices
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, index)| (*index - self.reference).abs())
+            .map(|(i, _)| i);
+
+        let mut phase = match (self.normalization, reference) {
+            (PhaseNormalization::ConjugateMultiplication, Some(r)) => subcarriers
+                .iter()
+                .map(|c| c.mul_conj(&subcarriers[r]).arg())
+                .collect(),
+            _ => phase(subcarriers),
+        };
+        if self.unwrap {
+            phase = unwrap_phase(&phase);
+        }
+        if self.detrend {
+            phase = detrend(indices, &phase);
+        }
+        if let (PhaseNormalization::ReferenceSubcarrier, Some(r)) = (self.normalization, reference)
+        {
+            let offset = phase[r];
+            phase.iter_mut().for_each(|p| *p -= offset);
+        }
+        phase
+    }
+}
+
+/// Everything needed to turn a [`CsiFrame`] into one row of the dataset.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CsiSettings {
+    pub quantity: CsiQuantity,
+    pub segment: LtfSegment,
+    pub config: CsiConfig,
+    pub sanitization: PhaseSanitization,
+}
+
+impl CsiSettings {
+    /// Column indices and values of the selected quantity and segment, `None` if the frame
+    /// does not contain the selected segment.
+    ///
+    /// The raw values are returned unsegmented and numbered in buffer order.
+    pub fn compute(&self, frame: &CsiFrame) -> Option<(Vec<i32>, Vec<f32>)> {
+        if self.quantity == CsiQuantity::Raw {
+            let subcarriers = frame.subcarriers();
+            let values = self.quantity.compute(&[], &subcarriers, &self.sanitization);
+            return Some(((0..values.len() as i32).collect(), values));
+        }
+        let segments = frame.segments(&self.config);
+        let segment = segments
+            .iter()
+            .find(|s| s.kind == self.segment || s.kind == LtfSegment::Unknown)?;
+        Some((
+            segment.indices.clone(),
+            self.quantity
+                .compute(&segment.indices, &segment.values, &self.sanitization),
+        ))
+    }
+}
+
+pub fn amplitude(subcarriers: &[Complex]) -> Vec<f32> {
+    subcarriers.iter().map(|c| c.norm()).collect()
+}
+
+/// Amplitudes below one LSB of the `int8` values are clamped to 0 dB.
+pub fn amplitude_db(subcarriers: &[Complex]) -> Vec<f32> {
+    subcarriers
+        .iter()
+        .map(|c| 20.0 * c.norm().max(1.0).log10())
+        .collect()
+}
+
+pub fn phase(subcarriers: &[Complex]) -> Vec<f32> {
+    subcarriers.iter().map(|c| c.arg()).collect()
+}
+
+/// Removes the 2π jumps between adjacent subcarriers.
+pub fn unwrap_phase(phase: &[f32]) -> Vec<f32> {
+    let mut unwrapped = Vec::with_capacity(phase.len());
+    let mut offset = 0.0;
+    for (i, p) in phase.iter().enumerate() {
+        if i > 0 {
+            let delta = p - phase[i - 1];
+            if delta > PI {
+                offset -= 2.0 * PI;
+            } else if delta < -PI {
+                offset += 2.0 * PI;
+            }
+        }
+        unwrapped.push(p + offset);
+    }
+    unwrapped
+}
+
+/// Subtracts the least squares line of `values` over `indices`.
+pub fn detrend(indices: &[i32], values: &[f32]) -> Vec<f32> {
+    let n = values.len().min(indices.len());
+    if n < 2 {
+        return values.to_vec();
+    }
+    let mean_x = indices[..n].iter().map(|x| *x as f32).sum::<f32>() / n as f32;
+    let mean_y = values[..n].iter().sum::<f32>() / n as f32;
+    let (mut sxy, mut sxx) = (0.0, 0.0);
+    for (x, y) in indices[..n].iter().zip(values) {
+        let dx = *x as f32 - mean_x;
+        sxy += dx * (y - mean_y);
+        sxx += dx * dx;
+    }
+    let slope = if sxx > 0.0 { sxy / sxx } else { 0.0 };
+    indices[..n]
+        .iter()
+        .zip(values)
+        .map(|(x, y)| y - mean_y - slope * (*x as f32 - mean_x))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn received(payload: &str) -> Packet {
+        Packet {
+            relative_time: 1.0,
+            absolute_time: 2.0,
+            direction: SerialDirection::Receive,
+            payload: payload.to_string(),
+        }
+    }
+
+    fn feed(parser: &mut CsiParser, lines: &[&str]) -> Vec<CsiLine> {
+        lines
+            .iter()
+            .map(|line| parser.feed(&received(line)))
+            .collect()
+    }
+
+    #[test]
+    fn parses_frame_on_one_line() {
+        let mut parser = CsiParser::default();
+        let line = "mac: aa:bb:cc:dd:ee:ff, rssi: -42, rate: 11, channel: 6, len: 4, csi raw data: [1,-2,3,4]";
+        let CsiLine::Frame(frame) = parser.feed(&received(line)) else {
+            panic!("no frame parsed");
+        };
+        assert_eq!(frame.mac, "aa:bb:cc:dd:ee:ff");
+        assert_eq!(frame.rssi, -42);
+        assert_eq!(frame.rate, 11);
+        assert_eq!(frame.channel, 6);
+        assert_eq!(frame.data, vec![1, -2, 3, 4]);
+        assert_eq!(frame.absolute_time, 2.0);
+        assert_eq!(parser.stats.frames, 1);
+    }
+
+    #[test]
+    fn parses_header_and_data_on_separate_lines() {
+        let mut parser = CsiParser::default();
+        let lines = feed(
+            &mut parser,
+            &[
+                "new csi data",
+                "rssi: -40",
+                "rx state: 0",
+                "csi raw data:[1,2,",
+                "3,4]",
+            ],
+        );
+        assert!(lines[..4].iter().all(|line| *line == CsiLine::Consumed));
+        let CsiLine::Frame(frame) = &lines[4] else {
+            panic!("no frame parsed");
+        };
+        assert_eq!(frame.rssi, -40);
+        assert_eq!(frame.data, vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn drops_truncated_raw_data() {
+        let mut parser = CsiParser::default();
+        let lines = feed(
+            &mut parser,
+            &["new csi data", "csi raw data:[1,2,", "wifi: disconnected"],
+        );
+        assert_eq!(lines[2], CsiLine::Ignored);
+        assert_eq!(parser.stats.malformed, 1);
+        // the next frame is not affected
+        let CsiLine::Frame(frame) = parser.feed(&received("csi raw data: [5,6]")) else {
+            panic!("no frame parsed");
+        };
+        assert_eq!(frame.data, vec![5, 6]);
+    }
+
+    #[test]
+    fn non_csi_line_ends_pending_frame() {
+        let mut parser = CsiParser::default();
+        let lines = feed(
+            &mut parser,
+            &[
+                "rate: 11",
+                "new csi data",
+                "rssi: -40",
+                "I (1234) wifi: connected",
+            ],
+        );
+        assert_eq!(lines[0], CsiLine::Ignored);
+        assert_eq!(lines[3], CsiLine::Ignored);
+        // the fields of the stale header do not leak into the next frame
+        let CsiLine::Frame(frame) = parser.feed(&received("csi raw data: [1,2]")) else {
+            panic!("no frame parsed");
+        };
+        assert_eq!(frame.rssi, 0);
+        assert_eq!(frame.rate, 0);
+    }
+}
@@ -1,10 +1,22 @@
 use std::fmt;
+use std::str::FromStr;
 use std::time::{SystemTime, UNIX_EPOCH};
 
-#[derive(Clone, Debug, PartialEq)]
+use serde::{Deserialize, Serialize};
+
+use crate::commands::DeviceConfig;
+use crate::csi::{CsiFrame, CsiStats};
+use crate::motion::MotionStatus;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum SerialDirection {
+    #[serde(rename = "SEND")]
     Send,
+    #[serde(rename = "RECV")]
     Receive,
+    /// Generated by the application itself, e.g. by the motion detection.
+    #[serde(rename = "EVENT")]
+    Event,
 }
 
 impl fmt::Display for SerialDirection {
@@ -12,6 +24,20 @@ impl fmt::Display for SerialDirection {
         match *self {
             SerialDirection::Send => write!(f, "SEND"),
             SerialDirection::Receive => write!(f, "RECV"),
+            SerialDirection::Event => write!(f, "EVENT"),
+        }
+    }
+}
+
+impl FromStr for SerialDirection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "SEND" => Ok(SerialDirection::Send),
+            "RECV" => Ok(SerialDirection::Receive),
+            "EVENT" => Ok(SerialDirection::Event),
+            _ => Err(format!("unknown direction: {s}")),
         }
     }
 }
@@ -23,7 +49,7 @@ pub fn get_epoch_ms() -> u128 {
         .as_millis()
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Packet {
     pub relative_time: f64,
     pub absolute_time: f64,
@@ -42,13 +68,49 @@ impl Default for Packet {
     }
 }
 
+/// Bounds of the in-memory storage, shared by the GUI settings and the main thread.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StorageSettings {
+    /// Number of rows, raw packets and CSI frames kept in memory.
+    pub capacity: usize,
+    /// Write everything older than the capacity to the session file.
+    pub spill: bool,
+}
+
+impl Default for StorageSettings {
+    fn default() -> Self {
+        Self {
+            capacity: 50_000,
+            spill: true,
+        }
+    }
+}
+
+/// Number of elements removed from the front of the buffers of a [`DataContainer`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Evicted {
+    pub rows: usize,
+    pub raw_traffic: usize,
+    pub csi_frames: usize,
+}
+
 #[derive(Clone, Debug)]
 pub struct DataContainer {
     pub time: Vec<f64>,
     pub absolute_time: Vec<f64>,
     pub dataset: Vec<Vec<f32>>,
     pub raw_traffic: Vec<Packet>,
+    pub csi_frames: Vec<CsiFrame>,
+    /// Subcarrier index of every dataset while CSI frames are plotted.
+    pub csi_subcarriers: Vec<i32>,
+    pub motion: MotionStatus,
+    /// Last reply of the device to `show-config`.
+    pub device_config: Option<DeviceConfig>,
+    pub csi_stats: CsiStats,
     pub loaded_from_file: bool,
+    /// Incremented whenever the buffers are modified other than by appending or evicting.
+    pub generation: u64,
+    pub evicted: Evicted,
 }
 
 impl Default for DataContainer {
@@ -58,7 +120,122 @@ impl Default for DataContainer {
             absolute_time: vec![],
             dataset: vec![vec![]],
             raw_traffic: vec![],
+            csi_frames: vec![],
+            csi_subcarriers: vec![],
+            motion: MotionStatus::default(),
+            device_config: None,
+            csi_stats: CsiStats::default(),
             loaded_from_file: false,
+            generation: 0,
+            evicted: Evicted::default(),
         }
     }
 }
+
+impl DataContainer {
+    /// Clears all data, snapshots of the container are replaced on the next sync.
+    pub fn reset(&mut self) {
+        *self = DataContainer {
+            generation: self.generation + 1,
+            ..Default::default()
+        };
+    }
+
+    /// Marks the buffers as modified in place, snapshots are copied in full on the next sync.
+    pub fn invalidate(&mut self) {
+        self.generation += 1;
+    }
+
+    /// Removes the oldest elements of every buffer exceeding `capacity` by more than an
+    /// eighth and returns them. Evicting in chunks keeps the buffers contiguous for the
+    /// plots while moving the remaining elements only once per chunk.
+    pub fn evict(&mut self, capacity: usize) -> Option<DataContainer> {
+        let capacity = capacity.max(1);
+        let excess = |len: usize| {
+            if len > capacity + capacity / 8 {
+                len - capacity
+            } else {
+                0
+            }
+        };
+        let rows = excess(self.time.len());
+        let raw_traffic = excess(self.raw_traffic.len());
+        let csi_frames = excess(self.csi_frames.len());
+        if rows == 0 && raw_traffic == 0 && csi_frames == 0 {
+            return None;
+        }
+        let evicted = DataContainer {
+            time: self.time.drain(..rows).collect(),
+            absolute_time: self
+                .absolute_time
+                .drain(..rows.min(self.absolute_time.len()))
+                .collect(),
+            dataset: self
+                .dataset
+                .iter_mut()
+                .map(|set| set.drain(..rows.min(set.len())).collect())
+                .collect(),
+            raw_traffic: self.raw_traffic.drain(..raw_traffic).collect(),
+            csi_frames: self.csi_frames.drain(..csi_frames).collect(),
+            csi_subcarriers: self.csi_subcarriers.clone(),
+            generation: self.generation,
+            evicted: self.evicted,
+            ..Default::default()
+        };
+        self.evicted.rows += rows;
+        self.evicted.raw_traffic += raw_traffic;
+        self.evicted.csi_frames += csi_frames;
+        Some(evicted)
+    }
+
+    /// Brings `self` up to date with `source` by only copying what has been appended and
+    /// dropping what has been evicted since the last sync. Falls back to a full copy if the
+    /// buffers have been modified otherwise.
+    pub fn sync_from(&mut self, source: &DataContainer) {
+        let incremental = self.generation == source.generation
+            && self.dataset.len() == source.dataset.len()
+            && source.evicted.rows >= self.evicted.rows
+            && source.evicted.raw_traffic >= self.evicted.raw_traffic
+            && source.evicted.csi_frames >= self.evicted.csi_frames;
+        let rows = source.evicted.rows.wrapping_sub(self.evicted.rows);
+        let synced = incremental
+            && sync_buffer(&mut self.time, &source.time, rows)
+            && sync_buffer(&mut self.absolute_time, &source.absolute_time, rows)
+            && self
+                .dataset
+                .iter_mut()
+                .zip(&source.dataset)
+                .all(|(set, source_set)| sync_buffer(set, source_set, rows))
+            && sync_buffer(
+                &mut self.raw_traffic,
+                &source.raw_traffic,
+                source.evicted.raw_traffic - self.evicted.raw_traffic,
+            )
+            && sync_buffer(
+                &mut self.csi_frames,
+                &source.csi_frames,
+                source.evicted.csi_frames - self.evicted.csi_frames,
+            );
+        if !synced {
+            *self = source.clone();
+            return;
+        }
+        self.csi_subcarriers.clone_from(&source.csi_subcarriers);
+        self.motion.clone_from(&source.motion);
+        self.device_config.clone_from(&source.device_config);
+        self.csi_stats.clone_from(&source.csi_stats);
+        self.loaded_from_file = source.loaded_from_file;
+        self.evicted = source.evicted;
+    }
+}
+
+/// Drops `evicted` elements from the front of `target` and appends the elements `source`
+/// has in addition. Returns false if `target` is not a prefix of `source` after evicting.
+fn sync_buffer<T: Clone>(target: &mut Vec<T>, source: &[T], evicted: usize) -> bool {
+    if evicted > target.len() || target.len() - evicted > source.len() {
+        return false;
+    }
+    target.drain(..evicted);
+    target.extend_from_slice(&source[target.len()..]);
+    true
+}
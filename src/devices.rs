@@ -0,0 +1,174 @@
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, RwLock};
+use std::thread;
+
+use eframe::egui;
+
+use crate::csi::CsiSettings;
+use crate::data::{DataContainer, Packet, StorageSettings};
+use crate::framing::FramingStats;
+use crate::io::{FileOptions, RecordOptions};
+use crate::main_thread;
+use crate::motion::MotionSettings;
+use crate::queue::CommandQueue;
+use crate::replay::ReplayControl;
+use crate::serial::{serial_thread, Device};
+
+/// A device opened next to the one of the main view, with its own serial and main thread.
+///
+/// Both threads end once the pane is dropped.
+pub struct DevicePane {
+    /// Device selected in the combo box of the pane.
+    pub device: String,
+    pub device_lock: Arc<RwLock<Device>>,
+    pub connected_lock: Arc<RwLock<bool>>,
+    pub data_lock: Arc<RwLock<DataContainer>>,
+    pub send_tx: Sender<String>,
+    pub clear_tx: Sender<bool>,
+    pub csi_settings_tx: Sender<CsiSettings>,
+    pub storage_settings_tx: Sender<StorageSettings>,
+    /// Snapshot of the data, synced by the GUI.
+    pub data: DataContainer,
+    pub connected: bool,
+    pub console_open: bool,
+    /// Plot the datasets of the device in the time series plots of the main view.
+    pub overlay: bool,
+    pub command: String,
+}
+
+impl DevicePane {
+    pub fn spawn(
+        ctx: &egui::Context,
+        csi_settings: CsiSettings,
+        storage_settings: StorageSettings,
+    ) -> Self {
+        let device_lock = Arc::new(RwLock::new(Device::default()));
+        let devices_lock = Arc::new(RwLock::new(vec![]));
+        let connected_lock = Arc::new(RwLock::new(false));
+        let framing_stats_lock = Arc::new(RwLock::new(FramingStats::default()));
+        let replay_lock = Arc::new(RwLock::new(ReplayControl::default()));
+        let queue_lock = Arc::new(RwLock::new(CommandQueue::default()));
+        let data_lock = Arc::new(RwLock::new(DataContainer::default()));
+        let recording_lock = Arc::new(RwLock::new(None));
+
+        let (send_tx, send_rx): (Sender<String>, Receiver<String>) = mpsc::channel();
+        let (raw_data_tx, raw_data_rx): (Sender<Packet>, Receiver<Packet>) = mpsc::channel();
+        let (sync_tx, sync_rx): (Sender<bool>, Receiver<bool>) = mpsc::channel();
+        let (clear_tx, clear_rx): (Sender<bool>, Receiver<bool>) = mpsc::channel();
+        let (csi_settings_tx, csi_settings_rx): (Sender<CsiSettings>, Receiver<CsiSettings>) =
+            mpsc::channel();
+        let (storage_settings_tx, storage_settings_rx): (
+            Sender<StorageSettings>,
+            Receiver<StorageSettings>,
+        ) = mpsc::channel();
+        // files, motion detection and recording are only available in the main view
+        let (_, save_rx): (Sender<FileOptions>, Receiver<FileOptions>) = mpsc::channel();
+        let (_, load_rx) = mpsc::channel();
+        let (load_names_tx, _) = mpsc::channel();
+        let (_, motion_settings_rx): (Sender<MotionSettings>, Receiver<MotionSettings>) =
+            mpsc::channel();
+        let (_, record_rx): (
+            Sender<Option<RecordOptions>>,
+            Receiver<Option<RecordOptions>>,
+        ) = mpsc::channel();
+
+        if let Err(err) = csi_settings_tx.send(csi_settings) {
+            log::error!("csi_settings_tx thread send failed: {:?}", err);
+        }
+
+        let serial_device_lock = device_lock.clone();
+        let serial_connected_lock = connected_lock.clone();
+        thread::spawn(move || {
+            serial_thread(
+                send_rx,
+                raw_data_tx,
+                serial_device_lock,
+                devices_lock,
+                serial_connected_lock,
+                framing_stats_lock,
+                replay_lock,
+                queue_lock,
+            );
+        });
+
+        let main_data_lock = data_lock.clone();
+        thread::spawn(move || {
+            main_thread(
+                sync_tx,
+                main_data_lock,
+                raw_data_rx,
+                save_rx,
+                load_rx,
+                load_names_tx,
+                clear_rx,
+                csi_settings_rx,
+                motion_settings_rx,
+                storage_settings,
+                storage_settings_rx,
+                record_rx,
+                recording_lock,
+            );
+        });
+
+        let repaint_signal = ctx.clone();
+        thread::spawn(move || {
+            while sync_rx.recv().is_ok() {
+                repaint_signal.request_repaint();
+            }
+        });
+
+        Self {
+            device: "".to_string(),
+            device_lock,
+            connected_lock,
+            data_lock,
+            send_tx,
+            clear_tx,
+            csi_settings_tx,
+            storage_settings_tx,
+            data: DataContainer::default(),
+            connected: false,
+            console_open: true,
+            overlay: true,
+            command: "".to_string(),
+        }
+    }
+
+    /// Copies the state of the threads into the pane, called once per frame.
+    pub fn sync(&mut self) {
+        if let Ok(read_guard) = self.connected_lock.read() {
+            self.connected = *read_guard;
+        }
+        if let Ok(read_guard) = self.data_lock.read() {
+            self.data.sync_from(&read_guard);
+        }
+    }
+
+    pub fn connect(&mut self, device: &Device) {
+        if let Ok(mut write_guard) = self.device_lock.write() {
+            *write_guard = device.clone();
+        }
+        if let Err(err) = self.send_tx.send("__RESET__\r\n".to_string()) {
+            log::error!("send_tx thread send failed: {:?}", err);
+        }
+    }
+
+    pub fn disconnect(&mut self) {
+        if let Ok(mut write_guard) = self.device_lock.write() {
+            write_guard.name.clear();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        if let Err(err) = self.clear_tx.send(true) {
+            log::error!("clear_tx thread send failed: {:?}", err);
+        }
+        self.data = DataContainer::default();
+    }
+}
+
+impl Drop for DevicePane {
+    fn drop(&mut self) {
+        self.disconnect();
+    }
+}
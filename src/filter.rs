@@ -0,0 +1,359 @@
+use std::f64::consts::PI;
+use std::fmt;
+
+/// A single stage of a [`FilterChain`].
+///
+/// Window sizes are given in samples, frequencies in Hz.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Filter {
+    /// Replaces samples further than `threshold` scaled MADs from the window median.
+    Hampel {
+        half_window: usize,
+        threshold: f32,
+    },
+    MovingAverage {
+        window: usize,
+    },
+    MovingMedian {
+        window: usize,
+    },
+    LowPass {
+        order: usize,
+        cutoff: f32,
+    },
+    BandPass {
+        order: usize,
+        low: f32,
+        high: f32,
+    },
+    SavitzkyGolay {
+        half_window: usize,
+        order: usize,
+    },
+}
+
+impl Filter {
+    /// Every filter with its default parameters.
+    pub const ALL: [Filter; 6] = [
+        Filter::Hampel {
+            half_window: 5,
+            threshold: 3.0,
+        },
+        Filter::MovingAverage { window: 5 },
+        Filter::MovingMedian { window: 5 },
+        Filter::LowPass {
+            order: 4,
+            cutoff: 2.0,
+        },
+        Filter::BandPass {
+            order: 2,
+            low: 0.1,
+            high: 0.5,
+        },
+        Filter::SavitzkyGolay {
+            half_window: 5,
+            order: 2,
+        },
+    ];
+
+    /// Applies the filter to `values` sampled at `sample_rate` Hz.
+    pub fn apply(&self, values: &[f32], sample_rate: f64) -> Vec<f32> {
+        match *self {
+            Filter::Hampel {
+                half_window,
+                threshold,
+            } => hampel(values, half_window, threshold),
+            Filter::MovingAverage { window } => moving_average(values, window),
+            Filter::MovingMedian { window } => moving_median(values, window),
+            Filter::LowPass { order, cutoff } => {
+                Biquads::low_pass(order, cutoff as f64, sample_rate).apply(values)
+            }
+            Filter::BandPass { order, low, high } => {
+                Biquads::band_pass(order, low as f64, high as f64, sample_rate).apply(values)
+            }
+            Filter::SavitzkyGolay { half_window, order } => {
+                savitzky_golay(values, half_window, order)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Filter::Hampel { .. } => write!(f, "Hampel"),
+            Filter::MovingAverage { .. } => write!(f, "Moving Average"),
+            Filter::MovingMedian { .. } => write!(f, "Moving Median"),
+            Filter::LowPass { .. } => write!(f, "Butterworth Low-Pass"),
+            Filter::BandPass { .. } => write!(f, "Butterworth Band-Pass"),
+            Filter::SavitzkyGolay { .. } => write!(f, "Savitzky-Golay"),
+        }
+    }
+}
+
+pub type FilterChain = Vec<Filter>;
+
+/// Which series are plotted for datasets with a filter chain.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FilterDisplay {
+    Raw,
+    Filtered,
+    #[default]
+    Both,
+}
+
+impl fmt::Display for FilterDisplay {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FilterDisplay::Raw => write!(f, "Raw"),
+            FilterDisplay::Filtered => write!(f, "Filtered"),
+            FilterDisplay::Both => write!(f, "Raw and Filtered"),
+        }
+    }
+}
+
+/// Runs all filters of the chain in order.
+pub fn apply_chain(chain: &[Filter], values: &[f32], sample_rate: f64) -> Vec<f32> {
+    chain.iter().fold(values.to_vec(), |values, filter| {
+        filter.apply(&values, sample_rate)
+    })
+}
+
+/// Mean sample rate in Hz of the timestamps given in ms.
+pub fn sample_rate(time: &[f64]) -> f64 {
+    match (time.first(), time.last()) {
+        (Some(first), Some(last)) if last > first => {
+            (time.len() - 1) as f64 / (last - first) * 1000.0
+        }
+        _ => 0.0,
+    }
+}
+
+fn median(values: &mut [f32]) -> f32 {
+    values.sort_by(|a, b| a.total_cmp(b));
+    let mid = values.len() / 2;
+    match values.len() % 2 {
+        0 => (values[mid - 1] + values[mid]) / 2.0,
+        _ => values[mid],
+    }
+}
+
+/// Centered window of `window` samples around `i`, shrunk at the borders.
+fn centered(len: usize, i: usize, window: usize) -> std::ops::Range<usize> {
+    let half = window.max(1) / 2;
+    i.saturating_sub(half)..(i + window.max(1) - half).min(len)
+}
+
+pub fn moving_average(values: &[f32], window: usize) -> Vec<f32> {
+    (0..values.len())
+        .map(|i| {
+            let range = centered(values.len(), i, window);
+            let n = range.len() as f32;
+            values[range].iter().sum::<f32>() / n
+        })
+        .collect()
+}
+
+pub fn moving_median(values: &[f32], window: usize) -> Vec<f32> {
+    (0..values.len())
+        .map(|i| median(&mut values[centered(values.len(), i, window)].to_vec()))
+        .collect()
+}
+
+pub fn hampel(values: &[f32], half_window: usize, threshold: f32) -> Vec<f32> {
+    // scales the median absolute deviation to the standard deviation of normal data
+    const MAD_SCALE: f32 = 1.4826;
+    (0..values.len())
+        .map(|i| {
+            let mut window = values[centered(values.len(), i, 2 * half_window + 1)].to_vec();
+            let med = median(&mut window);
+            let mut deviations: Vec<f32> = window.iter().map(|v| (v - med).abs()).collect();
+            let sigma = MAD_SCALE * median(&mut deviations);
+            if (values[i] - med).abs() > threshold * sigma {
+                med
+            } else {
+                values[i]
+            }
+        })
+        .collect()
+}
+
+/// Least squares polynomial fit of `order` over the window, evaluated at the center and,
+/// at the borders, at the position of the sample in the first or last full window.
+pub fn savitzky_golay(values: &[f32], half_window: usize, order: usize) -> Vec<f32> {
+    let window = 2 * half_window + 1;
+    if values.len() < window || order >= window {
+        return values.to_vec();
+    }
+    let center = savitzky_golay_weights(half_window, order, 0);
+    (0..values.len())
+        .map(|i| {
+            let (start, weights) = if i < half_window {
+                (
+                    0,
+                    savitzky_golay_weights(half_window, order, i as i64 - half_window as i64),
+                )
+            } else if i + half_window >= values.len() {
+                let start = values.len() - window;
+                (
+                    start,
+                    savitzky_golay_weights(
+                        half_window,
+                        order,
+                        (i - start) as i64 - half_window as i64,
+                    ),
+                )
+            } else {
+                (i - half_window, center.clone())
+            };
+            values[start..start + window]
+                .iter()
+                .zip(weights)
+                .map(|(v, w)| v * w as f32)
+                .sum()
+        })
+        .collect()
+}
+
+/// Convolution weights evaluating the fitted polynomial at `at` in `-half..=half`.
+fn savitzky_golay_weights(half: usize, order: usize, at: i64) -> Vec<f64> {
+    let positions: Vec<f64> = (-(half as i64)..=half as i64).map(|x| x as f64).collect();
+    let n = order + 1;
+    // normal equations J^T J c = e(at), the weights are then J c
+    let mut matrix = vec![vec![0.0; n + 1]; n];
+    for (row, line) in matrix.iter_mut().enumerate() {
+        for (col, value) in line.iter_mut().take(n).enumerate() {
+            *value = positions.iter().map(|x| x.powi((row + col) as i32)).sum();
+        }
+        line[n] = (at as f64).powi(row as i32);
+    }
+    let coefficients = solve(matrix);
+    positions
+        .iter()
+        .map(|x| {
+            coefficients
+                .iter()
+                .enumerate()
+                .map(|(k, c)| c * x.powi(k as i32))
+                .sum()
+        })
+        .collect()
+}
+
+/// Gauss-Jordan elimination with partial pivoting of an augmented matrix.
+fn solve(mut matrix: Vec<Vec<f64>>) -> Vec<f64> {
+    let n = matrix.len();
+    for col in 0..n {
+        let pivot = (col..n)
+            .max_by(|a, b| matrix[*a][col].abs().total_cmp(&matrix[*b][col].abs()))
+            .unwrap_or(col);
+        matrix.swap(col, pivot);
+        let p = matrix[col][col];
+        if p.abs() < f64::EPSILON {
+            continue;
+        }
+        for value in matrix[col].iter_mut() {
+            *value /= p;
+        }
+        let pivot_row = matrix[col].clone();
+        for (row, line) in matrix.iter_mut().enumerate() {
+            if row != col {
+                let factor = line[col];
+                for (value, p) in line.iter_mut().zip(&pivot_row).skip(col) {
+                    *value -= factor * p;
+                }
+            }
+        }
+    }
+    matrix.iter().map(|row| row[n]).collect()
+}
+
+/// Second order section in transposed direct form II.
+#[derive(Clone, Copy, Debug)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+}
+
+/// Cascade of second order sections designed with the bilinear transform.
+#[derive(Clone, Debug, Default)]
+pub struct Biquads {
+    sections: Vec<Biquad>,
+}
+
+impl Biquads {
+    fn butterworth(order: usize, cutoff: f64, sample_rate: f64, high_pass: bool) -> Self {
+        let order = order.clamp(1, 8);
+        if sample_rate <= 0.0 || cutoff <= 0.0 {
+            return Self::default();
+        }
+        let k = (PI * cutoff.min(0.49 * sample_rate) / sample_rate).tan();
+        let mut sections: Vec<Biquad> = (0..order / 2)
+            .map(|i| {
+                let q = 1.0 / (2.0 * (PI * (2 * i + 1) as f64 / (2 * order) as f64).cos());
+                let norm = 1.0 / (1.0 + k / q + k * k);
+                let b = if high_pass {
+                    [norm, -2.0 * norm, norm]
+                } else {
+                    [k * k * norm, 2.0 * k * k * norm, k * k * norm]
+                };
+                Biquad {
+                    b,
+                    a: [2.0 * (k * k - 1.0) * norm, (1.0 - k / q + k * k) * norm],
+                }
+            })
+            .collect();
+        if order % 2 == 1 {
+            let norm = 1.0 / (1.0 + k);
+            let b = if high_pass {
+                [norm, -norm, 0.0]
+            } else {
+                [k * norm, k * norm, 0.0]
+            };
+            sections.push(Biquad {
+                b,
+                a: [(k - 1.0) * norm, 0.0],
+            });
+        }
+        Self { sections }
+    }
+
+    pub fn low_pass(order: usize, cutoff: f64, sample_rate: f64) -> Self {
+        Self::butterworth(order, cutoff, sample_rate, false)
+    }
+
+    pub fn high_pass(order: usize, cutoff: f64, sample_rate: f64) -> Self {
+        Self::butterworth(order, cutoff, sample_rate, true)
+    }
+
+    /// High-pass at `low` followed by a low-pass at `high`, each of the given order.
+    pub fn band_pass(order: usize, low: f64, high: f64, sample_rate: f64) -> Self {
+        let mut filter = Self::high_pass(order, low, sample_rate);
+        filter
+            .sections
+            .extend(Self::low_pass(order, high, sample_rate).sections);
+        filter
+    }
+
+    /// Filters `values`, starting in the steady state of the first sample to avoid a
+    /// transient at the beginning.
+    pub fn apply(&self, values: &[f32]) -> Vec<f32> {
+        let mut output: Vec<f64> = values.iter().map(|v| *v as f64).collect();
+        for Biquad { b, a } in self.sections.iter() {
+            let Some(first) = output.first().copied() else {
+                break;
+            };
+            let gain = (b[0] + b[1] + b[2]) / (1.0 + a[0] + a[1]);
+            let steady = gain * first;
+            let mut z2 = b[2] * first - a[1] * steady;
+            let mut z1 = b[1] * first - a[0] * steady + z2;
+            for x in output.iter_mut() {
+                let y = b[0] * *x + z1;
+                z1 = b[1] * *x - a[0] * y + z2;
+                z2 = b[2] * *x - a[1] * y;
+                *x = y;
+            }
+        }
+        output.into_iter().map(|v| v as f32).collect()
+    }
+}
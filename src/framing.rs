@@ -0,0 +1,418 @@
+use std::fmt;
+use std::path::Path;
+
+use defmt_decoder::{Frame, Locations, Table};
+use serde::{Deserialize, Serialize};
+
+use crate::csi::CsiFrame;
+
+/// How the byte stream of a device is split into messages.
+///
+/// In the binary modes every frame carries one message followed by the CRC-32 (IEEE,
+/// little-endian) of the message. The first byte of a message is its type:
+///
+/// * `0x01` CSI frame: `u16` sequence number, `[u8; 6]` MAC, `i8` RSSI, `u8` rate,
+///   `u8` channel, `u8` secondary channel, `i8` noise floor, `u32` timestamp [µs],
+///   `u16` length and `length` × `i8` CSI data, all little-endian.
+/// * `0x02` text: a UTF-8 line, e.g. the response to a command.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Framing {
+    /// Lines terminated by `\r\n` or `\0\0`.
+    #[default]
+    Text,
+    /// Consistent overhead byte stuffing, frames are terminated by `0x00`.
+    Cobs,
+    /// Serial line internet protocol (RFC 1055), frames are terminated by `0xC0`.
+    Slip,
+    /// defmt log frames, decoded with the table of the ELF selected for the device.
+    Defmt,
+}
+
+impl Framing {
+    pub const ALL: [Framing; 4] = [Framing::Text, Framing::Cobs, Framing::Slip, Framing::Defmt];
+}
+
+impl fmt::Display for Framing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Framing::Text => write!(f, "Text"),
+            Framing::Cobs => write!(f, "COBS + CRC"),
+            Framing::Slip => write!(f, "SLIP + CRC"),
+            Framing::Defmt => write!(f, "defmt"),
+        }
+    }
+}
+
+const MESSAGE_CSI: u8 = 0x01;
+const MESSAGE_TEXT: u8 = 0x02;
+const CSI_HEADER_LEN: usize = 1 + 2 + 6 + 5 + 4 + 2;
+
+const SLIP_END: u8 = 0xC0;
+const SLIP_ESC: u8 = 0xDB;
+const SLIP_ESC_END: u8 = 0xDC;
+const SLIP_ESC_ESC: u8 = 0xDD;
+
+// frames are dropped if no delimiter arrives within this many bytes
+const MAX_FRAME_LEN: usize = 16 * 1024;
+
+/// Statistics of the binary framing, shared with the GUI.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FramingStats {
+    pub frames: u64,
+    pub crc_failures: u64,
+    /// Frames that could not be unstuffed or have an unknown layout.
+    pub malformed: u64,
+    /// Frames missing according to the sequence numbers of the CSI frames.
+    pub dropped: u64,
+}
+
+impl fmt::Display for FramingStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} frames, {} CRC failures, {} dropped, {} malformed",
+            self.frames, self.crc_failures, self.dropped, self.malformed
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Message {
+    Csi(CsiFrame),
+    Text(String),
+}
+
+/// Splits the received bytes into frames and decodes their messages.
+#[derive(Clone, Debug, Default)]
+pub struct FrameDecoder {
+    framing: Framing,
+    buffer: Vec<u8>,
+    last_sequence: Option<u16>,
+    pub stats: FramingStats,
+}
+
+impl FrameDecoder {
+    pub fn new(framing: Framing) -> Self {
+        Self {
+            framing,
+            ..Default::default()
+        }
+    }
+
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<Message> {
+        let delimiter = match self.framing {
+            Framing::Text | Framing::Defmt => return vec![],
+            Framing::Cobs => 0x00,
+            Framing::Slip => SLIP_END,
+        };
+        let mut messages = vec![];
+        for byte in bytes {
+            if *byte != delimiter {
+                self.buffer.push(*byte);
+                if self.buffer.len() > MAX_FRAME_LEN {
+                    self.buffer.clear();
+                    self.stats.malformed += 1;
+                }
+                continue;
+            }
+            // SLIP senders may start frames with END to flush line noise
+            if self.buffer.is_empty() {
+                continue;
+            }
+            let frame = std::mem::take(&mut self.buffer);
+            let decoded = match self.framing {
+                Framing::Cobs => cobs_decode(&frame),
+                _ => slip_decode(&frame),
+            };
+            match decoded {
+                Some(payload) => messages.extend(self.decode_payload(&payload)),
+                None => self.stats.malformed += 1,
+            }
+        }
+        messages
+    }
+
+    fn decode_payload(&mut self, payload: &[u8]) -> Option<Message> {
+        let Some((message, crc)) = payload.split_last_chunk::<4>() else {
+            self.stats.malformed += 1;
+            return None;
+        };
+        if crc32fast::hash(message) != u32::from_le_bytes(*crc) {
+            self.stats.crc_failures += 1;
+            return None;
+        }
+        let decoded = match message.first() {
+            Some(&MESSAGE_CSI) => self.decode_csi(message).map(Message::Csi),
+            Some(&MESSAGE_TEXT) => Some(Message::Text(
+                String::from_utf8_lossy(&message[1..])
+                    .trim_end()
+                    .to_string(),
+            )),
+            _ => None,
+        };
+        match decoded {
+            Some(_) => self.stats.frames += 1,
+            None => self.stats.malformed += 1,
+        }
+        decoded
+    }
+
+    fn decode_csi(&mut self, message: &[u8]) -> Option<CsiFrame> {
+        if message.len() < CSI_HEADER_LEN {
+            return None;
+        }
+        let u16_at = |i: usize| u16::from_le_bytes([message[i], message[i + 1]]);
+        let sequence = u16_at(1);
+        let mac = message[3..9]
+            .iter()
+            .map(|b| format!("{b:02X}"))
+            .collect::<Vec<_>>()
+            .join(":");
+        let len = u16_at(CSI_HEADER_LEN - 2) as usize;
+        let data = message.get(CSI_HEADER_LEN..CSI_HEADER_LEN + len)?;
+
+        if let Some(last) = self.last_sequence {
+            let missing = sequence.wrapping_sub(last).wrapping_sub(1);
+            // a jump backwards, e.g. after a restart of the sender, is a resync and no loss
+            if missing < u16::MAX / 2 {
+                self.stats.dropped += missing as u64;
+            }
+        }
+        self.last_sequence = Some(sequence);
+
+        Some(CsiFrame {
+            mac,
+            rssi: message[9] as i8 as i32,
+            rate: message[10] as u32,
+            channel: message[11] as u32,
+            secondary_channel: message[12] as u32,
+            noise_floor: message[13] as i8 as i32,
+            timestamp: u32::from_le_bytes([message[14], message[15], message[16], message[17]])
+                as u64,
+            len,
+            data: data.iter().map(|b| *b as i8).collect(),
+            sequence: Some(sequence as u32),
+            ..Default::default()
+        })
+    }
+}
+
+fn cobs_decode(frame: &[u8]) -> Option<Vec<u8>> {
+    let mut output = Vec::with_capacity(frame.len());
+    let mut i = 0;
+    while i < frame.len() {
+        let code = frame[i] as usize;
+        if code == 0 || i + code > frame.len() {
+            return None;
+        }
+        output.extend_from_slice(&frame[i + 1..i + code]);
+        i += code;
+        // a code of 0xFF marks a full block without a following zero
+        if code < 0xFF && i < frame.len() {
+            output.push(0);
+        }
+    }
+    Some(output)
+}
+
+fn slip_decode(frame: &[u8]) -> Option<Vec<u8>> {
+    let mut output = Vec::with_capacity(frame.len());
+    let mut bytes = frame.iter();
+    while let Some(byte) = bytes.next() {
+        match *byte {
+            SLIP_ESC => match bytes.next() {
+                Some(&SLIP_ESC_END) => output.push(SLIP_END),
+                Some(&SLIP_ESC_ESC) => output.push(SLIP_ESC),
+                _ => return None,
+            },
+            b => output.push(b),
+        }
+    }
+    Some(output)
+}
+
+/// The defmt table of an ELF and the source locations of its log statements.
+pub struct DefmtTable {
+    pub table: Table,
+    pub locations: Locations,
+}
+
+impl DefmtTable {
+    pub fn load(elf_path: &Path) -> Result<Self, String> {
+        let elf = std::fs::read(elf_path)
+            .map_err(|e| format!("failed to read {}: {}", elf_path.display(), e))?;
+        let table = Table::parse(&elf)
+            .map_err(|e| format!("failed to parse defmt table: {}", e))?
+            .ok_or_else(|| format!("{} contains no defmt data", elf_path.display()))?;
+        // the locations are only available if the ELF contains debug info
+        let locations = table.get_locations(&elf).unwrap_or_else(|e| {
+            log::warn!("No defmt locations available: {}", e);
+            Locations::new()
+        });
+        Ok(Self { table, locations })
+    }
+
+    /// One console line with timestamp, level, message and source location.
+    pub fn format(&self, frame: &Frame) -> String {
+        let mut line = String::new();
+        if let Some(timestamp) = frame.display_timestamp() {
+            line.push_str(&format!("{timestamp} "));
+        }
+        if let Some(level) = frame.level() {
+            line.push_str(&format!("{:<5} ", level.as_str().to_uppercase()));
+        }
+        line.push_str(&frame.display_message().to_string());
+        if let Some(location) = self.locations.get(&frame.index()) {
+            line.push_str(&format!(" ({}:{})", location.file.display(), location.line));
+        }
+        line
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cobs_encode(payload: &[u8]) -> Vec<u8> {
+        let mut output = vec![0];
+        let mut code_idx = 0;
+        for byte in payload {
+            if *byte == 0 {
+                output[code_idx] = (output.len() - code_idx) as u8;
+                code_idx = output.len();
+                output.push(0);
+            } else {
+                output.push(*byte);
+                if output.len() - code_idx == 0xFF {
+                    output[code_idx] = 0xFF;
+                    code_idx = output.len();
+                    output.push(0);
+                }
+            }
+        }
+        output[code_idx] = (output.len() - code_idx) as u8;
+        output.push(0x00);
+        output
+    }
+
+    fn slip_encode(payload: &[u8]) -> Vec<u8> {
+        let mut output = vec![SLIP_END];
+        for byte in payload {
+            match *byte {
+                SLIP_END => output.extend([SLIP_ESC, SLIP_ESC_END]),
+                SLIP_ESC => output.extend([SLIP_ESC, SLIP_ESC_ESC]),
+                b => output.push(b),
+            }
+        }
+        output.push(SLIP_END);
+        output
+    }
+
+    fn with_crc(message: &[u8]) -> Vec<u8> {
+        let mut payload = message.to_vec();
+        payload.extend(crc32fast::hash(message).to_le_bytes());
+        payload
+    }
+
+    fn csi_message(sequence: u16, data: &[i8]) -> Vec<u8> {
+        let mut message = vec![MESSAGE_CSI];
+        message.extend(sequence.to_le_bytes());
+        message.extend([0xAA, 0xBB, 0xCC, 0x00, 0xC0, 0xDB]);
+        message.extend([(-40i8) as u8, 11, 6, 0, (-90i8) as u8]);
+        message.extend(1234u32.to_le_bytes());
+        message.extend((data.len() as u16).to_le_bytes());
+        message.extend(data.iter().map(|v| *v as u8));
+        message
+    }
+
+    fn text_message(text: &str) -> Vec<u8> {
+        let mut message = vec![MESSAGE_TEXT];
+        message.extend(text.as_bytes());
+        message
+    }
+
+    #[test]
+    fn cobs_round_trip() {
+        let mut decoder = FrameDecoder::new(Framing::Cobs);
+        let mut bytes = cobs_encode(&with_crc(&csi_message(7, &[0, -1, 2, 0])));
+        bytes.extend(cobs_encode(&with_crc(&text_message("ok"))));
+        let messages = decoder.push(&bytes);
+        let [Message::Csi(frame), Message::Text(text)] = messages.as_slice() else {
+            panic!("unexpected messages: {messages:?}");
+        };
+        assert_eq!(frame.sequence, Some(7));
+        assert_eq!(frame.mac, "AA:BB:CC:00:C0:DB");
+        assert_eq!(frame.rssi, -40);
+        assert_eq!(frame.timestamp, 1234);
+        assert_eq!(frame.data, vec![0, -1, 2, 0]);
+        assert_eq!(text, "ok");
+        assert_eq!(decoder.stats.frames, 2);
+    }
+
+    #[test]
+    fn cobs_round_trip_of_long_message() {
+        let data: Vec<i8> = (0..300).map(|i| (i % 7) as i8 - 3).collect();
+        let mut decoder = FrameDecoder::new(Framing::Cobs);
+        let messages = decoder.push(&cobs_encode(&with_crc(&csi_message(1, &data))));
+        let [Message::Csi(frame)] = messages.as_slice() else {
+            panic!("unexpected messages: {messages:?}");
+        };
+        assert_eq!(frame.data, data);
+    }
+
+    #[test]
+    fn slip_round_trip() {
+        let mut decoder = FrameDecoder::new(Framing::Slip);
+        let bytes = slip_encode(&with_crc(&csi_message(3, &[-64, 0x5B, 1, 2])));
+        // the frame arrives in pieces
+        let (first, second) = bytes.split_at(5);
+        assert!(decoder.push(first).is_empty());
+        let messages = decoder.push(second);
+        let [Message::Csi(frame)] = messages.as_slice() else {
+            panic!("unexpected messages: {messages:?}");
+        };
+        assert_eq!(frame.mac, "AA:BB:CC:00:C0:DB");
+        assert_eq!(frame.data, vec![-64, 0x5B, 1, 2]);
+    }
+
+    #[test]
+    fn counts_crc_failures() {
+        let mut decoder = FrameDecoder::new(Framing::Cobs);
+        let mut payload = with_crc(&text_message("ok"));
+        payload[1] ^= 0x01;
+        assert!(decoder.push(&cobs_encode(&payload)).is_empty());
+        assert_eq!(decoder.stats.crc_failures, 1);
+        assert_eq!(decoder.stats.frames, 0);
+    }
+
+    #[test]
+    fn counts_malformed_frames() {
+        let mut decoder = FrameDecoder::new(Framing::Slip);
+        // an invalid escape, a payload shorter than the CRC and an unknown message type
+        let mut bytes = vec![SLIP_END, SLIP_ESC, 0x01, SLIP_END];
+        bytes.extend(slip_encode(&[1, 2]));
+        bytes.extend(slip_encode(&with_crc(&[0x7F, 1, 2])));
+        assert!(decoder.push(&bytes).is_empty());
+        assert_eq!(decoder.stats.malformed, 3);
+    }
+
+    #[test]
+    fn counts_dropped_frames() {
+        let mut decoder = FrameDecoder::new(Framing::Cobs);
+        for sequence in [u16::MAX - 1, u16::MAX, 2, 3] {
+            decoder.push(&cobs_encode(&with_crc(&csi_message(sequence, &[1, 2]))));
+        }
+        // 0 and 1 are missing after the wrap around
+        assert_eq!(decoder.stats.dropped, 2);
+    }
+
+    #[test]
+    fn backwards_sequence_is_resync() {
+        let mut decoder = FrameDecoder::new(Framing::Cobs);
+        for sequence in [1000, 1001, 0, 1] {
+            decoder.push(&cobs_encode(&with_crc(&csi_message(sequence, &[1, 2]))));
+        }
+        assert_eq!(decoder.stats.dropped, 0);
+        assert_eq!(decoder.stats.frames, 4);
+    }
+}
@@ -0,0 +1,160 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::csi::CsiFrame;
+
+/// How the CSI frames of different receivers are matched to one transmission.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AlignMode {
+    /// Same sequence number of the transmitter, received within the tolerance.
+    #[default]
+    Sequence,
+    /// Nearest absolute time within the tolerance.
+    Time,
+}
+
+impl AlignMode {
+    pub const ALL: [AlignMode; 2] = [AlignMode::Sequence, AlignMode::Time];
+}
+
+impl fmt::Display for AlignMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AlignMode::Sequence => write!(f, "Sequence number"),
+            AlignMode::Time => write!(f, "Absolute time"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FusionSettings {
+    pub mode: AlignMode,
+    /// Largest difference of the absolute times of matched frames in ms.
+    pub tolerance: f64,
+}
+
+impl Default for FusionSettings {
+    fn default() -> Self {
+        Self {
+            mode: AlignMode::Sequence,
+            tolerance: 50.0,
+        }
+    }
+}
+
+/// The frames of one transmission, one entry per receiver.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FusedRecord {
+    /// Absolute time of the first received frame in ms.
+    pub absolute_time: f64,
+    pub sequence: Option<u32>,
+    pub frames: Vec<Option<CsiFrame>>,
+}
+
+/// Packet loss and clock offset of one receiver.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ReceiverStats {
+    pub frames: usize,
+    /// Records without a frame of this receiver.
+    pub lost: usize,
+    /// Median offset of the board clock (CSI timestamp) to the one of the first receiver in ms.
+    pub clock_offset: Option<f64>,
+    /// Median delay of the arrival at the host after the first receiver in ms.
+    pub arrival_delay: Option<f64>,
+    /// Clock offset over the absolute time of the records, to show the drift.
+    pub offsets: Vec<[f64; 2]>,
+}
+
+impl ReceiverStats {
+    pub fn loss(&self, records: usize) -> f64 {
+        if records == 0 {
+            0.0
+        } else {
+            self.lost as f64 / records as f64
+        }
+    }
+}
+
+fn median(mut values: Vec<f64>) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(|a, b| a.total_cmp(b));
+    Some(values[values.len() / 2])
+}
+
+/// Matches the CSI frames of several receivers into fused records, sorted by time.
+pub fn align(receivers: &[&[CsiFrame]], settings: &FusionSettings) -> Vec<FusedRecord> {
+    let mut frames: Vec<(usize, &CsiFrame)> = receivers
+        .iter()
+        .enumerate()
+        .flat_map(|(r, frames)| frames.iter().map(move |frame| (r, frame)))
+        .collect();
+    frames.sort_by(|a, b| a.1.absolute_time.total_cmp(&b.1.absolute_time));
+
+    let mut records: Vec<FusedRecord> = vec![];
+    // records that can still be matched, they are closed once they are older than the tolerance
+    let mut open = 0;
+    for (r, frame) in frames {
+        while open < records.len()
+            && frame.absolute_time - records[open].absolute_time > settings.tolerance
+        {
+            open += 1;
+        }
+        let candidate = records[open..]
+            .iter()
+            .enumerate()
+            .filter(|(_, record)| record.frames[r].is_none())
+            .filter(|(_, record)| match settings.mode {
+                AlignMode::Sequence => {
+                    frame.sequence.is_some() && record.sequence == frame.sequence
+                }
+                AlignMode::Time => true,
+            })
+            .min_by(|(_, a), (_, b)| {
+                (frame.absolute_time - a.absolute_time)
+                    .total_cmp(&(frame.absolute_time - b.absolute_time))
+            })
+            .map(|(i, _)| open + i);
+        match candidate {
+            Some(i) => records[i].frames[r] = Some(frame.clone()),
+            None => {
+                let mut record = FusedRecord {
+                    absolute_time: frame.absolute_time,
+                    sequence: frame.sequence,
+                    frames: vec![None; receivers.len()],
+                };
+                record.frames[r] = Some(frame.clone());
+                records.push(record);
+            }
+        }
+    }
+    records
+}
+
+/// Packet loss and clock offsets of the receivers, relative to the first receiver.
+pub fn receiver_stats(records: &[FusedRecord], receivers: usize) -> Vec<ReceiverStats> {
+    (0..receivers)
+        .map(|r| {
+            let mut stats = ReceiverStats::default();
+            let mut delays = vec![];
+            for record in records {
+                let Some(frame) = &record.frames[r] else {
+                    stats.lost += 1;
+                    continue;
+                };
+                stats.frames += 1;
+                if let Some(Some(reference)) = record.frames.first() {
+                    // the CSI timestamp of the ESP32 is in µs
+                    let offset = (frame.timestamp as f64 - reference.timestamp as f64) / 1000.0;
+                    stats.offsets.push([record.absolute_time, offset]);
+                    delays.push(frame.absolute_time - reference.absolute_time);
+                }
+            }
+            stats.clock_offset = median(stats.offsets.iter().map(|p| p[1]).collect());
+            stats.arrival_delay = median(delays);
+            stats
+        })
+        .collect()
+}
@@ -4,27 +4,57 @@ use std::ops::RangeInclusive;
 use std::path::PathBuf;
 use std::sync::mpsc::{Receiver, Sender};
 use std::sync::{Arc, RwLock};
-use std::time::Duration;
+use std::thread;
+use std::time::{Duration, Instant};
 
+use crate::breathing::{BreathingEstimator, BreathingSettings, RateMethod, SubcarrierRanking};
 use crate::color_picker::{color_picker_widget, color_picker_window, COLORS};
+use crate::commands::{
+    redact, CsiFlags, DeviceConfig, EspCommand, NetworkArchitecture, TrafficType, WifiMode,
+    WifiOption,
+};
+use crate::csi::{CsiConfig, CsiFrame, CsiQuantity, CsiSettings, LtfSegment, PhaseNormalization};
 use crate::custom_highlighter::highlight_impl;
-use crate::data::{DataContainer, SerialDirection};
+use crate::data::{DataContainer, Evicted, SerialDirection, StorageSettings};
+use crate::devices::DevicePane;
+use crate::filter::{apply_chain, sample_rate, Filter, FilterChain, FilterDisplay};
+use crate::framing::{Framing, FramingStats};
+use crate::fusion::{align, receiver_stats, AlignMode, FusionSettings, ReceiverStats};
+use crate::heatmap::{heatmap_image, Colormap, HeatmapPlacement, HeatmapSettings};
+use crate::io::{
+    save_devices_to_csv, save_fused_to_csv, DeviceExport, RecordOptions, RecordSettings, Rotation,
+    TrafficFormat,
+};
+use crate::motion::MotionSettings;
+use crate::network::{network_device, network_endpoint, NetworkProtocol};
+use crate::profiles::{
+    export_profiles, import_profiles, load_profiles, save_profiles, DeviceProfile, DeviceProfiles,
+};
+use crate::queue::{CommandQueue, CommandStatus};
+use crate::replay::{ReplayControl, REPLAY_DEVICE};
+use crate::secrets::{load_secrets, save_secrets, EncryptedSecrets, MIN_PASSPHRASE_LEN};
 use crate::serial::{clear_serial_settings, save_serial_settings, Device, SerialDevices};
 use crate::settings_window::settings_window;
+use crate::spectrum::{spectrogram, spectrum, SpectrumSettings, Window};
 use crate::toggle::toggle;
+use crate::transport::is_available;
 #[cfg(feature = "self_update")]
 use crate::update::check_update;
 use crate::FileOptions;
 use crate::{APP_INFO, PREFERENCES_KEY};
 use eframe::egui::panel::Side;
 use eframe::egui::{
-    Align2, CollapsingHeader, Color32, FontFamily, FontId, KeyboardShortcut, Pos2, Sense, Ui, Vec2,
+    vec2, Align2, CollapsingHeader, Color32, FontFamily, FontId, KeyboardShortcut, Pos2, Sense,
+    TextureHandle, TextureOptions, Ui, Vec2,
 };
 use eframe::{egui, Storage};
 use egui::ThemePreference;
 use egui_file_dialog::information_panel::InformationPanel;
 use egui_file_dialog::FileDialog;
-use egui_plot::{log_grid_spacer, GridMark, Legend, Line, Plot, PlotPoint, PlotPoints};
+use egui_plot::{
+    log_grid_spacer, GridMark, Legend, Line, LineStyle, Plot, PlotBounds, PlotImage, PlotPoint,
+    PlotPoints,
+};
 use preferences::Preferences;
 #[cfg(feature = "self_update")]
 use self_update::update::Release;
@@ -52,7 +82,14 @@ const CLEAR_PLOT_SHORTCUT: KeyboardShortcut =
 #[derive(Clone)]
 pub enum FileDialogState {
     Open,
+    OpenElf,
+    OpenReplay,
+    Record,
     Save,
+    SaveDevices,
+    SaveFused,
+    ImportProfiles,
+    ExportProfiles,
     SavePlot,
     None,
 }
@@ -74,6 +111,14 @@ pub struct GuiSettingsContainer {
     pub save_absolute_time: bool,
     pub dark_mode: bool,
     pub theme_preference: ThemePreference,
+    #[serde(default)]
+    pub storage: StorageSettings,
+    #[serde(default)]
+    pub record: RecordSettings,
+    #[serde(default)]
+    pub traffic_format: TrafficFormat,
+    #[serde(default)]
+    pub fusion: FusionSettings,
 }
 
 impl Default for GuiSettingsContainer {
@@ -87,6 +132,10 @@ impl Default for GuiSettingsContainer {
             save_absolute_time: false,
             dark_mode: true,
             theme_preference: ThemePreference::System,
+            storage: StorageSettings::default(),
+            record: RecordSettings::default(),
+            traffic_format: TrafficFormat::default(),
+            fusion: FusionSettings::default(),
         }
     }
 }
@@ -102,6 +151,40 @@ pub fn load_gui_settings() -> GuiSettingsContainer {
     })
 }
 
+/// What a single plot of the stacked plots shows.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PlotMode {
+    TimeSeries,
+    /// The given quantity of the latest CSI frames over the subcarrier index.
+    Snapshot(CsiQuantity),
+    /// Mean amplitude spectrum of the selected datasets.
+    Spectrum,
+    /// Short-time Fourier transform of the selected datasets over time.
+    Spectrogram,
+}
+
+impl std::fmt::Display for PlotMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            PlotMode::TimeSeries => write!(f, "Time Series"),
+            PlotMode::Snapshot(quantity) => write!(f, "Snapshot: {quantity}"),
+            PlotMode::Spectrum => write!(f, "FFT Spectrum"),
+            PlotMode::Spectrogram => write!(f, "Doppler Spectrogram"),
+        }
+    }
+}
+
+/// Everything the filtered series depend on, they are only filtered again if it changes.
+#[derive(Clone, Debug, PartialEq)]
+struct FilterCacheKey {
+    generation: u64,
+    evicted: Evicted,
+    rows: usize,
+    window: usize,
+    chains: Vec<FilterChain>,
+    display: FilterDisplay,
+}
+
 pub enum ColorWindow {
     NoShow,
     ColorIndex(usize),
@@ -112,6 +195,12 @@ pub struct MyApp {
     command: String,
     device: String,
     old_device: String,
+    network_protocol: NetworkProtocol,
+    network_address: String,
+    device_panes: Vec<DevicePane>,
+    fusion_stats: Vec<ReceiverStats>,
+    fusion_records: usize,
+    fusion_updated: Option<Instant>,
     device_idx: usize,
     serial_devices: SerialDevices,
     plotting_range: usize,
@@ -129,12 +218,38 @@ pub struct MyApp {
     device_lock: Arc<RwLock<Device>>,
     devices_lock: Arc<RwLock<Vec<String>>>,
     connected_lock: Arc<RwLock<bool>>,
+    framing_stats_lock: Arc<RwLock<FramingStats>>,
+    recording_lock: Arc<RwLock<Option<PathBuf>>>,
+    recording: Option<PathBuf>,
+    replay_lock: Arc<RwLock<ReplayControl>>,
+    queue_lock: Arc<RwLock<CommandQueue>>,
+    /// Snapshot of the queue of the serial thread.
+    command_queue: CommandQueue,
     data_lock: Arc<RwLock<DataContainer>>,
     save_tx: Sender<FileOptions>,
     load_tx: Sender<PathBuf>,
     load_names_rx: Receiver<Vec<String>>,
     send_tx: Sender<String>,
     clear_tx: Sender<bool>,
+    csi_settings_tx: Sender<CsiSettings>,
+    csi_settings: CsiSettings,
+    motion_settings_tx: Sender<MotionSettings>,
+    motion_settings: MotionSettings,
+    storage_settings_tx: Sender<StorageSettings>,
+    record_tx: Sender<Option<RecordOptions>>,
+    plot_modes: Vec<PlotMode>,
+    snapshot_frames: usize,
+    heatmap: HeatmapSettings,
+    heatmap_texture: Option<TextureHandle>,
+    spectrum: SpectrumSettings,
+    spectrogram_textures: Vec<Option<TextureHandle>>,
+    filter_chains: Vec<FilterChain>,
+    breathing_settings: BreathingSettings,
+    breathing: BreathingEstimator,
+    filter_target: Option<usize>,
+    filter_display: FilterDisplay,
+    filter_cache_key: Option<FilterCacheKey>,
+    filtered: Arc<Vec<Option<Vec<PlotPoint>>>>,
     history: Vec<String>,
     index: usize,
     eol: String,
@@ -148,32 +263,38 @@ pub struct MyApp {
     show_warning_window: WindowFeedback,
     do_not_show_clear_warning: bool,
     init: bool,
-    //new
     //set-traffic
     pub traffic_enable: bool,
-    pub traffic_type: usize,
+    pub traffic_type: TrafficType,
     pub traffic_interval: String,
-    // Add these for the new section:
     //set-network
-    pub network_architecture: usize,
+    pub network_architecture: NetworkArchitecture,
     //set-wifi
-    pub wifi_mode: usize,
+    pub wifi_mode: WifiMode,
     pub wifi_max_connections: String,
     pub wifi_hide_ssid: bool,
     pub wifi_ap_ssid: String,
     pub wifi_ap_password: String,
     pub wifi_sta_ssid: String,
     pub wifi_sta_password: String,
+    show_passwords: bool,
     //set-csi
-    pub csi_disable_lltf: bool,
-    pub csi_disable_htltf: bool,
-    pub csi_disable_stbc_htltf: bool,
-    pub csi_disable_ltf_merge: bool,
+    pub csi_flags: CsiFlags,
     //start button checkboxes
-    pub enable_set_traffic: bool,
-    pub enable_set_network: bool,
-    pub enable_set_csi: bool,
     pub enable_set_wifi: bool,
+    command_error: Option<String>,
+    /// Configuration of the device that was last applied to the settings above.
+    device_config: Option<DeviceConfig>,
+    /// Commands were sent after the configuration was reported.
+    device_config_outdated: bool,
+    profiles: DeviceProfiles,
+    /// Profile selected in the side panel.
+    profile_idx: Option<usize>,
+    profile_name: String,
+    /// Passphrase of the encrypted store of the Wi-Fi passwords of the profiles.
+    passphrase: String,
+    secrets_unlocked: bool,
+    secrets_error: Option<String>,
 
     #[cfg(feature = "self_update")]
     new_release: Option<Release>,
@@ -188,12 +309,20 @@ impl MyApp {
         devices_lock: Arc<RwLock<Vec<String>>>,
         devices: SerialDevices,
         connected_lock: Arc<RwLock<bool>>,
+        framing_stats_lock: Arc<RwLock<FramingStats>>,
+        recording_lock: Arc<RwLock<Option<PathBuf>>>,
+        replay_lock: Arc<RwLock<ReplayControl>>,
+        queue_lock: Arc<RwLock<CommandQueue>>,
         gui_conf: GuiSettingsContainer,
         save_tx: Sender<FileOptions>,
         load_tx: Sender<PathBuf>,
         load_names_rx: Receiver<Vec<String>>,
         send_tx: Sender<String>,
         clear_tx: Sender<bool>,
+        csi_settings_tx: Sender<CsiSettings>,
+        motion_settings_tx: Sender<MotionSettings>,
+        storage_settings_tx: Sender<StorageSettings>,
+        record_tx: Sender<Option<RecordOptions>>,
     ) -> Self {
         let mut file_dialog = FileDialog::default()
             //.initial_directory(PathBuf::from("/path/to/app"))
@@ -217,6 +346,18 @@ impl MyApp {
             .add_file_filter(
                 "CSV files",
                 Arc::new(|p| p.extension().unwrap_or_default().to_ascii_lowercase() == "csv"),
+            )
+            .add_file_filter(
+                "Traffic logs",
+                Arc::new(|p| {
+                    matches!(
+                        p.extension()
+                            .unwrap_or_default()
+                            .to_ascii_lowercase()
+                            .to_str(),
+                        Some("csv" | "ndjson" | "jsonl")
+                    )
+                }),
             );
         // Load the persistent data of the file dialog.
         // Alternatively, you can also use the `FileDialog::storage` builder method.
@@ -229,33 +370,43 @@ impl MyApp {
             //new
             //set-traffic
             traffic_enable: false,
-            traffic_type: 0,
+            traffic_type: TrafficType::default(),
             traffic_interval: String::new(),
             //set-network
-            network_architecture: 3,
+            network_architecture: NetworkArchitecture::default(),
             //set-wifi
-            wifi_mode: 2, // default to "sniffer"
+            wifi_mode: WifiMode::default(),
             wifi_max_connections: "1".to_string(),
             wifi_hide_ssid: false,
             wifi_ap_ssid: String::new(),
             wifi_ap_password: String::new(),
             wifi_sta_ssid: String::new(),
             wifi_sta_password: String::new(),
+            show_passwords: false,
             //set-csi
-            csi_disable_lltf: false,
-            csi_disable_htltf: false,
-            csi_disable_stbc_htltf: false,
-            csi_disable_ltf_merge: false,
+            csi_flags: CsiFlags::default(),
             //start button checkboxes
-            enable_set_traffic: false,
-            enable_set_network: false,
-            enable_set_csi: false,
             enable_set_wifi: false,
+            command_error: None,
+            device_config: None,
+            device_config_outdated: false,
+            profiles: load_profiles(),
+            profile_idx: None,
+            profile_name: String::new(),
+            passphrase: String::new(),
+            secrets_unlocked: false,
+            secrets_error: None,
             //old
             connected_to_device: false,
             picked_path: PathBuf::new(),
             device: "".to_string(),
             old_device: "".to_string(),
+            network_protocol: NetworkProtocol::default(),
+            network_address: "".to_string(),
+            device_panes: vec![],
+            fusion_stats: vec![],
+            fusion_records: 0,
+            fusion_updated: None,
             data: DataContainer::default(),
             file_dialog_state: FileDialogState::None,
             file_dialog,
@@ -270,6 +421,12 @@ impl MyApp {
                 }
             }),
             connected_lock,
+            framing_stats_lock,
+            recording_lock,
+            recording: None,
+            replay_lock,
+            queue_lock,
+            command_queue: CommandQueue::default(),
             device_lock,
             devices_lock,
             device_idx: 0,
@@ -281,6 +438,25 @@ impl MyApp {
             load_names_rx,
             send_tx,
             clear_tx,
+            csi_settings_tx,
+            csi_settings: CsiSettings::default(),
+            motion_settings_tx,
+            motion_settings: MotionSettings::default(),
+            storage_settings_tx,
+            record_tx,
+            plot_modes: vec![PlotMode::TimeSeries; 10],
+            snapshot_frames: 1,
+            heatmap: HeatmapSettings::default(),
+            heatmap_texture: None,
+            spectrum: SpectrumSettings::default(),
+            spectrogram_textures: vec![None; 10],
+            filter_chains: vec![],
+            breathing_settings: BreathingSettings::default(),
+            breathing: BreathingEstimator::default(),
+            filter_target: None,
+            filter_display: FilterDisplay::default(),
+            filter_cache_key: None,
+            filtered: Arc::new(vec![]),
             plotting_range: usize::MAX,
             plot_serial_display_ratio: 0.45,
             command: "".to_string(),
@@ -351,6 +527,12 @@ impl MyApp {
                 packet.payload
             )),
             (false, false, SerialDirection::Receive) => Some(packet.payload.clone() + "\n"),
+            (false, _, SerialDirection::Event) => Some(format!(
+                "[{}] t + {:.3}s: {}\n",
+                packet.direction,
+                packet.relative_time as f32 / 1000.0,
+                packet.payload
+            )),
             (_, _, _) => None,
         }
     }
@@ -365,13 +547,19 @@ impl MyApp {
             let panel_height = ui.available_size().y;
             let mut plot_height: f32 = 0.0;
 
-            if self.serial_devices.number_of_plots[self.device_idx] > 0 {
+            let show_heatmap = self.heatmap.placement != HeatmapPlacement::Hidden;
+            let number_of_line_plots = if self.heatmap.placement == HeatmapPlacement::ReplacePlots {
+                0
+            } else {
+                self.serial_devices.number_of_plots[self.device_idx]
+            };
+            let number_of_panels = number_of_line_plots + show_heatmap as usize;
+
+            if number_of_panels > 0 {
                 let height = ui.available_size().y * self.plot_serial_display_ratio;
                 plot_height = height;
                 // need to subtract 12.0, this seems to be the height of the separator of two adjacent plots
-                plot_height = plot_height
-                    / (self.serial_devices.number_of_plots[self.device_idx] as f32)
-                    - 12.0;
+                plot_height = plot_height / (number_of_panels as f32) - 12.0;
             }
 
             let mut plot_ui_heigh: f32 = 0.0;
@@ -381,7 +569,7 @@ impl MyApp {
                 ui.add_space(left_border);
                 ui.vertical(|ui| {
                     if let Ok(read_guard) = self.data_lock.read() {
-                        self.data = read_guard.clone();
+                        self.data.sync_from(&read_guard);
                     }
 
                     if self.data.loaded_from_file && self.file_opened {
@@ -395,10 +583,15 @@ impl MyApp {
                             self.color_vals = (0..max(self.labels.len(), 1)).map(|_| 0.0).collect();
                         }
                     }
-                    if self.serial_devices.number_of_plots[self.device_idx] > 0 {
+                    if number_of_panels > 0 {
                         if self.data.dataset.len() != self.labels.len() && !self.file_opened {
                             self.labels = (0..max(self.data.dataset.len(), 1))
-                                .map(|i| format!("Column {i}"))
+                                .map(|i| match self.data.csi_subcarriers.get(i) {
+                                    Some(index) if !self.data.csi_frames.is_empty() => {
+                                        self.csi_settings.quantity.column_name(*index)
+                                    }
+                                    _ => format!("Column {i}"),
+                                })
                                 .collect();
                             self.colors = (0..max(self.data.dataset.len(), 1))
                                 .map(|i| COLORS[i % COLORS.len()])
@@ -423,17 +616,78 @@ impl MyApp {
                             }
                         }
 
+                        // the filters only run on the plotted window and never touch the raw data
+                        let filter_key = FilterCacheKey {
+                            generation: self.data.generation,
+                            evicted: self.data.evicted,
+                            rows: self.data.time.len(),
+                            window,
+                            chains: self.filter_chains.clone(),
+                            display: self.filter_display,
+                        };
+                        if self.filter_cache_key.as_ref() != Some(&filter_key) {
+                            let rate = sample_rate(&self.data.time[window..]);
+                            self.filtered = Arc::new(
+                                graphs
+                                    .iter()
+                                    .enumerate()
+                                    .map(|(i, graph)| {
+                                        let chain = self.filter_chains.get(i)?;
+                                        if chain.is_empty()
+                                            || self.filter_display == FilterDisplay::Raw
+                                        {
+                                            return None;
+                                        }
+                                        let values: Vec<f32> =
+                                            graph.iter().map(|p| p.y as f32).collect();
+                                        Some(
+                                            apply_chain(chain, &values, rate)
+                                                .into_iter()
+                                                .zip(graph)
+                                                .map(|(y, p)| PlotPoint::new(p.x, y))
+                                                .collect(),
+                                        )
+                                    })
+                                    .collect(),
+                            );
+                            self.filter_cache_key = Some(filter_key);
+                        }
+                        let filtered = self.filtered.clone();
+
+                        let overlays = self.overlay_graphs();
+
                         let t_fmt = |x: GridMark, _range: &RangeInclusive<f64>| {
                             format!("{:4.2} s", x.value)
                         };
 
                         let plots_ui = ui.vertical(|ui| {
-                            for graph_idx in 0..self.serial_devices.number_of_plots[self.device_idx]
-                            {
+                            for graph_idx in 0..number_of_line_plots {
                                 if graph_idx != 0 {
                                     ui.separator();
                                 }
 
+                                match self.plot_modes[graph_idx] {
+                                    PlotMode::TimeSeries => {}
+                                    PlotMode::Snapshot(quantity) => {
+                                        self.draw_snapshot_plot(
+                                            ui,
+                                            graph_idx,
+                                            quantity,
+                                            plot_height,
+                                            width,
+                                        );
+                                        continue;
+                                    }
+                                    PlotMode::Spectrum => {
+                                        self.draw_spectrum_plot(ui, graph_idx, plot_height, width);
+                                        continue;
+                                    }
+                                    PlotMode::Spectrogram => {
+                                        self.draw_spectrogram(ui, graph_idx, plot_height, width);
+                                        continue;
+                                    }
+                                }
+
                                 let signal_plot = Plot::new(format!("data-{graph_idx}"))
                                     .height(plot_height)
                                     .width(width)
@@ -446,17 +700,52 @@ impl MyApp {
                                     for (i, graph) in graphs.iter().enumerate() {
                                         // this check needs to be here for when we change devices (not very elegant)
                                         if i < self.labels.len() {
-                                            signal_plot_ui.line(
-                                                Line::new(PlotPoints::Owned(graph.to_vec()))
-                                                    .name(&self.labels[i])
-                                                    .color(self.colors[i]),
-                                            );
+                                            let filtered = filtered.get(i).and_then(|f| f.as_ref());
+                                            if filtered.is_none()
+                                                || self.filter_display == FilterDisplay::Both
+                                            {
+                                                let color = if filtered.is_some() {
+                                                    self.colors[i].gamma_multiply(0.4)
+                                                } else {
+                                                    self.colors[i]
+                                                };
+                                                signal_plot_ui.line(
+                                                    Line::new(PlotPoints::Owned(graph.to_vec()))
+                                                        .name(&self.labels[i])
+                                                        .color(color),
+                                                );
+                                            }
+                                            if let Some(filtered) = filtered {
+                                                signal_plot_ui.line(
+                                                    Line::new(PlotPoints::Owned(filtered.to_vec()))
+                                                        .name(format!(
+                                                            "{} (filtered)",
+                                                            self.labels[i]
+                                                        ))
+                                                        .color(self.colors[i]),
+                                                );
+                                            }
                                         }
                                     }
+                                    // additional devices are dashed
+                                    for (i, (name, points)) in overlays.iter().enumerate() {
+                                        signal_plot_ui.line(
+                                            Line::new(PlotPoints::Owned(points.to_vec()))
+                                                .name(name)
+                                                .color(COLORS[i % COLORS.len()])
+                                                .style(LineStyle::dashed_loose()),
+                                        );
+                                    }
                                 });
 
                                 self.plot_location = Some(plot_inner.response.rect);
                             }
+                            if show_heatmap {
+                                if number_of_line_plots > 0 {
+                                    ui.separator();
+                                }
+                                self.draw_heatmap(ui, window, plot_height, width);
+                            }
                             let separator_response = ui.separator();
                             let separator = ui
                                 .interact(
@@ -577,173 +866,1605 @@ impl MyApp {
         });
     }
 
-    fn draw_serial_settings(&mut self, ctx: &egui::Context, ui: &mut Ui) {
-        ui.horizontal(|ui| {
-            ui.heading("Serial Monitor");
-            self.paint_connection_indicator(ui);
+    fn draw_snapshot_plot(
+        &mut self,
+        ui: &mut Ui,
+        graph_idx: usize,
+        quantity: CsiQuantity,
+        plot_height: f32,
+        width: f32,
+    ) {
+        let frames = &self.data.csi_frames;
+        let number_of_frames = self.snapshot_frames.clamp(1, frames.len().max(1));
+        let base_color = self.colors.first().copied().unwrap_or(COLORS[0]);
+
+        let snapshot_plot = Plot::new(format!("snapshot-{graph_idx}"))
+            .height(plot_height)
+            .width(width)
+            .legend(Legend::default())
+            .x_axis_label("Subcarrier")
+            .y_axis_label(quantity.to_string());
+
+        let settings = CsiSettings {
+            quantity,
+            ..self.csi_settings
+        };
+
+        let plot_inner = snapshot_plot.show(ui, |snapshot_plot_ui| {
+            // draw the oldest frame first, such that the latest one is on top
+            for (age, frame) in frames.iter().rev().take(number_of_frames).enumerate().rev() {
+                let Some((indices, values)) = settings.compute(frame) else {
+                    continue;
+                };
+                let points: Vec<PlotPoint> = indices
+                    .into_iter()
+                    .zip(values)
+                    .map(|(x, y)| PlotPoint::new(x as f64, y as f64))
+                    .collect();
+                let opacity = 1.0 - age as f32 / number_of_frames as f32;
+                let line =
+                    Line::new(PlotPoints::Owned(points)).color(base_color.gamma_multiply(opacity));
+                if age == 0 {
+                    snapshot_plot_ui.line(line.name(format!("{quantity} (latest frame)")));
+                } else {
+                    snapshot_plot_ui.line(line);
+                }
+            }
         });
+        self.plot_location = Some(plot_inner.response.rect);
+    }
 
-        let devices: Vec<String> = if let Ok(read_guard) = self.devices_lock.read() {
-            read_guard.clone()
-        } else {
-            vec![]
-        };
+    fn draw_spectrum_plot(&mut self, ui: &mut Ui, graph_idx: usize, plot_height: f32, width: f32) {
+        let points = spectrum(&self.data.time, &self.data.dataset, &self.spectrum);
+        let color = self.colors.first().copied().unwrap_or(COLORS[0]);
+
+        let spectrum_plot = Plot::new(format!("spectrum-{graph_idx}"))
+            .height(plot_height)
+            .width(width)
+            .legend(Legend::default())
+            .x_axis_label("Frequency [Hz]")
+            .y_axis_label("Amplitude");
+
+        let plot_inner = spectrum_plot.show(ui, |spectrum_plot_ui| {
+            spectrum_plot_ui.line(
+                Line::new(PlotPoints::from(points))
+                    .name(format!("{} Spectrum", self.spectrum.window))
+                    .color(color),
+            );
+        });
+        self.plot_location = Some(plot_inner.response.rect);
+    }
 
-        if !devices.contains(&self.device) {
-            self.device.clear();
-        }
-        if let Ok(dev) = self.device_lock.read() {
-            if !dev.name.is_empty() {
-                self.device = dev.name.clone();
+    fn draw_spectrogram(&mut self, ui: &mut Ui, graph_idx: usize, plot_height: f32, width: f32) {
+        let Some(spectrogram) = spectrogram(&self.data.time, &self.data.dataset, &self.spectrum)
+        else {
+            ui.allocate_space(vec2(width, plot_height));
+            return;
+        };
+        let settings = HeatmapSettings {
+            colormap: self.heatmap.colormap,
+            ..HeatmapSettings::default()
+        };
+        let (image, min, max) = heatmap_image(&spectrogram.bins, 0, &settings);
+        let columns = spectrogram.bins.first().map_or(0, |bin| bin.len()) as f64;
+        let rows = spectrogram.bins.len() as f64;
+        match &mut self.spectrogram_textures[graph_idx] {
+            Some(texture) => texture.set(image, TextureOptions::NEAREST),
+            None => {
+                self.spectrogram_textures[graph_idx] = Some(ui.ctx().load_texture(
+                    format!("spectrogram-{graph_idx}"),
+                    image,
+                    TextureOptions::NEAREST,
+                ))
             }
         }
-        ui.add_space(10.0);
-        ui.horizontal(|ui| {
-            ui.label("Device");
-            ui.add_space(130.0);
-            ui.label("Baud");
+        let Some(texture) = &self.spectrogram_textures[graph_idx] else {
+            return;
+        };
+
+        let duration = columns * spectrogram.time_step;
+        let t_start = spectrogram.start - spectrogram.time_step / 2.0;
+        let bandwidth = rows * spectrogram.frequency_step;
+        let f_start = -spectrogram.frequency_step / 2.0;
+
+        let t_fmt = |x: GridMark, _range: &RangeInclusive<f64>| format!("{:4.2} s", x.value);
+        let spectrogram_plot = Plot::new(format!("spectrogram-{graph_idx}"))
+            .height(plot_height)
+            .width(width)
+            .x_axis_formatter(t_fmt)
+            .y_axis_label("Frequency [Hz]")
+            .label_formatter(move |_name, point| {
+                format!(
+                    "t = {:.3} s\nf = {:.3} Hz\nscale: {:.1} ... {:.1} dB",
+                    point.x, point.y, min, max
+                )
+            });
+
+        let follow = self.heatmap.follow;
+        let plot_inner = spectrogram_plot.show(ui, |spectrogram_plot_ui| {
+            spectrogram_plot_ui.image(PlotImage::new(
+                texture.id(),
+                PlotPoint::new(t_start + duration / 2.0, f_start + bandwidth / 2.0),
+                vec2(duration as f32, bandwidth as f32),
+            ));
+            if follow {
+                spectrogram_plot_ui.set_plot_bounds(PlotBounds::from_min_max(
+                    [t_start, f_start],
+                    [t_start + duration, f_start + bandwidth],
+                ));
+            }
         });
+        self.plot_location = Some(plot_inner.response.rect);
+    }
 
-        let old_name = self.device.clone();
-        ui.horizontal(|ui| {
-            if self.file_opened {
-                ui.disable();
+    fn draw_heatmap(&mut self, ui: &mut Ui, window: usize, plot_height: f32, width: f32) {
+        let (image, min, max) = heatmap_image(&self.data.dataset, window, &self.heatmap);
+        match &mut self.heatmap_texture {
+            Some(texture) => texture.set(image, TextureOptions::NEAREST),
+            None => {
+                self.heatmap_texture = Some(ui.ctx().load_texture(
+                    "heatmap",
+                    image,
+                    TextureOptions::NEAREST,
+                ))
             }
-            let dev_text = self.device.replace("/dev/tty.", "");
-            ui.horizontal(|ui| {
-                if self.connected_to_device {
-                    ui.disable();
-                }
-                let _response = egui::ComboBox::from_id_salt("Device")
-                    .selected_text(dev_text)
-                    .width(RIGHT_PANEL_WIDTH * 0.92 - 155.0)
-                    .show_ui(ui, |ui| {
-                        devices
-                            .into_iter()
-                            // on macOS each device appears as /dev/tty.* and /dev/cu.*
-                            // we only display the /dev/tty.* here
-                            .filter(|dev| !dev.contains("/dev/cu."))
-                            .for_each(|dev| {
-                                // this makes the names shorter in the UI on UNIX and UNIX-like platforms
-                                let dev_text = dev.replace("/dev/tty.", "");
-                                ui.selectable_value(&mut self.device, dev, dev_text);
-                            });
-                    })
-                    .response;
-                // let selected_new_device = response.changed();  //somehow this does not work
-                // if selected_new_device {
-                if old_name != self.device {
-                    if !self.data.time.is_empty() {
-                        self.show_warning_window = WindowFeedback::Waiting;
-                        self.old_device = old_name;
-                    } else {
-                        self.show_warning_window = WindowFeedback::Clear;
-                    }
+        }
+        let Some(texture) = &self.heatmap_texture else {
+            return;
+        };
+
+        let t_start = self.data.time.get(window).copied().unwrap_or_default() / 1000.0;
+        let t_end = self.data.time.last().copied().unwrap_or_default() / 1000.0;
+        let duration = (t_end - t_start).max(1e-3);
+        let rows = self.data.dataset.len() as f64;
+
+        let t_fmt = |x: GridMark, _range: &RangeInclusive<f64>| format!("{:4.2} s", x.value);
+        let labels = &self.labels;
+        let heatmap_plot = Plot::new("heatmap")
+            .height(plot_height)
+            .width(width)
+            .x_axis_formatter(t_fmt)
+            .label_formatter(move |_name, point| {
+                let row = point.y.floor();
+                if row < 0.0 || row as usize >= labels.len() {
+                    return "".to_string();
                 }
+                format!(
+                    "{}\nt = {:.3} s\nscale: {:.2} ... {:.2}",
+                    labels[row as usize], point.x, min, max
+                )
             });
-            match self.show_warning_window {
-                WindowFeedback::None => {}
-                WindowFeedback::Waiting => {
-                    self.show_warning_window = self.clear_warning_window(ctx);
-                }
-                WindowFeedback::Clear => {
-                    // new device selected, check in previously used devices
-                    let mut device_is_already_saved = false;
-                    for (idx, dev) in self.serial_devices.devices.iter().enumerate() {
-                        if dev.name == self.device {
-                            // this is the device!
-                            self.device = dev.name.clone();
-                            self.device_idx = idx;
-                            self.init = true;
-                            device_is_already_saved = true;
-                        }
-                    }
-                    if !device_is_already_saved {
-                        // create new device in the archive
-                        let mut device = Device::default();
-                        device.name = self.device.clone();
-                        self.serial_devices.devices.push(device);
-                        self.serial_devices.number_of_plots.push(1);
-                        self.serial_devices.number_of_highlights.push(1);
-                        self.serial_devices
-                            .highlight_labels
-                            .push(vec!["".to_string()]);
-                        self.serial_devices
-                            .labels
-                            .push(vec!["Column 0".to_string()]);
-                        self.device_idx = self.serial_devices.devices.len() - 1;
-                        save_serial_settings(&self.serial_devices);
+
+        let follow = self.heatmap.follow;
+        let plot_inner = heatmap_plot.show(ui, |heatmap_plot_ui| {
+            heatmap_plot_ui.image(PlotImage::new(
+                texture.id(),
+                PlotPoint::new(t_start + duration / 2.0, rows / 2.0),
+                vec2(duration as f32, rows as f32),
+            ));
+            if follow {
+                heatmap_plot_ui.set_plot_bounds(PlotBounds::from_min_max(
+                    [t_start, 0.0],
+                    [t_start + duration, rows],
+                ));
+            }
+        });
+        self.plot_location = Some(plot_inner.response.rect);
+    }
+
+    fn device_list(&self) -> Vec<String> {
+        let mut devices: Vec<String> = if let Ok(read_guard) = self.devices_lock.read() {
+            read_guard.clone()
+        } else {
+            vec![]
+        };
+        // network sources are not enumerated, the ones used before are listed
+        devices.extend(
+            self.serial_devices
+                .devices
+                .iter()
+                .filter(|dev| network_endpoint(&dev.name).is_some())
+                .map(|dev| dev.name.clone()),
+        );
+        devices
+    }
+
+    /// Labels of an additional device, the saved ones are used if they fit the datasets.
+    fn device_labels(&self, name: &str, data: &DataContainer) -> Vec<String> {
+        let saved = self
+            .serial_devices
+            .devices
+            .iter()
+            .position(|dev| dev.name == name)
+            .and_then(|idx| self.serial_devices.labels.get(idx))
+            .filter(|labels| labels.len() == data.dataset.len() && data.csi_frames.is_empty());
+        match saved {
+            Some(labels) => labels.clone(),
+            None => (0..data.dataset.len())
+                .map(|i| match data.csi_subcarriers.get(i) {
+                    Some(index) if !data.csi_frames.is_empty() => {
+                        self.csi_settings.quantity.column_name(*index)
                     }
-                    self.clear_tx
-                        .send(true)
-                        .expect("failed to send clear after choosing new device");
-                    // need to clear the data here such that we don't get errors in the gui (plot)
-                    self.data = DataContainer::default();
-                    self.show_warning_window = WindowFeedback::None;
-                }
-                WindowFeedback::Cancel => {
-                    self.device = self.old_device.clone();
-                    self.show_warning_window = WindowFeedback::None;
+                    _ => format!("Column {i}"),
+                })
+                .collect(),
+        }
+    }
+
+    /// Datasets of the additional devices on the time axis of the main view.
+    fn overlay_graphs(&self) -> Vec<(String, Vec<PlotPoint>)> {
+        let panes: Vec<&DevicePane> = self
+            .device_panes
+            .iter()
+            .filter(|pane| pane.overlay && !pane.data.absolute_time.is_empty())
+            .collect();
+        // absolute time at which the relative time of the main view is zero
+        let offset = match (self.data.absolute_time.first(), self.data.time.first()) {
+            (Some(absolute_time), Some(time)) => absolute_time - time,
+            _ => panes
+                .iter()
+                .filter_map(|pane| pane.data.absolute_time.first())
+                .fold(f64::INFINITY, |offset, time| offset.min(*time)),
+        };
+        let mut graphs = vec![];
+        for pane in panes {
+            let labels = self.device_labels(&pane.device, &pane.data);
+            let times = &pane.data.absolute_time;
+            let window = times.len().saturating_sub(self.plotting_range);
+            for (label, set) in labels.iter().zip(&pane.data.dataset) {
+                if set.len() != times.len() {
+                    continue;
                 }
+                let points = times[window..]
+                    .iter()
+                    .zip(&set[window..])
+                    .map(|(time, y)| PlotPoint::new((time - offset) / 1000.0, *y as f64))
+                    .collect();
+                graphs.push((format!("{}: {}", pane.device, label), points));
             }
-            egui::ComboBox::from_id_salt("Baud Rate")
-                .selected_text(format!(
-                    "{}",
-                    self.serial_devices.devices[self.device_idx].baud_rate
-                ))
-                .width(80.0)
-                .show_ui(ui, |ui| {
-                    if self.connected_to_device {
-                        ui.disable();
-                    }
-                    BAUD_RATES.iter().for_each(|baud_rate| {
-                        ui.selectable_value(
-                            &mut self.serial_devices.devices[self.device_idx].baud_rate,
-                            *baud_rate,
-                            baud_rate.to_string(),
-                        );
-                    });
+        }
+        graphs
+    }
+
+    fn draw_device_panes(&mut self, ctx: &egui::Context, ui: &mut Ui) {
+        let devices = self.device_list();
+        let mut close = None;
+        for (i, pane) in self.device_panes.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                ui.add_enabled_ui(!pane.connected, |ui| {
+                    egui::ComboBox::from_id_salt(format!("Device Pane {i}"))
+                        .selected_text(pane.device.replace("/dev/tty.", ""))
+                        .width(RIGHT_PANEL_WIDTH * 0.92 - 190.0)
+                        .show_ui(ui, |ui| {
+                            // on macOS each device appears as /dev/tty.* and /dev/cu.*
+                            for dev in devices.iter().filter(|dev| !dev.contains("/dev/cu.")) {
+                                ui.selectable_value(
+                                    &mut pane.device,
+                                    dev.clone(),
+                                    dev.replace("/dev/tty.", ""),
+                                );
+                            }
+                        });
                 });
-            let connect_text = if self.connected_to_device {
-                "Disconnect"
-            } else {
-                "Connect"
-            };
-            if ui.button(connect_text).clicked() {
-                if let Ok(mut device) = self.device_lock.write() {
-                    if self.connected_to_device {
-                        let _ = self.send_tx.send("__CTRLC__\r\n".to_string()); // send Ctrl+R to the device
-                        device.name.clear();
+                let connect_text = if pane.connected {
+                    "Disconnect"
+                } else {
+                    "Connect"
+                };
+                if ui
+                    .add_enabled(!pane.device.is_empty(), egui::Button::new(connect_text))
+                    .clicked()
+                {
+                    if pane.connected {
+                        pane.disconnect();
                     } else {
-                        device.name = self.serial_devices.devices[self.device_idx].name.clone();
-                        device.baud_rate = self.serial_devices.devices[self.device_idx].baud_rate;
-                        let _ = self.send_tx.send("__RESET__\r\n".to_string()); // send Ctrl+R to the device
+                        // baud rate, framing and labels are taken from the saved devices
+                        let device = self
+                            .serial_devices
+                            .devices
+                            .iter()
+                            .find(|dev| dev.name == pane.device)
+                            .cloned()
+                            .unwrap_or_else(|| Device {
+                                name: pane.device.clone(),
+                                ..Default::default()
+                            });
+                        pane.clear();
+                        pane.connect(&device);
                     }
                 }
-            }
-        });
-        ui.add_space(5.0);
-        ui.horizontal(|ui| {
-            ui.label("Data Bits");
-            ui.add_space(5.0);
-            ui.label("Parity");
-            ui.add_space(20.0);
-            ui.label("Stop Bits");
-            ui.label("Flow Control");
-            ui.label("Timeout");
-        });
+                ui.toggle_value(
+                    &mut pane.console_open,
+                    egui::RichText::new(egui_phosphor::regular::TERMINAL_WINDOW),
+                )
+                .on_hover_text("Show the console of the device.");
+                ui.toggle_value(
+                    &mut pane.overlay,
+                    egui::RichText::new(egui_phosphor::regular::CHART_LINE),
+                )
+                .on_hover_text("Overlay the datasets of the device on the time series plots.");
+                if ui
+                    .button(egui_phosphor::regular::X)
+                    .on_hover_text("Close the device.")
+                    .clicked()
+                {
+                    close = Some(i);
+                }
+            });
+        }
+        if let Some(i) = close {
+            self.device_panes.remove(i);
+        }
         ui.horizontal(|ui| {
-            if self.connected_to_device {
-                ui.disable();
+            if ui
+                .button(egui::RichText::new(format!(
+                    "{} Add Device",
+                    egui_phosphor::regular::PLUS
+                )))
+                .on_hover_text("Open another device next to the one of the main view.")
+                .clicked()
+            {
+                self.device_panes.push(DevicePane::spawn(
+                    ctx,
+                    self.csi_settings,
+                    self.gui_conf.storage,
+                ));
             }
-            egui::ComboBox::from_id_salt("Data Bits")
-                .selected_text(
-                    self.serial_devices.devices[self.device_idx]
-                        .data_bits
-                        .to_string(),
+            if ui
+                .add_enabled(
+                    !self.device_panes.is_empty(),
+                    egui::Button::new(egui::RichText::new(format!(
+                        "{} Save All Devices",
+                        egui_phosphor::regular::FLOPPY_DISK
+                    ))),
                 )
-                .width(30.0)
-                .show_ui(ui, |ui| {
+                .on_hover_text(
+                    "Save the datasets of all devices into one CSV on their common time axis.",
+                )
+                .clicked()
+            {
+                self.file_dialog_state = FileDialogState::SaveDevices;
+                self.file_dialog.save_file();
+            }
+        });
+    }
+
+    /// Names and CSI frames of the main device and the additional devices.
+    fn receivers(&self) -> (Vec<String>, Vec<&[CsiFrame]>) {
+        let main = if self.device.is_empty() {
+            "Main".to_string()
+        } else {
+            self.device.clone()
+        };
+        let mut names = vec![main];
+        let mut frames = vec![self.data.csi_frames.as_slice()];
+        for pane in self.device_panes.iter() {
+            names.push(pane.device.clone());
+            frames.push(pane.data.csi_frames.as_slice());
+        }
+        (names, frames)
+    }
+
+    fn draw_fusion_settings(&mut self, ui: &mut Ui) {
+        let old_settings = self.gui_conf.fusion;
+        egui::Grid::new("fusion_settings")
+            .num_columns(2)
+            .spacing(Vec2 { x: 10.0, y: 10.0 })
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("Align by");
+                egui::ComboBox::from_id_salt("Align Mode")
+                    .selected_text(self.gui_conf.fusion.mode.to_string())
+                    .show_ui(ui, |ui| {
+                        for mode in AlignMode::ALL {
+                            ui.selectable_value(
+                                &mut self.gui_conf.fusion.mode,
+                                mode,
+                                mode.to_string(),
+                            );
+                        }
+                    })
+                    .response
+                    .on_hover_text(
+                        "Match the frames of the receivers by the sequence number of the transmitter or by the nearest absolute time.",
+                    );
+                ui.end_row();
+                ui.label("Tolerance");
+                ui.add(
+                    egui::DragValue::new(&mut self.gui_conf.fusion.tolerance)
+                        .range(1.0..=10_000.0)
+                        .suffix(" ms"),
+                )
+                .on_hover_text("Largest difference of the absolute times of matched frames.");
+                ui.end_row();
+            });
+
+        let (names, frames) = self.receivers();
+        let receivers = frames.iter().filter(|frames| !frames.is_empty()).count();
+        if receivers < 2 {
+            ui.label("Open additional devices that receive CSI frames to align them.");
+            return;
+        }
+        // the alignment runs over all frames in memory, it is refreshed once per second
+        let outdated = self
+            .fusion_updated
+            .is_none_or(|updated| updated.elapsed() > Duration::from_secs(1));
+        if outdated
+            || old_settings != self.gui_conf.fusion
+            || self.fusion_stats.len() != names.len()
+        {
+            let records = align(&frames, &self.gui_conf.fusion);
+            self.fusion_stats = receiver_stats(&records, names.len());
+            self.fusion_records = records.len();
+            self.fusion_updated = Some(Instant::now());
+        }
+
+        ui.add_space(5.0);
+        ui.label(format!("{} fused records", self.fusion_records));
+        egui::Grid::new("fusion_stats")
+            .num_columns(5)
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("Receiver");
+                ui.label("Frames");
+                ui.label("Loss");
+                ui.label("Clock Offset");
+                ui.label("Delay");
+                ui.end_row();
+                let format_ms = |value: Option<f64>| {
+                    value.map_or("-".to_string(), |value| format!("{value:.1} ms"))
+                };
+                for (name, stats) in names.iter().zip(&self.fusion_stats) {
+                    ui.label(name.replace("/dev/tty.", ""));
+                    ui.label(stats.frames.to_string());
+                    ui.label(format!("{:.1} %", stats.loss(self.fusion_records) * 100.0));
+                    ui.label(format_ms(stats.clock_offset))
+                        .on_hover_text("Offset of the board clock to the first receiver.");
+                    ui.label(format_ms(stats.arrival_delay))
+                        .on_hover_text("Arrival at the host after the first receiver.");
+                    ui.end_row();
+                }
+            });
+
+        let start = self
+            .fusion_stats
+            .iter()
+            .filter_map(|stats| stats.offsets.first())
+            .fold(f64::INFINITY, |start, point| start.min(point[0]));
+        Plot::new("fusion-offsets")
+            .height(120.0)
+            .legend(Legend::default())
+            .x_axis_formatter(|x: GridMark, _range: &RangeInclusive<f64>| {
+                format!("{:4.1} s", x.value)
+            })
+            .y_axis_label("Clock Offset [ms]")
+            .show(ui, |plot_ui| {
+                // the first receiver is the reference
+                for (i, (name, stats)) in names.iter().zip(&self.fusion_stats).enumerate().skip(1) {
+                    let points: Vec<PlotPoint> = stats
+                        .offsets
+                        .iter()
+                        .map(|point| PlotPoint::new((point[0] - start) / 1000.0, point[1]))
+                        .collect();
+                    plot_ui.line(
+                        Line::new(PlotPoints::Owned(points))
+                            .name(name)
+                            .color(COLORS[i % COLORS.len()]),
+                    );
+                }
+            });
+
+        if ui
+            .button(egui::RichText::new(format!(
+                "{} Save Fused CSV",
+                egui_phosphor::regular::FLOPPY_DISK
+            )))
+            .on_hover_text("Save one row per transmission with the CSI values of every receiver.")
+            .clicked()
+        {
+            self.file_dialog_state = FileDialogState::SaveFused;
+            self.file_dialog.save_file();
+        }
+    }
+
+    fn draw_device_consoles(&mut self, ctx: &egui::Context) {
+        for i in 0..self.device_panes.len() {
+            let mut open = self.device_panes[i].console_open;
+            let title = if self.device_panes[i].device.is_empty() {
+                format!("Device {}", i + 2)
+            } else {
+                self.device_panes[i].device.clone()
+            };
+            egui::Window::new(title)
+                .id(egui::Id::new(("device_console", i)))
+                .open(&mut open)
+                .default_size([500.0, 300.0])
+                .show(ctx, |ui| {
+                    let row_height = ui.text_style_height(&egui::TextStyle::Body);
+                    let num_rows = self.device_panes[i].data.raw_traffic.len();
+                    egui::ScrollArea::vertical()
+                        .id_salt(("device_console_output", i))
+                        .auto_shrink([false; 2])
+                        .stick_to_bottom(true)
+                        .max_height(ui.available_height() - 30.0)
+                        .show_rows(ui, row_height, num_rows, |ui, row_range| {
+                            let content: String = row_range
+                                .flat_map(|j| {
+                                    self.console_text(&self.device_panes[i].data.raw_traffic[j])
+                                })
+                                .collect();
+                            ui.add(
+                                egui::TextEdit::multiline(&mut content.as_str())
+                                    .font(DEFAULT_FONT_ID)
+                                    .desired_width(f32::INFINITY),
+                            );
+                        });
+                    ui.horizontal(|ui| {
+                        let eol = self.eol.replace("\\r", "\r").replace("\\n", "\n");
+                        let pane = &mut self.device_panes[i];
+                        let cmd_line = ui.add(
+                            egui::TextEdit::singleline(&mut pane.command)
+                                .desired_width(ui.available_width() - 50.0)
+                                .code_editor(),
+                        );
+                        let key_pressed = ui.input(|i| i.key_pressed(egui::Key::Enter));
+                        if (key_pressed && cmd_line.lost_focus()) || ui.button("Send").clicked() {
+                            if let Err(err) = pane.send_tx.send(pane.command.clone() + &eol) {
+                                log::error!("send_tx thread send failed: {:?}", err);
+                            }
+                            cmd_line.request_focus();
+                        }
+                    });
+                });
+            self.device_panes[i].console_open = open;
+        }
+    }
+
+    fn draw_network_source(&mut self, ui: &mut Ui) {
+        ui.add_space(5.0);
+        ui.horizontal(|ui| {
+            if self.connected_to_device || self.file_opened {
+                ui.disable();
+            }
+            egui::ComboBox::from_id_salt("Network Protocol")
+                .selected_text(self.network_protocol.to_string())
+                .width(100.0)
+                .show_ui(ui, |ui| {
+                    for protocol in NetworkProtocol::ALL {
+                        ui.selectable_value(
+                            &mut self.network_protocol,
+                            protocol,
+                            protocol.to_string(),
+                        );
+                    }
+                });
+            ui.add(
+                egui::TextEdit::singleline(&mut self.network_address)
+                    .hint_text(self.network_protocol.address_hint())
+                    .desired_width(RIGHT_PANEL_WIDTH * 0.92 - 175.0),
+            );
+            let device = network_device(self.network_protocol, &self.network_address);
+            let response = ui
+                .add_enabled(device.is_ok(), egui::Button::new("Add"))
+                .on_hover_text("Add the network source to the device list.");
+            match device {
+                Ok(device) => {
+                    if response.clicked() {
+                        // selected like a device from the list
+                        self.old_device = std::mem::replace(&mut self.device, device);
+                        self.show_warning_window = if self.data.time.is_empty() {
+                            WindowFeedback::Clear
+                        } else {
+                            WindowFeedback::Waiting
+                        };
+                    }
+                }
+                Err(err) => {
+                    if !self.network_address.is_empty() {
+                        response.on_disabled_hover_text(err);
+                    }
+                }
+            }
+        });
+    }
+
+    /// `set-wifi` options for the selected mode, empty fields are skipped.
+    fn wifi_commands(&self) -> Result<Vec<EspCommand>, String> {
+        let mut commands = vec![EspCommand::SetWifi(WifiOption::Mode(self.wifi_mode))];
+        if self.wifi_mode != WifiMode::Sniffer {
+            let max_connections = self.wifi_max_connections.trim();
+            if !max_connections.is_empty() {
+                let max_connections = max_connections
+                    .parse()
+                    .map_err(|_| format!("invalid number of connections: {max_connections}"))?;
+                commands.push(EspCommand::SetWifi(WifiOption::MaxConnections(
+                    max_connections,
+                )));
+            }
+            if self.wifi_hide_ssid {
+                commands.push(EspCommand::SetWifi(WifiOption::HideSsid));
+            }
+        }
+        let credentials = [
+            (
+                self.wifi_mode.has_ap(),
+                &self.wifi_ap_ssid,
+                WifiOption::ApSsid as fn(String) -> WifiOption,
+            ),
+            (
+                self.wifi_mode.has_ap(),
+                &self.wifi_ap_password,
+                WifiOption::ApPassword,
+            ),
+            (
+                self.wifi_mode.has_station(),
+                &self.wifi_sta_ssid,
+                WifiOption::StaSsid,
+            ),
+            (
+                self.wifi_mode.has_station(),
+                &self.wifi_sta_password,
+                WifiOption::StaPassword,
+            ),
+        ];
+        for (enabled, value, option) in credentials {
+            if enabled && !value.trim().is_empty() {
+                commands.push(EspCommand::SetWifi(option(value.trim().to_string())));
+            }
+        }
+        Ok(commands)
+    }
+
+    /// Configuration sent by the START button, followed by `start`.
+    fn start_commands(&self) -> Result<Vec<EspCommand>, String> {
+        let mut commands = vec![];
+        if self.traffic_enable {
+            let interval = self.traffic_interval.trim();
+            let interval = if interval.is_empty() {
+                None
+            } else {
+                Some(
+                    interval
+                        .parse()
+                        .map_err(|_| format!("invalid traffic interval: {interval}"))?,
+                )
+            };
+            commands.push(EspCommand::SetTraffic {
+                traffic_type: self.traffic_type,
+                interval,
+            });
+        }
+        commands.push(EspCommand::SetNetwork(self.network_architecture));
+        if self.csi_flags.any() {
+            commands.push(EspCommand::SetCsi(self.csi_flags));
+        }
+        if self.enable_set_wifi {
+            commands.extend(self.wifi_commands()?);
+        }
+        commands.push(EspCommand::Start);
+        Ok(commands)
+    }
+
+    /// Validates all commands before the sequence is queued, the serial thread sends them
+    /// one after the other.
+    fn send_commands(&mut self, commands: Result<Vec<EspCommand>, String>) {
+        let commands = commands.and_then(|commands| {
+            if !self.connected_to_device {
+                return Err("no device connected".to_string());
+            }
+            commands
+                .iter()
+                .try_for_each(|command| command.validate())
+                .map(|_| commands)
+        });
+        let commands = commands.and_then(|commands| {
+            let config_changed = commands
+                .iter()
+                .any(|command| *command != EspCommand::ShowConfig);
+            let Ok(mut queue) = self.queue_lock.write() else {
+                return Err("the command queue is not available".to_string());
+            };
+            if !queue.submit(commands) {
+                return Err("the previous commands are still being sent".to_string());
+            }
+            self.command_queue.clone_from(&queue);
+            Ok(config_changed)
+        });
+        match commands {
+            Ok(config_changed) => {
+                self.command_error = None;
+                if config_changed {
+                    self.device_config_outdated = true;
+                }
+            }
+            Err(err) => {
+                log::error!("device commands not sent: {err}");
+                self.command_error = Some(err);
+            }
+        }
+    }
+
+    fn draw_command_queue(&mut self, ui: &mut Ui) {
+        if self.command_queue.commands.is_empty() {
+            return;
+        }
+        egui::Grid::new("command_queue")
+            .num_columns(2)
+            .striped(true)
+            .show(ui, |ui| {
+                for queued in self.command_queue.commands.iter() {
+                    let color = match queued.status {
+                        CommandStatus::Ok => Color32::GREEN,
+                        CommandStatus::Failed(_) | CommandStatus::Timeout => Color32::RED,
+                        CommandStatus::Sent => Color32::from_rgb(255, 140, 0),
+                        CommandStatus::Pending | CommandStatus::Aborted => Color32::GRAY,
+                    };
+                    ui.label(redact(&queued.command.to_string()));
+                    ui.colored_label(color, queued.status.to_string());
+                    ui.end_row();
+                }
+            });
+        if self.command_queue.is_busy() {
+            // the status changes without new data to plot
+            ui.ctx().request_repaint_after(Duration::from_millis(100));
+            if ui.button("Cancel").clicked() {
+                if let Ok(mut queue) = self.queue_lock.write() {
+                    queue.abort();
+                }
+            }
+        }
+    }
+
+    /// Takes over the settings reported by `show-config`, once per reply.
+    fn sync_device_config(&mut self) {
+        if self.data.device_config.is_none() || self.data.device_config == self.device_config {
+            return;
+        }
+        self.device_config.clone_from(&self.data.device_config);
+        self.device_config_outdated = false;
+        let Some(config) = self.device_config.clone() else {
+            return;
+        };
+        if let Some(wifi_mode) = config.wifi_mode {
+            self.wifi_mode = wifi_mode;
+        }
+        if let Some(arch) = config.network_architecture {
+            self.network_architecture = arch;
+        }
+        if let Some(enable) = config.traffic_enable {
+            self.traffic_enable = enable;
+        }
+        if let Some(traffic_type) = config.traffic_type {
+            self.traffic_type = traffic_type;
+        }
+        if let Some(interval) = config.traffic_interval {
+            self.traffic_interval = interval.to_string();
+        }
+        for (disabled, flag) in [
+            (config.disable_lltf, &mut self.csi_flags.disable_lltf),
+            (config.disable_htltf, &mut self.csi_flags.disable_htltf),
+            (
+                config.disable_stbc_htltf,
+                &mut self.csi_flags.disable_stbc_htltf,
+            ),
+            (
+                config.disable_ltf_merge,
+                &mut self.csi_flags.disable_ltf_merge,
+            ),
+        ] {
+            if let Some(disabled) = disabled {
+                *flag = disabled;
+            }
+        }
+        if let Some(max_connections) = config.max_connections {
+            self.wifi_max_connections = max_connections.to_string();
+        }
+        if let Some(hide_ssid) = config.hide_ssid {
+            self.wifi_hide_ssid = hide_ssid;
+        }
+        if let Some(ssid) = config.ap_ssid {
+            self.wifi_ap_ssid = ssid;
+        }
+        if let Some(ssid) = config.sta_ssid {
+            self.wifi_sta_ssid = ssid;
+        }
+    }
+
+    /// Settings that differ from the last configuration reported by the device, with the
+    /// value of the device. Settings the START button does not send are not compared.
+    fn config_mismatches(&self) -> Vec<(&'static str, String)> {
+        let Some(config) = self
+            .device_config
+            .as_ref()
+            .filter(|_| !self.device_config_outdated)
+        else {
+            return vec![];
+        };
+        fn differs<T: PartialEq + ToString>(device: &Option<T>, gui: &T) -> Option<String> {
+            device
+                .as_ref()
+                .filter(|device| *device != gui)
+                .map(|device| device.to_string())
+        }
+        let is_sniffer = self.wifi_mode == WifiMode::Sniffer;
+        let mut mismatches = vec![
+            ("Mode", differs(&config.wifi_mode, &self.wifi_mode)),
+            (
+                "Architecture",
+                differs(&config.network_architecture, &self.network_architecture),
+            ),
+            (
+                "Enable Traffic",
+                differs(&config.traffic_enable, &self.traffic_enable),
+            ),
+            (
+                "Disable LLTF",
+                differs(&config.disable_lltf, &self.csi_flags.disable_lltf),
+            ),
+            (
+                "Disable HTLTF",
+                differs(&config.disable_htltf, &self.csi_flags.disable_htltf),
+            ),
+            (
+                "Disable STBC HTLTF",
+                differs(
+                    &config.disable_stbc_htltf,
+                    &self.csi_flags.disable_stbc_htltf,
+                ),
+            ),
+            (
+                "Disable LTF Merge",
+                differs(&config.disable_ltf_merge, &self.csi_flags.disable_ltf_merge),
+            ),
+        ];
+        if self.traffic_enable {
+            mismatches.push((
+                "Traffic Type",
+                differs(&config.traffic_type, &self.traffic_type),
+            ));
+            mismatches.push((
+                "Interval",
+                differs(
+                    &config.traffic_interval.map(|interval| interval.to_string()),
+                    &self.traffic_interval.trim().to_string(),
+                ),
+            ));
+        }
+        if self.enable_set_wifi && !is_sniffer {
+            mismatches.push((
+                "Max Connections",
+                differs(
+                    &config.max_connections.map(|max| max.to_string()),
+                    &self.wifi_max_connections.trim().to_string(),
+                ),
+            ));
+            mismatches.push((
+                "Hide SSID",
+                differs(&config.hide_ssid, &self.wifi_hide_ssid),
+            ));
+        }
+        if self.enable_set_wifi && self.wifi_mode.has_ap() {
+            mismatches.push((
+                "AP SSID",
+                differs(&config.ap_ssid, &self.wifi_ap_ssid.trim().to_string()),
+            ));
+        }
+        if self.enable_set_wifi && self.wifi_mode.has_station() {
+            mismatches.push((
+                "STA SSID",
+                differs(&config.sta_ssid, &self.wifi_sta_ssid.trim().to_string()),
+            ));
+        }
+        mismatches
+            .into_iter()
+            .filter_map(|(setting, device)| device.map(|device| (setting, device)))
+            .collect()
+    }
+
+    /// Settings of the side panel as a profile, bound to the same port as the saved one.
+    fn current_profile(&self, name: &str) -> DeviceProfile {
+        DeviceProfile {
+            name: name.to_string(),
+            port: self
+                .profiles
+                .profiles
+                .iter()
+                .find(|profile| profile.name == name)
+                .and_then(|profile| profile.port.clone()),
+            traffic_enable: self.traffic_enable,
+            traffic_type: self.traffic_type,
+            traffic_interval: self.traffic_interval.clone(),
+            network_architecture: self.network_architecture,
+            csi_flags: self.csi_flags,
+            enable_set_wifi: self.enable_set_wifi,
+            wifi_mode: self.wifi_mode,
+            wifi_max_connections: self.wifi_max_connections.clone(),
+            wifi_hide_ssid: self.wifi_hide_ssid,
+            wifi_ap_ssid: self.wifi_ap_ssid.clone(),
+            wifi_ap_password: self.wifi_ap_password.clone(),
+            wifi_sta_ssid: self.wifi_sta_ssid.clone(),
+            wifi_sta_password: self.wifi_sta_password.clone(),
+        }
+    }
+
+    fn load_profile(&mut self, idx: usize) {
+        let Some(profile) = self.profiles.profiles.get(idx).cloned() else {
+            return;
+        };
+        self.traffic_enable = profile.traffic_enable;
+        self.traffic_type = profile.traffic_type;
+        self.traffic_interval = profile.traffic_interval;
+        self.network_architecture = profile.network_architecture;
+        self.csi_flags = profile.csi_flags;
+        self.enable_set_wifi = profile.enable_set_wifi;
+        self.wifi_mode = profile.wifi_mode;
+        self.wifi_max_connections = profile.wifi_max_connections;
+        self.wifi_hide_ssid = profile.wifi_hide_ssid;
+        self.wifi_ap_ssid = profile.wifi_ap_ssid;
+        self.wifi_ap_password = profile.wifi_ap_password;
+        self.wifi_sta_ssid = profile.wifi_sta_ssid;
+        self.wifi_sta_password = profile.wifi_sta_password;
+        self.profile_idx = Some(idx);
+        self.profile_name = profile.name;
+    }
+
+    /// Saves the profiles, and their Wi-Fi passwords to the encrypted store if it is unlocked.
+    fn store_profiles(&self) {
+        save_profiles(&self.profiles);
+        if self.secrets_unlocked {
+            match EncryptedSecrets::encrypt(&self.profiles.passwords(), &self.passphrase) {
+                Ok(secrets) => save_secrets(&secrets),
+                Err(err) => log::error!("failed to encrypt the Wi-Fi passwords: {err}"),
+            }
+        }
+    }
+
+    fn unlock_secrets(&mut self) {
+        if self.passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+            self.secrets_error = Some(format!(
+                "The passphrase needs at least {MIN_PASSPHRASE_LEN} characters."
+            ));
+            return;
+        }
+        // without a store, it is created with this passphrase once the profiles are saved
+        if let Some(secrets) = load_secrets() {
+            match secrets.decrypt(&self.passphrase) {
+                Ok(passwords) => self.profiles.set_passwords(&passwords),
+                Err(err) => {
+                    self.secrets_error = Some(format!("Password store not unlocked: {err}."));
+                    return;
+                }
+            }
+        }
+        self.secrets_unlocked = true;
+        self.secrets_error = None;
+    }
+
+    fn lock_secrets(&mut self) {
+        self.profiles.clear_passwords();
+        self.passphrase.clear();
+        self.secrets_unlocked = false;
+    }
+
+    fn draw_profiles(&mut self, ui: &mut Ui) {
+        let selected = self
+            .profile_idx
+            .and_then(|idx| self.profiles.profiles.get(idx))
+            .cloned();
+        egui::Grid::new("device_profiles")
+            .num_columns(2)
+            .spacing(Vec2 { x: 10.0, y: 10.0 })
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("Profile");
+                egui::ComboBox::from_id_salt("Device Profile")
+                    .selected_text(selected.as_ref().map_or("None", |p| p.name.as_str()))
+                    .show_ui(ui, |ui| {
+                        for idx in 0..self.profiles.profiles.len() {
+                            let name = self.profiles.profiles[idx].name.clone();
+                            if ui
+                                .selectable_label(self.profile_idx == Some(idx), &name)
+                                .clicked()
+                            {
+                                self.profile_idx = Some(idx);
+                                self.profile_name = name;
+                            }
+                        }
+                    });
+                ui.end_row();
+                ui.label("Name");
+                ui.text_edit_singleline(&mut self.profile_name);
+                ui.end_row();
+                ui.label("Port");
+                ui.add_enabled_ui(selected.is_some(), |ui| {
+                    let port = selected.as_ref().and_then(|p| p.port.clone());
+                    egui::ComboBox::from_id_salt("Profile Port")
+                        .selected_text(port.as_deref().unwrap_or("None"))
+                        .show_ui(ui, |ui| {
+                            let mut new_port = port.clone();
+                            ui.selectable_value(&mut new_port, None, "None");
+                            for dev in self.device_list() {
+                                let dev_text = dev.replace("/dev/tty.", "");
+                                ui.selectable_value(&mut new_port, Some(dev), dev_text);
+                            }
+                            if new_port != port {
+                                if let Some(idx) = self.profile_idx {
+                                    self.profiles.bind(idx, new_port);
+                                    save_profiles(&self.profiles);
+                                }
+                            }
+                        })
+                        .response
+                        .on_hover_text("Load the profile whenever this port is selected.");
+                });
+                ui.end_row();
+            });
+
+        ui.horizontal(|ui| {
+            let name = self.profile_name.trim().to_string();
+            if ui
+                .add_enabled(!name.is_empty(), egui::Button::new("Save"))
+                .on_hover_text("Save the device settings above under this name.")
+                .clicked()
+            {
+                let profile = self.current_profile(&name);
+                self.profile_idx = Some(self.profiles.insert(profile));
+                self.store_profiles();
+            }
+            if let Some(idx) = self.profile_idx.filter(|_| selected.is_some()) {
+                if ui
+                    .button("Load")
+                    .on_hover_text("Load the settings of the profile without sending them.")
+                    .clicked()
+                {
+                    self.load_profile(idx);
+                }
+                if ui
+                    .add_enabled(!self.command_queue.is_busy(), egui::Button::new("Apply"))
+                    .on_hover_text("Load the profile and send the whole sequence to the board.")
+                    .clicked()
+                {
+                    self.load_profile(idx);
+                    self.send_commands(self.start_commands());
+                }
+                if ui.button("Delete").clicked() {
+                    self.profiles.profiles.remove(idx);
+                    self.profile_idx = None;
+                    self.store_profiles();
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            if ui
+                .button(egui::RichText::new(format!(
+                    "{} Import",
+                    egui_phosphor::regular::FILE_ARROW_UP
+                )))
+                .on_hover_text(
+                    "Add the profiles of a .json file, replacing the ones with the same name.",
+                )
+                .clicked()
+            {
+                self.file_dialog_state = FileDialogState::ImportProfiles;
+                self.file_dialog.pick_file();
+            }
+            if ui
+                .add_enabled(
+                    !self.profiles.profiles.is_empty(),
+                    egui::Button::new(egui::RichText::new(format!(
+                        "{} Export",
+                        egui_phosphor::regular::FILE_ARROW_DOWN
+                    ))),
+                )
+                .on_hover_text("Save all profiles to a .json file.")
+                .clicked()
+            {
+                self.file_dialog_state = FileDialogState::ExportProfiles;
+                self.file_dialog.save_file();
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Passphrase:");
+            let response = ui.add_enabled(
+                !self.secrets_unlocked,
+                egui::TextEdit::singleline(&mut self.passphrase).password(true),
+            );
+            let enter = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if self.secrets_unlocked {
+                if ui
+                    .button(egui::RichText::new(format!(
+                        "{} Lock",
+                        egui_phosphor::regular::LOCK
+                    )))
+                    .on_hover_text("Forget the passphrase and the Wi-Fi passwords of the profiles.")
+                    .clicked()
+                {
+                    self.lock_secrets();
+                }
+            } else if ui
+                .button(egui::RichText::new(format!(
+                    "{} Unlock",
+                    egui_phosphor::regular::LOCK_OPEN
+                )))
+                .on_hover_text(
+                    "Load the Wi-Fi passwords of the profiles from the encrypted password store, \
+                    it is created with this passphrase if there is none.",
+                )
+                .clicked()
+                || enter
+            {
+                self.unlock_secrets();
+            }
+        });
+        if let Some(err) = &self.secrets_error {
+            ui.colored_label(Color32::RED, err);
+        } else if !self.secrets_unlocked {
+            ui.label("Wi-Fi passwords are only saved while the password store is unlocked.");
+        }
+    }
+
+    fn draw_esp_commands(&mut self, ui: &mut Ui) {
+        self.sync_device_config();
+        let mismatches = self.config_mismatches();
+        // marks a setting that differs from the device
+        let mismatch_marker = |ui: &mut Ui, setting: &str| {
+            if let Some((_, device)) = mismatches.iter().find(|(name, _)| *name == setting) {
+                ui.colored_label(
+                    Color32::from_rgb(255, 140, 0),
+                    egui_phosphor::regular::WARNING,
+                )
+                .on_hover_text(format!("The device is set to \"{device}\"."));
+            }
+        };
+
+        ui.add_space(16.0);
+        let start = ui.add_enabled_ui(!self.command_queue.is_busy(), |ui| {
+            ui.add_sized([RIGHT_PANEL_WIDTH * 0.9, 40.0], egui::Button::new("START"))
+        });
+        if start.inner.clicked() {
+            self.send_commands(self.start_commands());
+        }
+        self.draw_command_queue(ui);
+        if !mismatches.is_empty() {
+            let settings: Vec<&str> = mismatches.iter().map(|(setting, _)| *setting).collect();
+            ui.colored_label(
+                Color32::from_rgb(255, 140, 0),
+                format!("Differs from the device: {}", settings.join(", ")),
+            );
+        }
+        if let Some(err) = &self.command_error {
+            ui.colored_label(Color32::RED, err);
+        }
+        ui.add_space(16.0);
+
+        ui.heading("Set Traffic");
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.traffic_enable, "Enable Traffic");
+            mismatch_marker(ui, "Enable Traffic");
+        });
+        ui.add_enabled_ui(self.traffic_enable, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Traffic Type:");
+                egui::ComboBox::from_id_salt("traffic_type_combo")
+                    .selected_text(self.traffic_type.to_string())
+                    .show_ui(ui, |ui| {
+                        for traffic_type in TrafficType::ALL {
+                            ui.selectable_value(
+                                &mut self.traffic_type,
+                                traffic_type,
+                                traffic_type.to_string(),
+                            );
+                        }
+                    });
+                mismatch_marker(ui, "Traffic Type");
+            });
+            ui.horizontal(|ui| {
+                ui.label("Interval (ms):");
+                ui.text_edit_singleline(&mut self.traffic_interval);
+                mismatch_marker(ui, "Interval");
+            });
+        });
+        ui.add_space(16.0);
+
+        ui.heading("Set Network");
+        ui.horizontal(|ui| {
+            ui.label("Architecture:");
+            egui::ComboBox::from_id_salt("architecture_combo")
+                .selected_text(self.network_architecture.to_string())
+                .show_ui(ui, |ui| {
+                    for arch in NetworkArchitecture::ALL {
+                        ui.selectable_value(&mut self.network_architecture, arch, arch.to_string());
+                    }
+                });
+            mismatch_marker(ui, "Architecture");
+        });
+        if ui
+            .button("Set Network")
+            .on_hover_text("Send the architecture to the device.")
+            .clicked()
+        {
+            self.send_commands(Ok(vec![EspCommand::SetNetwork(self.network_architecture)]));
+        }
+        ui.add_space(16.0);
+
+        ui.heading("Set CSI");
+        for (flag, label) in [
+            (&mut self.csi_flags.disable_lltf, "Disable LLTF"),
+            (&mut self.csi_flags.disable_htltf, "Disable HTLTF"),
+            (&mut self.csi_flags.disable_stbc_htltf, "Disable STBC HTLTF"),
+            (&mut self.csi_flags.disable_ltf_merge, "Disable LTF Merge"),
+        ] {
+            ui.horizontal(|ui| {
+                ui.checkbox(flag, label);
+                mismatch_marker(ui, label);
+            });
+        }
+        if ui
+            .button("Set CSI")
+            .on_hover_text("Send the CSI settings to the device.")
+            .clicked()
+        {
+            self.send_commands(Ok(vec![EspCommand::SetCsi(self.csi_flags)]));
+        }
+        ui.add_space(16.0);
+
+        ui.heading("Set WiFi");
+        ui.checkbox(&mut self.enable_set_wifi, "Enable Set WiFi");
+        ui.horizontal(|ui| {
+            ui.label("Mode:");
+            egui::ComboBox::from_id_salt("wifi_mode_combo")
+                .selected_text(self.wifi_mode.to_string())
+                .show_ui(ui, |ui| {
+                    for mode in WifiMode::ALL {
+                        ui.selectable_value(&mut self.wifi_mode, mode, mode.to_string());
+                    }
+                });
+            mismatch_marker(ui, "Mode");
+        });
+
+        // options of the other modes are cleared and grayed out
+        let is_sniffer = self.wifi_mode == WifiMode::Sniffer;
+        if is_sniffer {
+            self.wifi_max_connections.clear();
+            self.wifi_hide_ssid = false;
+        }
+        if !self.wifi_mode.has_ap() {
+            self.wifi_ap_ssid.clear();
+            self.wifi_ap_password.clear();
+        }
+        if !self.wifi_mode.has_station() {
+            self.wifi_sta_ssid.clear();
+            self.wifi_sta_password.clear();
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Max Connections:");
+            ui.add_enabled_ui(!is_sniffer, |ui| {
+                ui.text_edit_singleline(&mut self.wifi_max_connections);
+            });
+            mismatch_marker(ui, "Max Connections");
+        });
+        ui.horizontal(|ui| {
+            ui.add_enabled_ui(!is_sniffer, |ui| {
+                ui.checkbox(&mut self.wifi_hide_ssid, "Hide SSID (AP only)");
+            });
+            mismatch_marker(ui, "Hide SSID");
+        });
+        let has_ap = self.wifi_mode.has_ap();
+        let has_station = self.wifi_mode.has_station();
+        for (label, enabled, value) in [
+            ("AP SSID", has_ap, &mut self.wifi_ap_ssid),
+            ("AP Password", has_ap, &mut self.wifi_ap_password),
+            ("STA SSID", has_station, &mut self.wifi_sta_ssid),
+            ("STA Password", has_station, &mut self.wifi_sta_password),
+        ] {
+            let secret = label.ends_with("Password");
+            ui.horizontal(|ui| {
+                ui.label(format!("{label}:"));
+                ui.add_enabled_ui(enabled, |ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(value).password(secret && !self.show_passwords),
+                    );
+                });
+                if secret {
+                    let icon = if self.show_passwords {
+                        egui_phosphor::regular::EYE_SLASH
+                    } else {
+                        egui_phosphor::regular::EYE
+                    };
+                    ui.toggle_value(&mut self.show_passwords, icon)
+                        .on_hover_text("Show the passwords.");
+                }
+                mismatch_marker(ui, label);
+            });
+        }
+        if ui.button("Set WiFi").clicked() && self.enable_set_wifi {
+            self.send_commands(self.wifi_commands());
+        }
+
+        //this does not work yet
+        ui.add_space(16.0);
+        ui.heading("Ctrl R");
+        if ui.button("Send Ctrl R").clicked() {
+            // send Ctrl+R to the device
+            if let Err(err) = self.send_tx.send("__RESET__\r\n".to_string()) {
+                log::error!("send_tx thread send failed: {:?}", err);
+            }
+        }
+
+        ui.add_space(16.0);
+        ui.heading("Show Config");
+        if ui
+            .button("Show Config")
+            .on_hover_text("Read the configuration of the device into the settings above.")
+            .clicked()
+        {
+            self.send_commands(Ok(vec![EspCommand::ShowConfig]));
+        }
+
+        ui.add_space(16.0);
+        ui.heading("Reset Config");
+        if ui.button("Reset Config").clicked() {
+            self.send_commands(Ok(vec![EspCommand::ResetConfig]));
+        }
+        ui.add_space(16.0);
+    }
+
+    fn draw_replay_settings(&mut self, ui: &mut Ui) {
+        ui.add_space(5.0);
+        ui.horizontal(|ui| {
+            ui.label("Log");
+            ui.add_enabled_ui(!self.connected_to_device, |ui| {
+                if ui
+                    .button(egui::RichText::new(format!(
+                        "{} Select log",
+                        egui_phosphor::regular::FOLDER_OPEN
+                    )))
+                    .on_hover_text("Raw traffic log (CSV or NDJSON) to replay.")
+                    .clicked()
+                {
+                    self.file_dialog_state = FileDialogState::OpenReplay;
+                    self.file_dialog.pick_file();
+                }
+            });
+            match &self.serial_devices.devices[self.device_idx].replay_path {
+                Some(path) => {
+                    ui.label(
+                        path.file_name()
+                            .unwrap_or_default()
+                            .to_string_lossy()
+                            .to_string(),
+                    )
+                    .on_hover_text(path.display().to_string());
+                }
+                None => {
+                    ui.colored_label(Color32::from_rgb(255, 140, 0), "No log selected");
+                }
+            }
+        });
+        let Ok(mut control) = self.replay_lock.write() else {
+            return;
+        };
+        ui.add_space(5.0);
+        ui.horizontal(|ui| {
+            let play_icon = if control.paused {
+                egui_phosphor::regular::PLAY
+            } else {
+                egui_phosphor::regular::PAUSE
+            };
+            if ui
+                .button(play_icon)
+                .on_hover_text("Pause or resume the replay.")
+                .clicked()
+            {
+                control.paused = !control.paused;
+            }
+            ui.add(
+                egui::DragValue::new(&mut control.speed)
+                    .range(0.1..=100.0)
+                    .speed(0.1)
+                    .suffix("x"),
+            )
+            .on_hover_text("Playback speed relative to the original timing.");
+            ui.toggle_value(
+                &mut control.looping,
+                egui::RichText::new(egui_phosphor::regular::REPEAT),
+            )
+            .on_hover_text("Start again after the last packet.");
+        });
+        ui.horizontal(|ui| {
+            ui.add_enabled_ui(self.connected_to_device, |ui| {
+                let mut position = control.seek.unwrap_or(control.position) / 1000.0;
+                let duration = control.duration / 1000.0;
+                if ui
+                    .add(egui::Slider::new(&mut position, 0.0..=duration).suffix(" s"))
+                    .changed()
+                {
+                    control.seek = Some(position * 1000.0);
+                }
+                ui.label(format!("/ {duration:.1} s"));
+            });
+        });
+    }
+
+    fn draw_serial_settings(&mut self, ctx: &egui::Context, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.heading("Serial Monitor");
+            self.paint_connection_indicator(ui);
+            if self.motion_settings.enabled {
+                self.paint_motion_indicator(ui);
+            }
+        });
+
+        let devices = self.device_list();
+
+        if !is_available(&self.device, &devices) {
+            self.device.clear();
+        }
+        if let Ok(dev) = self.device_lock.read() {
+            if !dev.name.is_empty() {
+                self.device = dev.name.clone();
+            }
+        }
+        ui.add_space(10.0);
+        ui.horizontal(|ui| {
+            ui.label("Device");
+            ui.add_space(130.0);
+            ui.label("Baud");
+        });
+
+        let old_name = self.device.clone();
+        ui.horizontal(|ui| {
+            if self.file_opened {
+                ui.disable();
+            }
+            let dev_text = self.device.replace("/dev/tty.", "");
+            ui.horizontal(|ui| {
+                if self.connected_to_device {
+                    ui.disable();
+                }
+                let _response = egui::ComboBox::from_id_salt("Device")
+                    .selected_text(dev_text)
+                    .width(RIGHT_PANEL_WIDTH * 0.92 - 155.0)
+                    .show_ui(ui, |ui| {
+                        devices
+                            .into_iter()
+                            // on macOS each device appears as /dev/tty.* and /dev/cu.*
+                            // we only display the /dev/tty.* here
+                            .filter(|dev| !dev.contains("/dev/cu."))
+                            .for_each(|dev| {
+                                // this makes the names shorter in the UI on UNIX and UNIX-like platforms
+                                let dev_text = dev.replace("/dev/tty.", "");
+                                ui.selectable_value(&mut self.device, dev, dev_text);
+                            });
+                    })
+                    .response;
+                // let selected_new_device = response.changed();  //somehow this does not work
+                // if selected_new_device {
+                if old_name != self.device {
+                    if !self.data.time.is_empty() {
+                        self.show_warning_window = WindowFeedback::Waiting;
+                        self.old_device = old_name;
+                    } else {
+                        self.show_warning_window = WindowFeedback::Clear;
+                    }
+                }
+            });
+            match self.show_warning_window {
+                WindowFeedback::None => {}
+                WindowFeedback::Waiting => {
+                    self.show_warning_window = self.clear_warning_window(ctx);
+                }
+                WindowFeedback::Clear => {
+                    // new device selected, check in previously used devices
+                    let mut device_is_already_saved = false;
+                    for (idx, dev) in self.serial_devices.devices.iter().enumerate() {
+                        if dev.name == self.device {
+                            // this is the device!
+                            self.device = dev.name.clone();
+                            self.device_idx = idx;
+                            self.init = true;
+                            device_is_already_saved = true;
+                        }
+                    }
+                    if !device_is_already_saved {
+                        // create new device in the archive
+                        let mut device = Device::default();
+                        device.name = self.device.clone();
+                        self.serial_devices.devices.push(device);
+                        self.serial_devices.number_of_plots.push(1);
+                        self.serial_devices.number_of_highlights.push(1);
+                        self.serial_devices
+                            .highlight_labels
+                            .push(vec!["".to_string()]);
+                        self.serial_devices
+                            .labels
+                            .push(vec!["Column 0".to_string()]);
+                        self.device_idx = self.serial_devices.devices.len() - 1;
+                        save_serial_settings(&self.serial_devices);
+                    }
+                    // the profile bound to the port is loaded, it is sent with START
+                    if let Some(idx) = self.profiles.bound_to(&self.device) {
+                        self.load_profile(idx);
+                    }
+                    self.clear_tx
+                        .send(true)
+                        .expect("failed to send clear after choosing new device");
+                    // need to clear the data here such that we don't get errors in the gui (plot)
+                    self.data = DataContainer::default();
+                    self.show_warning_window = WindowFeedback::None;
+                }
+                WindowFeedback::Cancel => {
+                    self.device = self.old_device.clone();
+                    self.show_warning_window = WindowFeedback::None;
+                }
+            }
+            egui::ComboBox::from_id_salt("Baud Rate")
+                .selected_text(format!(
+                    "{}",
+                    self.serial_devices.devices[self.device_idx].baud_rate
+                ))
+                .width(80.0)
+                .show_ui(ui, |ui| {
+                    if self.connected_to_device {
+                        ui.disable();
+                    }
+                    BAUD_RATES.iter().for_each(|baud_rate| {
+                        ui.selectable_value(
+                            &mut self.serial_devices.devices[self.device_idx].baud_rate,
+                            *baud_rate,
+                            baud_rate.to_string(),
+                        );
+                    });
+                });
+            let connect_text = if self.connected_to_device {
+                "Disconnect"
+            } else {
+                "Connect"
+            };
+            if ui.button(connect_text).clicked() {
+                if let Ok(mut device) = self.device_lock.write() {
+                    if self.connected_to_device {
+                        let _ = self.send_tx.send("__CTRLC__\r\n".to_string()); // send Ctrl+R to the device
+                        device.name.clear();
+                    } else {
+                        device.name = self.serial_devices.devices[self.device_idx].name.clone();
+                        device.baud_rate = self.serial_devices.devices[self.device_idx].baud_rate;
+                        device.framing = self.serial_devices.devices[self.device_idx].framing;
+                        device.elf_path = self.serial_devices.devices[self.device_idx]
+                            .elf_path
+                            .clone();
+                        device.replay_path = self.serial_devices.devices[self.device_idx]
+                            .replay_path
+                            .clone();
+                        let _ = self.send_tx.send("__RESET__\r\n".to_string()); // send Ctrl+R to the device
+                    }
+                }
+            }
+        });
+        self.draw_network_source(ui);
+        ui.add_space(5.0);
+        ui.horizontal(|ui| {
+            ui.label("Data Bits");
+            ui.add_space(5.0);
+            ui.label("Parity");
+            ui.add_space(20.0);
+            ui.label("Stop Bits");
+            ui.label("Flow Control");
+            ui.label("Timeout");
+        });
+        ui.horizontal(|ui| {
+            if self.connected_to_device {
+                ui.disable();
+            }
+            egui::ComboBox::from_id_salt("Data Bits")
+                .selected_text(
+                    self.serial_devices.devices[self.device_idx]
+                        .data_bits
+                        .to_string(),
+                )
+                .width(30.0)
+                .show_ui(ui, |ui| {
                     ui.selectable_value(
                         &mut self.serial_devices.devices[self.device_idx].data_bits,
                         DataBits::Eight,
@@ -864,6 +2585,89 @@ impl MyApp {
                 });
         });
         ui.add_space(5.0);
+        ui.horizontal(|ui| {
+            ui.label("Framing");
+            ui.add_enabled_ui(!self.connected_to_device, |ui| {
+                egui::ComboBox::from_id_salt("Framing")
+                    .selected_text(
+                        self.serial_devices.devices[self.device_idx]
+                            .framing
+                            .to_string(),
+                    )
+                    .show_ui(ui, |ui| {
+                        for framing in Framing::ALL {
+                            ui.selectable_value(
+                                &mut self.serial_devices.devices[self.device_idx].framing,
+                                framing,
+                                framing.to_string(),
+                            );
+                        }
+                    })
+                    .response
+                    .on_hover_text(
+                        "Binary framing of the CSI frames or defmt logs, selected per device.",
+                    );
+            });
+            if self.connected_to_device
+                && self.serial_devices.devices[self.device_idx].framing != Framing::Text
+            {
+                if let Ok(stats) = self.framing_stats_lock.read() {
+                    let errors = stats.crc_failures + stats.dropped + stats.malformed;
+                    ui.label(format!("{} frames", stats.frames))
+                        .on_hover_text(stats.to_string());
+                    if errors > 0 {
+                        ui.colored_label(
+                            Color32::from_rgb(255, 140, 0),
+                            format!("{errors} errors"),
+                        )
+                        .on_hover_text(stats.to_string());
+                    }
+                }
+            } else if self.data.csi_stats.malformed > 0 {
+                ui.colored_label(
+                    Color32::from_rgb(255, 140, 0),
+                    format!("{} malformed", self.data.csi_stats.malformed),
+                )
+                .on_hover_text(self.data.csi_stats.to_string());
+            }
+        });
+        if self.serial_devices.devices[self.device_idx].framing == Framing::Defmt {
+            ui.add_space(5.0);
+            ui.horizontal(|ui| {
+                ui.label("ELF");
+                ui.add_enabled_ui(!self.connected_to_device, |ui| {
+                    if ui
+                        .button(egui::RichText::new(format!(
+                            "{} Select ELF",
+                            egui_phosphor::regular::FOLDER_OPEN
+                        )))
+                        .on_hover_text("Firmware ELF containing the defmt table.")
+                        .clicked()
+                    {
+                        self.file_dialog_state = FileDialogState::OpenElf;
+                        self.file_dialog.pick_file();
+                    }
+                });
+                match &self.serial_devices.devices[self.device_idx].elf_path {
+                    Some(path) => {
+                        ui.label(
+                            path.file_name()
+                                .unwrap_or_default()
+                                .to_string_lossy()
+                                .to_string(),
+                        )
+                        .on_hover_text(path.display().to_string());
+                    }
+                    None => {
+                        ui.colored_label(Color32::from_rgb(255, 140, 0), "No ELF selected");
+                    }
+                }
+            });
+        }
+        if self.serial_devices.devices[self.device_idx].name == REPLAY_DEVICE {
+            self.draw_replay_settings(ui);
+        }
+        ui.add_space(5.0);
         ui.horizontal(|ui| {
             if self.connected_to_device {
                 ui.disable();
@@ -873,7 +2677,7 @@ impl MyApp {
                     "{} Open file",
                     egui_phosphor::regular::FOLDER_OPEN
                 )))
-                .on_hover_text("Load data from .csv")
+                .on_hover_text("Load data or a raw traffic log from .csv or .ndjson")
                 .clicked()
             {
                 self.file_dialog_state = FileDialogState::Open;
@@ -927,13 +2731,73 @@ impl MyApp {
                 ui.end_row();
                 ui.label("Save Raw Traffic");
                 ui.add(toggle(&mut self.save_raw))
-                    .on_hover_text("Save second CSV containing raw traffic.")
+                    .on_hover_text("Save a second file containing every sent and received packet.")
                     .changed();
                 ui.end_row();
+                let missing_packets = if self.save_raw {
+                    self.data.evicted.raw_traffic
+                } else {
+                    0
+                };
+                if !self.gui_conf.storage.spill
+                    && (self.data.evicted.rows > 0 || missing_packets > 0)
+                {
+                    ui.label("");
+                    ui.colored_label(
+                        Color32::from_rgb(255, 140, 0),
+                        format!(
+                            "{} rows and {} packets were evicted from memory and are missing in the saved files.",
+                            self.data.evicted.rows, missing_packets
+                        ),
+                    )
+                    .on_hover_text("Spill to the session file to save everything.");
+                    ui.end_row();
+                }
+                ui.label("Traffic Format");
+                egui::ComboBox::from_id_salt("Traffic Format")
+                    .selected_text(self.gui_conf.traffic_format.to_string())
+                    .show_ui(ui, |ui| {
+                        for format in TrafficFormat::ALL {
+                            ui.selectable_value(
+                                &mut self.gui_conf.traffic_format,
+                                format,
+                                format.to_string(),
+                            );
+                        }
+                    })
+                    .response
+                    .on_hover_text("Both formats can be opened again.");
+                ui.end_row();
                 ui.label("Save Absolute Time");
                 ui.add(toggle(&mut self.gui_conf.save_absolute_time))
                     .on_hover_text("Save absolute time in CSV.");
                 ui.end_row();
+                ui.label("Buffer Capacity");
+                let capacity = ui
+                    .add(
+                        egui::DragValue::new(&mut self.gui_conf.storage.capacity)
+                            .speed(100.0)
+                            .range(1_000..=10_000_000),
+                    )
+                    .on_hover_text(
+                        "Number of samples, raw packets and CSI frames kept in memory.",
+                    );
+                ui.end_row();
+                ui.label("Spill to Session File");
+                let spill = ui.add(toggle(&mut self.gui_conf.storage.spill)).on_hover_text(
+                    "Write everything older than the buffer capacity to a session file in the temporary directory.",
+                );
+                ui.end_row();
+                if capacity.changed() || spill.changed() {
+                    if let Err(err) = self.storage_settings_tx.send(self.gui_conf.storage) {
+                        log::error!("storage_settings_tx thread send failed: {:?}", err);
+                    }
+                    for pane in self.device_panes.iter() {
+                        if let Err(err) = pane.storage_settings_tx.send(self.gui_conf.storage) {
+                            log::error!("storage_settings_tx thread send failed: {:?}", err);
+                        }
+                    }
+                }
             });
     }
 
@@ -1034,109 +2898,777 @@ impl MyApp {
                     ui.add(
                         egui::DragValue::new(&mut self.plotting_range).custom_formatter(window_fmt),
                     )
-                    .on_hover_text(
-                        "Select a window of the last datapoints to be displayed in the plot.",
-                    );
-                    if ui
-                        .button("Full Dataset")
-                        .on_hover_text("Show the full dataset.")
-                        .clicked()
-                    {
-                        self.plotting_range = usize::MAX;
-                    }
+                    .on_hover_text(
+                        "Select a window of the last datapoints to be displayed in the plot.",
+                    );
+                    if ui
+                        .button("Full Dataset")
+                        .on_hover_text("Show the full dataset.")
+                        .clicked()
+                    {
+                        self.plotting_range = usize::MAX;
+                    }
+                });
+                ui.end_row();
+                ui.label("Number of plots [#]: ");
+
+                ui.horizontal(|ui| {
+                    if ui
+                        .button(egui::RichText::new(
+                            egui_phosphor::regular::ARROW_FAT_LEFT.to_string(),
+                        ))
+                        .clicked()
+                    {
+                        if self.serial_devices.number_of_plots[self.device_idx] == 0 {
+                            return;
+                        }
+                        self.serial_devices.number_of_plots[self.device_idx] =
+                            (self.serial_devices.number_of_plots[self.device_idx] - 1).clamp(0, 10);
+                    }
+                    ui.add(
+                        egui::DragValue::new(
+                            &mut self.serial_devices.number_of_plots[self.device_idx],
+                        )
+                        .range(0..=10),
+                    )
+                    .on_hover_text("Select the number of plots to be shown.");
+                    if ui
+                        .button(egui::RichText::new(
+                            egui_phosphor::regular::ARROW_FAT_RIGHT.to_string(),
+                        ))
+                        .clicked()
+                    {
+                        if self.serial_devices.number_of_plots[self.device_idx] == 10 {
+                            return;
+                        }
+                        self.serial_devices.number_of_plots[self.device_idx] =
+                            (self.serial_devices.number_of_plots[self.device_idx] + 1).clamp(0, 10);
+                    }
+                });
+                ui.end_row();
+                for graph_idx in 0..self.serial_devices.number_of_plots[self.device_idx] {
+                    ui.label(format!("Plot {graph_idx}: "));
+                    egui::ComboBox::from_id_salt(format!("Plot Mode {graph_idx}"))
+                        .selected_text(self.plot_modes[graph_idx].to_string())
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(
+                                &mut self.plot_modes[graph_idx],
+                                PlotMode::TimeSeries,
+                                PlotMode::TimeSeries.to_string(),
+                            );
+                            for quantity in CsiQuantity::ALL {
+                                let mode = PlotMode::Snapshot(quantity);
+                                ui.selectable_value(
+                                    &mut self.plot_modes[graph_idx],
+                                    mode,
+                                    mode.to_string(),
+                                );
+                            }
+                            for mode in [PlotMode::Spectrum, PlotMode::Spectrogram] {
+                                ui.selectable_value(
+                                    &mut self.plot_modes[graph_idx],
+                                    mode,
+                                    mode.to_string(),
+                                );
+                            }
+                        })
+                        .response
+                        .on_hover_text(
+                            "Plot the datasets over time, the latest CSI frames over the subcarriers or the frequency content of the datasets.",
+                        );
+                    ui.end_row();
+                }
+                if self.plot_modes[..self.serial_devices.number_of_plots[self.device_idx]]
+                    .iter()
+                    .any(|mode| matches!(mode, PlotMode::Snapshot(_)))
+                {
+                    ui.label("Overlaid frames [#]: ");
+                    ui.add(egui::DragValue::new(&mut self.snapshot_frames).range(1..=50))
+                        .on_hover_text("Number of CSI frames overlaid in the snapshot plots, older frames fade out.");
+                    ui.end_row();
+                }
+                let plot_modes = &self.plot_modes[..self.serial_devices.number_of_plots[self.device_idx]];
+                let show_spectrogram = plot_modes.contains(&PlotMode::Spectrogram);
+                if show_spectrogram || plot_modes.contains(&PlotMode::Spectrum) {
+                    ui.label("FFT window: ");
+                    egui::ComboBox::from_id_salt("FFT Window")
+                        .selected_text(self.spectrum.window.to_string())
+                        .show_ui(ui, |ui| {
+                            for window in Window::ALL {
+                                ui.selectable_value(
+                                    &mut self.spectrum.window,
+                                    window,
+                                    window.to_string(),
+                                );
+                            }
+                        });
+                    ui.end_row();
+                    ui.label("FFT time span [s]: ");
+                    ui.add(
+                        egui::DragValue::new(&mut self.spectrum.span)
+                            .speed(0.1)
+                            .range(1.0..=3600.0),
+                    )
+                    .on_hover_text("Analyse the latest data of this duration.");
+                    ui.end_row();
+                    ui.label("FFT datasets: ");
+                    ui.horizontal(|ui| {
+                        let last = self.data.dataset.len().saturating_sub(1);
+                        self.spectrum.last_dataset = self.spectrum.last_dataset.min(last);
+                        self.spectrum.first_dataset =
+                            self.spectrum.first_dataset.min(self.spectrum.last_dataset);
+                        ui.add(
+                            egui::DragValue::new(&mut self.spectrum.first_dataset)
+                                .range(0..=self.spectrum.last_dataset),
+                        )
+                        .on_hover_text("First dataset included in the spectrum.");
+                        ui.label("to");
+                        ui.add(
+                            egui::DragValue::new(&mut self.spectrum.last_dataset)
+                                .range(self.spectrum.first_dataset..=last),
+                        )
+                        .on_hover_text("Last dataset included in the spectrum.");
+                    });
+                    ui.end_row();
+                    ui.label("Resample rate [Hz]: ");
+                    ui.horizontal(|ui| {
+                        let mut auto = self.spectrum.resample_rate.is_none();
+                        ui.add(toggle(&mut auto)).on_hover_text(
+                            "Resample the irregular data to its mean sample rate before the FFT.",
+                        );
+                        ui.label("Auto");
+                        let mut rate = self.spectrum.resample_rate.unwrap_or(100.0);
+                        ui.add_enabled(
+                            !auto,
+                            egui::DragValue::new(&mut rate).speed(0.1).range(0.1..=10000.0),
+                        );
+                        self.spectrum.resample_rate = if auto { None } else { Some(rate) };
+                    });
+                    ui.end_row();
+                }
+                if show_spectrogram {
+                    ui.label("STFT segment [#]: ");
+                    ui.add(egui::DragValue::new(&mut self.spectrum.segment).range(8..=4096))
+                        .on_hover_text("Number of resampled samples of every spectrogram column.");
+                    ui.end_row();
+                    ui.label("STFT overlap: ");
+                    ui.add(egui::Slider::new(&mut self.spectrum.overlap, 0.0..=0.95));
+                    ui.end_row();
+                    if self.heatmap.placement == HeatmapPlacement::Hidden {
+                        ui.label("Follow: ");
+                        ui.add(toggle(&mut self.heatmap.follow))
+                            .on_hover_text("Keep the latest data in view.");
+                        ui.end_row();
+                    }
+                }
+                let old_settings = self.csi_settings;
+                self.csi_settings.config = CsiConfig {
+                    lltf: !self.csi_flags.disable_lltf,
+                    htltf: !self.csi_flags.disable_htltf,
+                    stbc_htltf: !self.csi_flags.disable_stbc_htltf,
+                    ltf_merge: !self.csi_flags.disable_ltf_merge,
+                };
+                ui.label("CSI data: ");
+                egui::ComboBox::from_id_salt("CSI Quantity")
+                    .selected_text(self.csi_settings.quantity.to_string())
+                    .show_ui(ui, |ui| {
+                        for quantity in CsiQuantity::ALL {
+                            ui.selectable_value(
+                                &mut self.csi_settings.quantity,
+                                quantity,
+                                quantity.to_string(),
+                            );
+                        }
+                    })
+                    .response
+                    .on_hover_text("Select the quantity derived from the CSI frames.");
+                ui.end_row();
+                ui.label("LTF segment: ");
+                egui::ComboBox::from_id_salt("CSI Segment")
+                    .selected_text(self.csi_settings.segment.to_string())
+                    .show_ui(ui, |ui| {
+                        for segment in LtfSegment::ALL {
+                            ui.selectable_value(
+                                &mut self.csi_settings.segment,
+                                segment,
+                                segment.to_string(),
+                            );
+                        }
+                    })
+                    .response
+                    .on_hover_text(
+                        "Select the long training field of the CSI frames, guard and null subcarriers are removed.",
+                    );
+                ui.end_row();
+                let sanitized_phase_shown = self.csi_settings.quantity
+                    == CsiQuantity::PhaseSanitized
+                    || self.plot_modes[..self.serial_devices.number_of_plots[self.device_idx]]
+                        .contains(&PlotMode::Snapshot(CsiQuantity::PhaseSanitized));
+                if sanitized_phase_shown {
+                    let sanitization = &mut self.csi_settings.sanitization;
+                    ui.label("Phase unwrapping: ");
+                    ui.add(toggle(&mut sanitization.unwrap))
+                        .on_hover_text("Remove the 2π jumps between adjacent subcarriers.");
+                    ui.end_row();
+                    ui.label("Linear detrend: ");
+                    ui.add(toggle(&mut sanitization.detrend)).on_hover_text(
+                        "Remove the linear fit over the subcarriers (CFO and STO).",
+                    );
+                    ui.end_row();
+                    ui.label("Normalization: ");
+                    egui::ComboBox::from_id_salt("Phase Normalization")
+                        .selected_text(sanitization.normalization.to_string())
+                        .show_ui(ui, |ui| {
+                            for normalization in PhaseNormalization::ALL {
+                                ui.selectable_value(
+                                    &mut sanitization.normalization,
+                                    normalization,
+                                    normalization.to_string(),
+                                );
+                            }
+                        })
+                        .response
+                        .on_hover_text("Remove the common phase offset using a reference subcarrier.");
+                    ui.end_row();
+                    if sanitization.normalization != PhaseNormalization::None {
+                        ui.label("Reference subcarrier: ");
+                        ui.add(egui::DragValue::new(&mut sanitization.reference).range(-64..=63));
+                        ui.end_row();
+                    }
+                }
+                if old_settings != self.csi_settings {
+                    if let Err(err) = self.csi_settings_tx.send(self.csi_settings) {
+                        log::error!("csi_settings_tx thread send failed: {:?}", err);
+                    }
+                    for pane in self.device_panes.iter() {
+                        if let Err(err) = pane.csi_settings_tx.send(self.csi_settings) {
+                            log::error!("csi_settings_tx thread send failed: {:?}", err);
+                        }
+                    }
+                    // force new labels for the new subcarriers
+                    self.labels.clear();
+                }
+                ui.label("Heatmap: ");
+                egui::ComboBox::from_id_salt("Heatmap Placement")
+                    .selected_text(self.heatmap.placement.to_string())
+                    .show_ui(ui, |ui| {
+                        for placement in [
+                            HeatmapPlacement::Hidden,
+                            HeatmapPlacement::BelowPlots,
+                            HeatmapPlacement::ReplacePlots,
+                        ] {
+                            ui.selectable_value(
+                                &mut self.heatmap.placement,
+                                placement,
+                                placement.to_string(),
+                            );
+                        }
+                    })
+                    .response
+                    .on_hover_text("Show the datasets as a heatmap over time.");
+                ui.end_row();
+                if self.heatmap.placement != HeatmapPlacement::Hidden {
+                    ui.label("Colormap: ");
+                    egui::ComboBox::from_id_salt("Heatmap Colormap")
+                        .selected_text(self.heatmap.colormap.to_string())
+                        .show_ui(ui, |ui| {
+                            for colormap in Colormap::ALL {
+                                ui.selectable_value(
+                                    &mut self.heatmap.colormap,
+                                    colormap,
+                                    colormap.to_string(),
+                                );
+                            }
+                        });
+                    ui.end_row();
+                    ui.label("Color scale: ");
+                    ui.horizontal(|ui| {
+                        ui.add(toggle(&mut self.heatmap.auto_scale))
+                            .on_hover_text("Scale the colors to the displayed values.");
+                        ui.label("Auto");
+                        ui.add_enabled(
+                            !self.heatmap.auto_scale,
+                            egui::DragValue::new(&mut self.heatmap.min).speed(0.1),
+                        )
+                        .on_hover_text("Value mapped to the lowest color.");
+                        ui.add_enabled(
+                            !self.heatmap.auto_scale,
+                            egui::DragValue::new(&mut self.heatmap.max).speed(0.1),
+                        )
+                        .on_hover_text("Value mapped to the highest color.");
+                    });
+                    ui.end_row();
+                    ui.label("Follow: ");
+                    ui.add(toggle(&mut self.heatmap.follow))
+                        .on_hover_text("Keep the latest data in view.");
+                    ui.end_row();
+                }
+            });
+        ui.add_space(25.0);
+
+        if self.labels.len() == 1 {
+            ui.label("Detected 1 Dataset:");
+        } else {
+            ui.label(format!("Detected {} Datasets:", self.labels.len()));
+        }
+        ui.add_space(5.0);
+        for i in 0..self.labels.len().min(10) {
+            // if init, set names to what has been stored in the device last time
+            if self.init {
+                // self.names_tx.send(self.labels.clone()).expect("Failed to send names");
+                self.init = false;
+            }
+
+            if self.labels.len() <= i {
+                break;
+            }
+            ui.horizontal(|ui| {
+                let response = color_picker_widget(ui, "", &mut self.colors, i);
+
+                // Check if the square was clicked and toggle color picker window
+                if response.clicked() {
+                    self.show_color_window = ColorWindow::ColorIndex(i);
+                };
+
+                if ui
+                    .add(
+                        egui::TextEdit::singleline(&mut self.labels[i])
+                            .desired_width(0.95 * RIGHT_PANEL_WIDTH),
+                    )
+                    .on_hover_text("Use custom names for your Datasets.")
+                    .changed()
+                {
+                    // self.names_tx.send(self.labels.clone()).expect("Failed to send names");
+                };
+            });
+        }
+        match self.show_color_window {
+            ColorWindow::NoShow => {}
+            ColorWindow::ColorIndex(index) => {
+                if color_picker_window(
+                    ui.ctx(),
+                    &mut self.colors[index],
+                    &mut self.color_vals[index],
+                ) {
+                    self.show_color_window = ColorWindow::NoShow;
+                }
+            }
+        }
+
+        if self.labels.len() > 10 {
+            ui.label("Only renaming up to 10 Datasets is currently supported.");
+        }
+    }
+
+    fn draw_filter_settings(&mut self, ui: &mut Ui) {
+        self.filter_chains.resize(self.labels.len(), vec![]);
+        if self.filter_target.is_some_and(|i| i >= self.labels.len()) {
+            self.filter_target = None;
+        }
+        egui::Grid::new("filter_settings")
+            .num_columns(2)
+            .spacing(Vec2 { x: 10.0, y: 10.0 })
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("Show: ");
+                egui::ComboBox::from_id_salt("Filter Display")
+                    .selected_text(self.filter_display.to_string())
+                    .show_ui(ui, |ui| {
+                        for display in [
+                            FilterDisplay::Raw,
+                            FilterDisplay::Filtered,
+                            FilterDisplay::Both,
+                        ] {
+                            ui.selectable_value(
+                                &mut self.filter_display,
+                                display,
+                                display.to_string(),
+                            );
+                        }
+                    })
+                    .response
+                    .on_hover_text("Plot the raw series, the filtered series or both.");
+                ui.end_row();
+                ui.label("Dataset: ");
+                let target_text = |target: Option<usize>| match target {
+                    Some(i) => self.labels[i].clone(),
+                    None => "All Datasets".to_string(),
+                };
+                egui::ComboBox::from_id_salt("Filter Target")
+                    .selected_text(target_text(self.filter_target))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.filter_target, None, target_text(None));
+                        for i in 0..self.labels.len() {
+                            ui.selectable_value(
+                                &mut self.filter_target,
+                                Some(i),
+                                target_text(Some(i)),
+                            );
+                        }
+                    })
+                    .response
+                    .on_hover_text("Edit the filter chain of a single dataset or of all datasets.");
+                ui.end_row();
+            });
+        ui.add_space(5.0);
+
+        let mut chain = self
+            .filter_chains
+            .get(self.filter_target.unwrap_or(0))
+            .cloned()
+            .unwrap_or_default();
+        let old_chain = chain.clone();
+        let mut remove = None;
+        for (idx, filter) in chain.iter_mut().enumerate() {
+            ui.horizontal_wrapped(|ui| {
+                if ui
+                    .button(egui_phosphor::regular::TRASH.to_string())
+                    .on_hover_text("Remove filter.")
+                    .clicked()
+                {
+                    remove = Some(idx);
+                }
+                ui.label(format!("{}. {filter}", idx + 1));
+                match filter {
+                    Filter::Hampel {
+                        half_window,
+                        threshold,
+                    } => {
+                        ui.label("k");
+                        ui.add(egui::DragValue::new(half_window).range(1..=100))
+                            .on_hover_text("Half window size [#].");
+                        ui.label("t");
+                        ui.add(egui::DragValue::new(threshold).speed(0.1).range(0.5..=10.0))
+                            .on_hover_text("Threshold in scaled median absolute deviations.");
+                    }
+                    Filter::MovingAverage { window } | Filter::MovingMedian { window } => {
+                        ui.label("n");
+                        ui.add(egui::DragValue::new(window).range(1..=500))
+                            .on_hover_text("Window size [#].");
+                    }
+                    Filter::LowPass { order, cutoff } => {
+                        ui.label("order");
+                        ui.add(egui::DragValue::new(order).range(1..=8));
+                        ui.label("fc");
+                        ui.add(
+                            egui::DragValue::new(cutoff)
+                                .speed(0.01)
+                                .range(0.01..=1000.0),
+                        )
+                        .on_hover_text("Cutoff frequency [Hz].");
+                    }
+                    Filter::BandPass { order, low, high } => {
+                        ui.label("order");
+                        ui.add(egui::DragValue::new(order).range(1..=8));
+                        ui.label("f");
+                        ui.add(egui::DragValue::new(low).speed(0.01).range(0.01..=*high))
+                            .on_hover_text("Lower cutoff frequency [Hz].");
+                        ui.add(egui::DragValue::new(high).speed(0.01).range(*low..=1000.0))
+                            .on_hover_text("Upper cutoff frequency [Hz].");
+                    }
+                    Filter::SavitzkyGolay { half_window, order } => {
+                        ui.label("k");
+                        ui.add(egui::DragValue::new(half_window).range(1..=100))
+                            .on_hover_text("Half window size [#].");
+                        ui.label("order");
+                        ui.add(egui::DragValue::new(order).range(0..=(2 * *half_window).min(6)))
+                            .on_hover_text("Polynomial order.");
+                    }
+                }
+            });
+        }
+        if let Some(idx) = remove {
+            chain.remove(idx);
+        }
+        ui.menu_button(
+            format!("{} Add Filter", egui_phosphor::regular::PLUS),
+            |ui| {
+                for filter in Filter::ALL {
+                    if ui.button(filter.to_string()).clicked() {
+                        chain.push(filter);
+                        ui.close_menu();
+                    }
+                }
+            },
+        );
+        if chain != old_chain {
+            match self.filter_target {
+                Some(i) => self.filter_chains[i] = chain,
+                None => self
+                    .filter_chains
+                    .iter_mut()
+                    .for_each(|c| *c = chain.clone()),
+            }
+        }
+    }
+
+    fn draw_breathing_panel(&mut self, ui: &mut Ui) {
+        let readout = match self.breathing.bpm {
+            Some(bpm) if self.breathing_settings.enabled => format!("{bpm:.1} BPM"),
+            _ => "-- BPM".to_string(),
+        };
+        ui.label(egui::RichText::new(readout).size(28.0).strong());
+        if !self.breathing.subcarriers.is_empty() {
+            let names: Vec<String> = self
+                .breathing
+                .subcarriers
+                .iter()
+                .map(|i| match self.data.csi_subcarriers.get(*i) {
+                    Some(index) if !self.data.csi_frames.is_empty() => format!("SC {index}"),
+                    _ => self.labels.get(*i).cloned().unwrap_or_default(),
+                })
+                .collect();
+            ui.label(format!("Using {}", names.join(", ")));
+        }
+        let t_fmt = |x: GridMark, _range: &RangeInclusive<f64>| format!("{:4.2} s", x.value);
+        Plot::new("breathing-trend")
+            .height(120.0)
+            .width(RIGHT_PANEL_WIDTH * 0.9)
+            .x_axis_formatter(t_fmt)
+            .y_axis_label("BPM")
+            .show(ui, |trend_plot_ui| {
+                trend_plot_ui.line(
+                    Line::new(PlotPoints::from(self.breathing.trend.clone()))
+                        .name("Rate")
+                        .color(COLORS[0]),
+                );
+            });
+        ui.add_space(5.0);
+
+        let settings = &mut self.breathing_settings;
+        egui::Grid::new("breathing_settings")
+            .num_columns(2)
+            .spacing(Vec2 { x: 10.0, y: 10.0 })
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("Enabled: ");
+                ui.add(toggle(&mut settings.enabled)).on_hover_text(
+                    "Estimate the rate from the amplitude of the best subcarriers once per second.",
+                );
+                ui.end_row();
+                ui.label("Preset: ");
+                let presets = [("Breathing", 0.1, 0.5), ("Heart Rate", 0.8, 2.0)];
+                let selected = presets
+                    .iter()
+                    .find(|(_, low, high)| settings.low == *low && settings.high == *high)
+                    .map_or("Custom", |(name, _, _)| name);
+                egui::ComboBox::from_id_salt("Breathing Preset")
+                    .selected_text(selected)
+                    .show_ui(ui, |ui| {
+                        for (name, low, high) in presets {
+                            if ui.selectable_label(selected == name, name).clicked() {
+                                settings.low = low;
+                                settings.high = high;
+                            }
+                        }
+                    });
+                ui.end_row();
+                ui.label("Band [Hz]: ");
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::DragValue::new(&mut settings.low)
+                            .speed(0.01)
+                            .range(0.01..=settings.high),
+                    );
+                    ui.label("to");
+                    ui.add(
+                        egui::DragValue::new(&mut settings.high)
+                            .speed(0.01)
+                            .range(settings.low..=10.0),
+                    );
+                });
+                ui.end_row();
+                ui.label("Time span [s]: ");
+                ui.add(egui::DragValue::new(&mut settings.span).range(5.0..=600.0))
+                    .on_hover_text("Analyse the latest data of this duration.");
+                ui.end_row();
+                ui.label("Subcarriers [#]: ");
+                ui.add(egui::DragValue::new(&mut settings.subcarriers).range(1..=64))
+                    .on_hover_text("Number of best ranked subcarriers used for the estimate.");
+                ui.end_row();
+                ui.label("Ranking: ");
+                egui::ComboBox::from_id_salt("Breathing Ranking")
+                    .selected_text(settings.ranking.to_string())
+                    .show_ui(ui, |ui| {
+                        for ranking in [SubcarrierRanking::Variance, SubcarrierRanking::Snr] {
+                            ui.selectable_value(
+                                &mut settings.ranking,
+                                ranking,
+                                ranking.to_string(),
+                            );
+                        }
+                    });
+                ui.end_row();
+                ui.label("Method: ");
+                egui::ComboBox::from_id_salt("Breathing Method")
+                    .selected_text(settings.method.to_string())
+                    .show_ui(ui, |ui| {
+                        for method in [RateMethod::FftPeak, RateMethod::PeakCounting] {
+                            ui.selectable_value(&mut settings.method, method, method.to_string());
+                        }
+                    });
+                ui.end_row();
+            });
+    }
+
+    fn draw_record_settings(&mut self, ui: &mut Ui) {
+        egui::Grid::new("record_settings")
+            .num_columns(2)
+            .spacing(Vec2 { x: 10.0, y: 10.0 })
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("File");
+                ui.horizontal(|ui| {
+                    ui.add_enabled_ui(self.recording.is_none(), |ui| {
+                        if ui
+                            .button(egui::RichText::new(format!(
+                                "{} Choose File",
+                                egui_phosphor::regular::FOLDER_OPEN
+                            )))
+                            .on_hover_text("Choose the file, parts are numbered.")
+                            .clicked()
+                        {
+                            self.file_dialog_state = FileDialogState::Record;
+                            self.file_dialog.save_file();
+                        }
+                    });
+                    ui.label(
+                        self.gui_conf
+                            .record
+                            .file_path
+                            .file_name()
+                            .unwrap_or_default()
+                            .to_string_lossy()
+                            .to_string(),
+                    )
+                    .on_hover_text(self.gui_conf.record.file_path.display().to_string());
                 });
                 ui.end_row();
-                ui.label("Number of plots [#]: ");
-
+                ui.label("Rotation");
                 ui.horizontal(|ui| {
+                    ui.add_enabled_ui(self.recording.is_none(), |ui| {
+                        egui::ComboBox::from_id_salt("Rotation")
+                            .selected_text(self.gui_conf.record.rotation.to_string())
+                            .show_ui(ui, |ui| {
+                                for rotation in Rotation::ALL {
+                                    let selected = std::mem::discriminant(&rotation)
+                                        == std::mem::discriminant(&self.gui_conf.record.rotation);
+                                    if ui
+                                        .selectable_label(selected, rotation.to_string())
+                                        .clicked()
+                                        && !selected
+                                    {
+                                        self.gui_conf.record.rotation = rotation;
+                                    }
+                                }
+                            });
+                        match &mut self.gui_conf.record.rotation {
+                            Rotation::Never => {}
+                            Rotation::Size(megabytes) => {
+                                ui.add(
+                                    egui::DragValue::new(megabytes)
+                                        .range(1..=100_000)
+                                        .suffix(" MB"),
+                                );
+                            }
+                            Rotation::Interval(minutes) => {
+                                ui.add(
+                                    egui::DragValue::new(minutes)
+                                        .range(1..=10_000)
+                                        .suffix(" min"),
+                                );
+                            }
+                        }
+                    });
+                });
+                ui.end_row();
+                if let Some(path) = &self.recording {
                     if ui
-                        .button(egui::RichText::new(
-                            egui_phosphor::regular::ARROW_FAT_LEFT.to_string(),
-                        ))
+                        .button(egui::RichText::new(format!(
+                            "{} Stop",
+                            egui_phosphor::regular::STOP
+                        )))
+                        .on_hover_text("Stop recording and complete the current part.")
                         .clicked()
                     {
-                        if self.serial_devices.number_of_plots[self.device_idx] == 0 {
-                            return;
+                        if let Err(err) = self.record_tx.send(None) {
+                            log::error!("record_tx thread send failed: {:?}", err);
                         }
-                        self.serial_devices.number_of_plots[self.device_idx] =
-                            (self.serial_devices.number_of_plots[self.device_idx] - 1).clamp(0, 10);
                     }
-                    ui.add(
-                        egui::DragValue::new(
-                            &mut self.serial_devices.number_of_plots[self.device_idx],
-                        )
-                        .range(0..=10),
-                    )
-                    .on_hover_text("Select the number of plots to be shown.");
+                    ui.colored_label(
+                        Color32::RED,
+                        format!(
+                            "Recording to {}",
+                            path.file_name().unwrap_or_default().to_string_lossy()
+                        ),
+                    );
+                } else {
+                    let ready = self.connected_to_device
+                        && !self.gui_conf.record.file_path.as_os_str().is_empty();
                     if ui
-                        .button(egui::RichText::new(
-                            egui_phosphor::regular::ARROW_FAT_RIGHT.to_string(),
-                        ))
+                        .add_enabled(
+                            ready,
+                            egui::Button::new(egui::RichText::new(format!(
+                                "{} Record",
+                                egui_phosphor::regular::RECORD
+                            ))),
+                        )
+                        .on_hover_text(
+                            "Append the received rows and raw traffic to the file while connected.",
+                        )
                         .clicked()
                     {
-                        if self.serial_devices.number_of_plots[self.device_idx] == 10 {
-                            return;
+                        if let Err(err) = self.record_tx.send(Some(RecordOptions {
+                            file_path: self.gui_conf.record.file_path.clone(),
+                            rotation: self.gui_conf.record.rotation,
+                            names: self.labels.clone(),
+                        })) {
+                            log::error!("record_tx thread send failed: {:?}", err);
                         }
-                        self.serial_devices.number_of_plots[self.device_idx] =
-                            (self.serial_devices.number_of_plots[self.device_idx] + 1).clamp(0, 10);
                     }
-                });
+                }
                 ui.end_row();
             });
-        ui.add_space(25.0);
-
-        if self.labels.len() == 1 {
-            ui.label("Detected 1 Dataset:");
-        } else {
-            ui.label(format!("Detected {} Datasets:", self.labels.len()));
-        }
-        ui.add_space(5.0);
-        for i in 0..self.labels.len().min(10) {
-            // if init, set names to what has been stored in the device last time
-            if self.init {
-                // self.names_tx.send(self.labels.clone()).expect("Failed to send names");
-                self.init = false;
-            }
-
-            if self.labels.len() <= i {
-                break;
-            }
-            ui.horizontal(|ui| {
-                let response = color_picker_widget(ui, "", &mut self.colors, i);
-
-                // Check if the square was clicked and toggle color picker window
-                if response.clicked() {
-                    self.show_color_window = ColorWindow::ColorIndex(i);
-                };
+    }
 
-                if ui
-                    .add(
-                        egui::TextEdit::singleline(&mut self.labels[i])
-                            .desired_width(0.95 * RIGHT_PANEL_WIDTH),
-                    )
-                    .on_hover_text("Use custom names for your Datasets.")
-                    .changed()
-                {
-                    // self.names_tx.send(self.labels.clone()).expect("Failed to send names");
-                };
+    fn draw_motion_settings(&mut self, ui: &mut Ui) {
+        let old_settings = self.motion_settings;
+        egui::Grid::new("motion_settings")
+            .num_columns(2)
+            .spacing(Vec2 { x: 10.0, y: 10.0 })
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("Enabled: ");
+                ui.add(toggle(&mut self.motion_settings.enabled))
+                    .on_hover_text("Detect motion from the variance of the CSI amplitudes.");
+                ui.end_row();
+                ui.label("Window [#]: ");
+                ui.add(egui::DragValue::new(&mut self.motion_settings.window).range(2..=1000))
+                    .on_hover_text("Number of CSI frames in the sliding window.");
+                ui.end_row();
+                ui.label("Threshold [%]: ");
+                ui.add(
+                    egui::DragValue::new(&mut self.motion_settings.threshold)
+                        .speed(0.1)
+                        .range(0.0..=100.0),
+                )
+                .on_hover_text(
+                    "Motion is detected while the mean relative standard deviation of the subcarrier amplitudes is above this value.",
+                );
+                ui.end_row();
+                ui.label("Score [%]: ");
+                ui.label(format!("{:.1}", self.data.motion.score));
+                ui.end_row();
+                ui.label("Deviation [%]: ");
+                ui.label(format!("{:.1}", self.data.motion.deviation))
+                    .on_hover_text("Mean deviation of the latest frame from the moving average.");
+                ui.end_row();
             });
-        }
-        match self.show_color_window {
-            ColorWindow::NoShow => {}
-            ColorWindow::ColorIndex(index) => {
-                if color_picker_window(
-                    ui.ctx(),
-                    &mut self.colors[index],
-                    &mut self.color_vals[index],
-                ) {
-                    self.show_color_window = ColorWindow::NoShow;
-                }
+        if old_settings != self.motion_settings {
+            if let Err(err) = self.motion_settings_tx.send(self.motion_settings) {
+                log::error!("motion_settings_tx thread send failed: {:?}", err);
             }
         }
-
-        if self.labels.len() > 10 {
-            ui.label("Only renaming up to 10 Datasets is currently supported.");
-        }
     }
 
     fn draw_highlight_settings(&mut self, _ctx: &egui::Context, ui: &mut Ui) {
@@ -1229,578 +3761,60 @@ impl MyApp {
 
                         self.draw_global_settings(ui);
                         ui.add_space(10.0);
-                    // Add this to your update function or wherever you build your right panel UI
-                        // --- Start Section ---
-                        // ui.add_space(16.0);
-                        // if ui.add_sized([RIGHT_PANEL_WIDTH * 0.9, 40.0], egui::Button::new("START")).clicked() {
-                        //     let _ = self.send_tx.send("start\r\n".to_string());
-                        // }
-                        
-                        ui.add_space(16.0);
-                        if ui.add_sized([RIGHT_PANEL_WIDTH * 0.9, 40.0], egui::Button::new("START")).clicked() {
-                            use std::{thread, time::Duration};
-
-                            // 1. set-traffic (only if enabled in section)
-                            // if self.enable_set_traffic && self.traffic_enable {
-                            //     let mut cmd = String::from("set-traffic --enable");
-                            //     cmd.push_str(&format!(" --type={}", match self.traffic_type {
-                            //         0 => "icmp",
-                            //         1 => "udp",
-                            //         _ => "unknown",
-                            //     }));
-                            //     if !self.traffic_interval.trim().is_empty() {
-                            //         cmd.push_str(&format!(" --interval={}", self.traffic_interval.trim()));
-                            //     }
-                            //     cmd.push_str("\r\n");
-                            //     let _ = self.send_tx.send(cmd);
-                            //     thread::sleep(Duration::from_millis(150));
-                            // }
-                            if self.traffic_enable {
-                            let mut cmd = String::from("set-traffic --enable");
-                            cmd.push_str(&format!(" --type={}", match self.traffic_type {
-                                0 => "icmp",
-                                1 => "udp",
-                                _ => "unknown",
-                            }));
-                            if !self.traffic_interval.trim().is_empty() {
-                                cmd.push_str(&format!(" --interval={}", self.traffic_interval.trim()));
-                            }
-                            cmd.push_str("\r\n");
-                            let _ = self.send_tx.send(cmd);
-                            thread::sleep(Duration::from_millis(150));
-                        }
-
-                            // 2. set-network (only if enabled in section)
-                            // if self.enable_set_network {
-                            //     let arch = match self.network_architecture {
-                            //         0 => "rsta",
-                            //         1 => "rapsta",
-                            //         2 => "apsta",
-                            //         3 => "sniff",
-                            //         _ => "",
-                            //     };
-                            //     if !arch.is_empty() {
-                            //         let cmd = format!("set-network --arch={}\r\n", arch);
-                            //         let _ = self.send_tx.send(cmd);
-                            //         thread::sleep(Duration::from_millis(150));
-                            //     }
-                            // }
-                            // 2. set-network (always send)
-                            let arch = match self.network_architecture {
-                                0 => "rsta",
-                                1 => "rapsta",
-                                2 => "apsta",
-                                3 => "sniff",
-                                _ => "",
-                            };
-                            if !arch.is_empty() {
-                                let cmd = format!("set-network --arch={}\r\n", arch);
-                                let _ = self.send_tx.send(cmd);
-                                thread::sleep(Duration::from_millis(150));
-                            }
-
-                            // // 3. set-csi (only if enabled in section)
-                            // if self.enable_set_csi && (self.csi_disable_lltf || self.csi_disable_htltf || self.csi_disable_stbc_htltf || self.csi_disable_ltf_merge) {
-                            //     let mut cmd = String::from("set-csi");
-                            //     if self.csi_disable_lltf {
-                            //         cmd.push_str(" --disable-lltf");
-                            //     }
-                            //     if self.csi_disable_htltf {
-                            //         cmd.push_str(" --disable-htltf");
-                            //     }
-                            //     if self.csi_disable_stbc_htltf {
-                            //         cmd.push_str(" --disable-stbc-htltf");
-                            //     }
-                            //     if self.csi_disable_ltf_merge {
-                            //         cmd.push_str(" --disable-ltf-merge");
-                            //     }
-                            //     cmd.push_str("\r\n");
-                            //     let _ = self.send_tx.send(cmd);
-                            //     thread::sleep(Duration::from_millis(150));
-                            // }
-
-                            // 3. set-csi (send only if at least one option is checked)
-                            if self.csi_disable_lltf || self.csi_disable_htltf || self.csi_disable_stbc_htltf || self.csi_disable_ltf_merge {
-                                let mut cmd = String::from("set-csi");
-                                if self.csi_disable_lltf {
-                                    cmd.push_str(" --disable-lltf");
-                                }
-                                if self.csi_disable_htltf {
-                                    cmd.push_str(" --disable-htltf");
-                                }
-                                if self.csi_disable_stbc_htltf {
-                                    cmd.push_str(" --disable-stbc-htltf");
-                                }
-                                if self.csi_disable_ltf_merge {
-                                    cmd.push_str(" --disable-ltf-merge");
-                                }
-                                cmd.push_str("\r\n");
-                                let _ = self.send_tx.send(cmd);
-                                thread::sleep(Duration::from_millis(150));
-                            }
-
-                            // 4. set-wifi (only if enabled in section)
-                            if self.enable_set_wifi {
-                                use std::{thread, time::Duration};
-                                let mode_str = match self.wifi_mode {
-                                    0 => "ap",
-                                    1 => "station",
-                                    2 => "sniffer",
-                                    3 => "ap-station",
-                                    _ => "sniffer",
-                                };
-
-                                // Always send mode first
-                                let cmd = format!("set-wifi --mode={}\r\n", mode_str);
-                                let _ = self.send_tx.send(cmd);
-                                thread::sleep(Duration::from_millis(150));
-
-                                // Only send if not sniffer
-                                if self.wifi_mode != 2 {
-                                    if !self.wifi_max_connections.trim().is_empty() {
-                                        let cmd = format!("set-wifi --max-connections={}\r\n", self.wifi_max_connections.trim());
-                                        let _ = self.send_tx.send(cmd);
-                                        thread::sleep(Duration::from_millis(150));
-                                    }
-                                    if self.wifi_hide_ssid {
-                                        let cmd = "set-wifi --hide-ssid\r\n".to_string();
-                                        let _ = self.send_tx.send(cmd);
-                                        thread::sleep(Duration::from_millis(150));
-                                    }
-                                }
-
-                                // AP options
-                                if self.wifi_mode == 0 || self.wifi_mode == 3 {
-                                    if !self.wifi_ap_ssid.trim().is_empty() {
-                                        let cmd = format!("set-wifi --ap-ssid={}\r\n", self.wifi_ap_ssid.trim());
-                                        let _ = self.send_tx.send(cmd);
-                                        thread::sleep(Duration::from_millis(150));
-                                    }
-                                    if !self.wifi_ap_password.trim().is_empty() {
-                                        let cmd = format!("set-wifi --ap-password={}\r\n", self.wifi_ap_password.trim());
-                                        let _ = self.send_tx.send(cmd);
-                                        thread::sleep(Duration::from_millis(150));
-                                    }
-                                }
-
-                                // STA options
-                                if self.wifi_mode == 1 || self.wifi_mode == 3 {
-                                    if !self.wifi_sta_ssid.trim().is_empty() {
-                                        let cmd = format!("set-wifi --sta-ssid={}\r\n", self.wifi_sta_ssid.trim());
-                                        let _ = self.send_tx.send(cmd);
-                                        thread::sleep(Duration::from_millis(150));
-                                    }
-                                    if !self.wifi_sta_password.trim().is_empty() {
-                                        let cmd = format!("set-wifi --sta-password={}\r\n", self.wifi_sta_password.trim());
-                                        let _ = self.send_tx.send(cmd);
-                                        thread::sleep(Duration::from_millis(150));
-                                    }
-                                }
-                            }
-
-                            // Optionally, send "start" at the end
-                            let _ = self.send_tx.send("start\r\n".to_string());
-                        }
-
+                        self.draw_esp_commands(ui);
 
-                        // --- Set Traffic Section ---
-                        ui.vertical(|ui| {
-                        //     ui.heading("Set Traffic");
-                        //     ui.checkbox(&mut self.enable_set_traffic, "Enable Set Traffic");
-                        //     ui.checkbox(&mut self.traffic_enable, "Traffic Enable");
-                        //     ui.horizontal(|ui| {
-                        //         ui.label("Traffic Type:");
-                        //         egui::ComboBox::from_id_source("traffic_type_combo")
-                        //             .selected_text(match self.traffic_type {
-                        //                 0 => "icmp",
-                        //                 1 => "UDP",
-                        //                 _ => "Unknown",
-                        //             })
-                        //             .show_ui(ui, |ui| {
-                        //                 ui.selectable_value(&mut self.traffic_type, 0, "icmp");
-                        //                 ui.selectable_value(&mut self.traffic_type, 1, "UDP");
-                        //             });
-                        //     });
-                        //     ui.horizontal(|ui| {
-                        //         ui.label("Interval (ms):");
-                        //         ui.text_edit_singleline(&mut self.traffic_interval);
-                        //     });
-                        // if ui.button("Set Traffic").clicked() && self.enable_set_traffic {
-                        //     let mut cmd = String::from("set-traffic");
-                        //     if self.traffic_enable {
-                        //         cmd.push_str(" --enable");
-                        //     }
-                        //     cmd.push_str(&format!(" --type={}", match self.traffic_type {
-                        //         0 => "icmp",
-                        //         1 => "udp",
-                        //         _ => "unknown",
-                        //     }));
-                        //     if !self.traffic_interval.trim().is_empty() {
-                        //         cmd.push_str(&format!(" --interval={}", self.traffic_interval.trim()));
-                        //     }
-                        //     cmd.push_str("\r\n");
-                        //     let _ = self.send_tx.send(cmd);
-                        // }
-                        // --- Set Traffic Section ---
-                        ui.heading("Set Traffic");
-                        ui.checkbox(&mut self.traffic_enable, "Enable Traffic");
-                        ui.add_enabled_ui(self.traffic_enable, |ui| {
-                            ui.horizontal(|ui| {
-                                ui.label("Traffic Type:");
-                                egui::ComboBox::from_id_source("traffic_type_combo")
-                                    .selected_text(match self.traffic_type {
-                                        0 => "icmp",
-                                        1 => "UDP",
-                                        _ => "Unknown",
-                                    })
-                                    .show_ui(ui, |ui| {
-                                        ui.selectable_value(&mut self.traffic_type, 0, "icmp");
-                                        ui.selectable_value(&mut self.traffic_type, 1, "UDP");
-                                    });
-                            });
-                            ui.horizontal(|ui| {
-                                ui.label("Interval (ms):");
-                                ui.text_edit_singleline(&mut self.traffic_interval);
+                        CollapsingHeader::new("Device Profiles")
+                            .default_open(false)
+                            .show(ui, |ui| {
+                                self.draw_profiles(ui);
                             });
-                        });
 
-                            ui.add_space(16.0); // Space between sections
-
-                            // --- Set Network Section ---
-                            ui.heading("Set Network");
-                            //ui.checkbox(&mut self.enable_set_network, "Enable Set Network");
-                            ui.horizontal(|ui| {
-                                ui.label("Architecture:");
-                                egui::ComboBox::from_id_source("architecture_combo")
-                                    .selected_text(match self.network_architecture {
-                                        0 => "rsta",
-                                        1 => "rapsta",
-                                        2 => "apsta",
-                                        3 => "sniff",
-                                        _ => "Unknown",
-                                    })
-                                    .show_ui(ui, |ui| {
-                                        ui.selectable_value(&mut self.network_architecture, 0, "rsta");
-                                        ui.selectable_value(&mut self.network_architecture, 1, "rapsta");
-                                        ui.selectable_value(&mut self.network_architecture, 2, "apsta");
-                                        ui.selectable_value(&mut self.network_architecture, 3, "sniff");
-                                    });
+                        CollapsingHeader::new("Plot Settings")
+                            .default_open(true)
+                            .show(ui, |ui| {
+                                self.draw_plot_settings(ui);
                             });
-                            if ui.button("Set Network").clicked() && self.enable_set_network {
-                                let arch = match self.network_architecture {
-                                    0 => "rsta",
-                                    1 => "rapsta",
-                                    2 => "apsta",
-                                    3 => "sniff",
-                                    _ => "unknown",
-                                };
-                                let cmd = format!("set-network --arch={}\r\n", arch);
-                                let _ = self.send_tx.send(cmd);
-                            }
-                        });
-                        ui.add_space(16.0); // Space between sections
-
-                    //     // --- Set CSI Section ---
-                    //     ui.heading("Set CSI");
-
-                    //     ui.horizontal(|ui| {
-                    //     if ui.button("Disable LLTF").clicked() {
-                    //         // TODO: handle --disable-lltf
-                    //     }
-                    //     if ui.button("Disable HTLTF").clicked() {
-                    //         // TODO: handle --disable-htltf
-                    //     }
-                    // });
-                    //     ui.horizontal(|ui| {
-                    //         if ui.button("Disable STBC HTLTF").clicked() {
-                    //             // TODO: handle --disable-stbc-htltf
-                    //         }
-                    //         if ui.button("Disable LTF Merge").clicked() {
-                    //             // TODO: handle --disable-ltf-merge
-                    //         }
-                    //     });
-
-                    // --- Set CSI Section ---
-                    // ui.heading("Set CSI");
-                    // ui.checkbox(&mut self.enable_set_csi, "Enable Set CSI");
-                    // ui.checkbox(&mut self.csi_disable_lltf, "Disable LLTF");
-                    // ui.checkbox(&mut self.csi_disable_htltf, "Disable HTLTF");
-                    // ui.checkbox(&mut self.csi_disable_stbc_htltf, "Disable STBC HTLTF");
-                    // ui.checkbox(&mut self.csi_disable_ltf_merge, "Disable LTF Merge");
-                    ui.heading("Set CSI");
-                    ui.checkbox(&mut self.csi_disable_lltf, "Disable LLTF");
-                    ui.checkbox(&mut self.csi_disable_htltf, "Disable HTLTF");
-                    ui.checkbox(&mut self.csi_disable_stbc_htltf, "Disable STBC HTLTF");
-                    ui.checkbox(&mut self.csi_disable_ltf_merge, "Disable LTF Merge");
-                    if ui.button("Set CSI").clicked() && self.enable_set_csi {
-                        let mut cmd = String::from("set-csi");
-                        if self.csi_disable_lltf {
-                            cmd.push_str(" --disable-lltf");
-                        }
-                        if self.csi_disable_htltf {
-                            cmd.push_str(" --disable-htltf");
-                        }
-                        if self.csi_disable_stbc_htltf {
-                            cmd.push_str(" --disable-stbc-htltf");
-                        }
-                        if self.csi_disable_ltf_merge {
-                            cmd.push_str(" --disable-ltf-merge");
-                        }
-                        cmd.push_str("\r\n");
-                        let _ = self.send_tx.send(cmd);
-                    }
-                        
-                        ui.add_space(16.0);
-
-                        // --- Set WiFi Section ---
-                        // ui.heading("Set WiFi");
-                        // ui.checkbox(&mut self.enable_set_wifi, "Enable Set WiFi");
-                        // ui.horizontal(|ui| {
-                        //     ui.label("Mode:");
-                        //     egui::ComboBox::from_id_source("wifi_mode_combo")
-                        //         .selected_text(match self.wifi_mode {
-                        //             0 => "ap",
-                        //             1 => "station",
-                        //             2 => "sniffer",
-                        //             3 => "ap-station",
-                        //             _ => "sniffer",
-                        //         })
-                        //         .show_ui(ui, |ui| {
-                        //             ui.selectable_value(&mut self.wifi_mode, 0, "ap");
-                        //             ui.selectable_value(&mut self.wifi_mode, 1, "station");
-                        //             ui.selectable_value(&mut self.wifi_mode, 2, "sniffer");
-                        //             ui.selectable_value(&mut self.wifi_mode, 3, "ap-station");
-                        //         });
-                        // });
-                        // ui.horizontal(|ui| {
-                        //     ui.label("Max Connections:");
-                        //     ui.text_edit_singleline(&mut self.wifi_max_connections);
-                        // });
-                        // ui.checkbox(&mut self.wifi_hide_ssid, "Hide SSID (AP only)");
-                        // ui.horizontal(|ui| {
-                        //     ui.label("AP SSID:");
-                        //     ui.text_edit_singleline(&mut self.wifi_ap_ssid);
-                        // });
-                        // ui.horizontal(|ui| {
-                        //     ui.label("AP Password:");
-                        //     ui.text_edit_singleline(&mut self.wifi_ap_password);
-                        // });
-                        // ui.horizontal(|ui| {
-                        //     ui.label("STA SSID:");
-                        //     ui.text_edit_singleline(&mut self.wifi_sta_ssid);
-                        // });
-                        // ui.horizontal(|ui| {
-                        //     ui.label("STA Password:");
-                        //     ui.text_edit_singleline(&mut self.wifi_sta_password);
-                        // });
-                        // if ui.button("Set WiFi").clicked() && self.enable_set_wifi {
-                        //     let mut cmd = String::from("set-wifi");
-                        //     cmd.push_str(&format!(" --mode={}", match self.wifi_mode {
-                        //         0 => "ap",
-                        //         1 => "station",
-                        //         2 => "sniffer",
-                        //         3 => "ap-station",
-                        //         _ => "sniffer",
-                        //     }));
-                        //     if !self.wifi_max_connections.trim().is_empty() {
-                        //         cmd.push_str(&format!(" --max-connections={}", self.wifi_max_connections.trim()));
-                        //     }
-                        //     if self.wifi_hide_ssid {
-                        //         cmd.push_str(" --hide-ssid");
-                        //     }
-                        //     if !self.wifi_ap_ssid.trim().is_empty() {
-                        //         cmd.push_str(&format!(" --ap-ssid={}", self.wifi_ap_ssid.trim()));
-                        //     }
-                        //     if !self.wifi_ap_password.trim().is_empty() {
-                        //         cmd.push_str(&format!(" --ap-password={}", self.wifi_ap_password.trim()));
-                        //     }
-                        //     if !self.wifi_sta_ssid.trim().is_empty() {
-                        //         cmd.push_str(&format!(" --sta-ssid={}", self.wifi_sta_ssid.trim()));
-                        //     }
-                        //     if !self.wifi_sta_password.trim().is_empty() {
-                        //         cmd.push_str(&format!(" --sta-password={}", self.wifi_sta_password.trim()));
-                        //     }
-                        //     cmd.push_str("\r\n");
-                        //     let _ = self.send_tx.send(cmd);
-                        // }
-
-                        ui.heading("Set WiFi");
-                        ui.checkbox(&mut self.enable_set_wifi, "Enable Set WiFi");
-
-                        ui.horizontal(|ui| {
-                            ui.label("Mode:");
-                            egui::ComboBox::from_id_source("wifi_mode_combo")
-                                .selected_text(match self.wifi_mode {
-                                    0 => "ap",
-                                    1 => "station",
-                                    2 => "sniffer",
-                                    3 => "ap-station",
-                                    _ => "sniffer",
-                                })
-                                .show_ui(ui, |ui| {
-                                    ui.selectable_value(&mut self.wifi_mode, 0, "ap");
-                                    ui.selectable_value(&mut self.wifi_mode, 1, "station");
-                                    ui.selectable_value(&mut self.wifi_mode, 2, "sniffer");
-                                    ui.selectable_value(&mut self.wifi_mode, 3, "ap-station");
-                                });
-                        });
-
-                        // Logic for graying out and clearing fields
-                        let is_sniffer = self.wifi_mode == 2;
-                        let is_station = self.wifi_mode == 1;
-                        let is_ap = self.wifi_mode == 0;
-                        let is_apsta = self.wifi_mode == 3;
-
-                        if is_sniffer {
-                            self.wifi_max_connections.clear();
-                            self.wifi_hide_ssid = false;
-                            self.wifi_ap_ssid.clear();
-                            self.wifi_ap_password.clear();
-                            self.wifi_sta_ssid.clear();
-                            self.wifi_sta_password.clear();
-                        }
-                        if is_station {
-                            self.wifi_ap_ssid.clear();
-                            self.wifi_ap_password.clear();
-                        }
-                        if is_ap {
-                            self.wifi_sta_ssid.clear();
-                            self.wifi_sta_password.clear();
-                        }
 
-                        // Max Connections
-                        ui.horizontal(|ui| {
-                            ui.label("Max Connections:");
-                            ui.add_enabled_ui(!is_sniffer, |ui| {
-                                ui.text_edit_singleline(&mut self.wifi_max_connections);
+                        CollapsingHeader::new("Filters")
+                            .default_open(false)
+                            .show(ui, |ui| {
+                                self.draw_filter_settings(ui);
                             });
-                        });
-
-                        // Hide SSID
-                        ui.add_enabled_ui(!is_sniffer, |ui| {
-                            ui.checkbox(&mut self.wifi_hide_ssid, "Hide SSID (AP only)");
-                        });
 
-                        // AP SSID and Password
-                        ui.horizontal(|ui| {
-                            ui.label("AP SSID:");
-                            ui.add_enabled_ui(is_ap || is_apsta, |ui| {
-                                ui.text_edit_singleline(&mut self.wifi_ap_ssid);
-                            });
-                        });
-                        ui.horizontal(|ui| {
-                            ui.label("AP Password:");
-                            ui.add_enabled_ui(is_ap || is_apsta, |ui| {
-                                ui.text_edit_singleline(&mut self.wifi_ap_password);
+                        CollapsingHeader::new("Breathing Rate")
+                            .default_open(false)
+                            .show(ui, |ui| {
+                                self.draw_breathing_panel(ui);
                             });
-                        });
 
-                        // STA SSID and Password
-                        ui.horizontal(|ui| {
-                            ui.label("STA SSID:");
-                            ui.add_enabled_ui(is_station || is_apsta, |ui| {
-                                ui.text_edit_singleline(&mut self.wifi_sta_ssid);
-                            });
-                        });
-                        ui.horizontal(|ui| {
-                            ui.label("STA Password:");
-                            ui.add_enabled_ui(is_station || is_apsta, |ui| {
-                                ui.text_edit_singleline(&mut self.wifi_sta_password);
+                        CollapsingHeader::new("Motion Detection")
+                            .default_open(false)
+                            .show(ui, |ui| {
+                                self.draw_motion_settings(ui);
                             });
-                        });
-
-                        if ui.button("Set WiFi").clicked() && self.enable_set_wifi {
-                            use std::{thread, time::Duration};
-                            let mode_str = match self.wifi_mode {
-                                0 => "ap",
-                                1 => "station",
-                                2 => "sniffer",
-                                3 => "ap-station",
-                                _ => "sniffer",
-                            };
-
-                            // Always send mode first
-                            let cmd = format!("set-wifi --mode={}\r\n", mode_str);
-                            let _ = self.send_tx.send(cmd);
-                            thread::sleep(Duration::from_millis(150));
-
-                            // Only send if not sniffer
-                            if self.wifi_mode != 2 {
-                                if !self.wifi_max_connections.trim().is_empty() {
-                                    let cmd = format!("set-wifi --max-connections={}\r\n", self.wifi_max_connections.trim());
-                                    let _ = self.send_tx.send(cmd);
-                                    thread::sleep(Duration::from_millis(150));
-                                }
-                                if self.wifi_hide_ssid {
-                                    let cmd = "set-wifi --hide-ssid\r\n".to_string();
-                                    let _ = self.send_tx.send(cmd);
-                                    thread::sleep(Duration::from_millis(150));
-                                }
-                            }
-
-                            // AP options
-                            if self.wifi_mode == 0 || self.wifi_mode == 3 {
-                                if !self.wifi_ap_ssid.trim().is_empty() {
-                                    let cmd = format!("set-wifi --ap-ssid={}\r\n", self.wifi_ap_ssid.trim());
-                                    let _ = self.send_tx.send(cmd);
-                                    thread::sleep(Duration::from_millis(150));
-                                }
-                                if !self.wifi_ap_password.trim().is_empty() {
-                                    let cmd = format!("set-wifi --ap-password={}\r\n", self.wifi_ap_password.trim());
-                                    let _ = self.send_tx.send(cmd);
-                                    thread::sleep(Duration::from_millis(150));
-                                }
-                            }
-
-                            // STA options
-                            if self.wifi_mode == 1 || self.wifi_mode == 3 {
-                                if !self.wifi_sta_ssid.trim().is_empty() {
-                                    let cmd = format!("set-wifi --sta-ssid={}\r\n", self.wifi_sta_ssid.trim());
-                                    let _ = self.send_tx.send(cmd);
-                                    thread::sleep(Duration::from_millis(150));
-                                }
-                                if !self.wifi_sta_password.trim().is_empty() {
-                                    let cmd = format!("set-wifi --sta-password={}\r\n", self.wifi_sta_password.trim());
-                                    let _ = self.send_tx.send(cmd);
-                                    thread::sleep(Duration::from_millis(150));
-                                }
-                            }
-                        }
-                        
-                        //this does not work yet
-                        // --- Ctrl R Section ---
-                        ui.add_space(16.0);
-                        ui.heading("Ctrl R");
-                        if ui.button("Send Ctrl R").clicked() {
-                            let _ = self.send_tx.send("__RESET__\r\n".to_string()); // send Ctrl+R to the device
-                        }
 
-                        
-                        // --- Show Config Section ---
-                        ui.add_space(16.0);
-                        ui.heading("Show Config");
-                        if ui.button("Show Config").clicked() {
-                            let _ = self.send_tx.send("show-config\r\n".to_string());
-                        }
+                        CollapsingHeader::new("Text Highlight Settings")
+                            .default_open(true)
+                            .show(ui, |ui| {
+                                self.draw_highlight_settings(ctx, ui);
+                            });
 
-                        ui.add_space(16.0);
-                        ui.heading("Rest Config");
-                        if ui.button("Reset Config").clicked() {
-                            let _ = self.send_tx.send("reset-config\r\n".to_string());
-                        }
-                        
-                        
+                        CollapsingHeader::new("Additional Devices")
+                            .default_open(false)
+                            .show(ui, |ui| {
+                                self.draw_device_panes(ctx, ui);
+                            });
 
-                        CollapsingHeader::new("Plot Settings")
-                            .default_open(true)
+                        CollapsingHeader::new("CSI Fusion")
+                            .default_open(false)
                             .show(ui, |ui| {
-                                self.draw_plot_settings(ui);
+                                self.draw_fusion_settings(ui);
                             });
 
-                        CollapsingHeader::new("Text Highlight Settings")
-                            .default_open(true)
+                        CollapsingHeader::new("Recording")
+                            .default_open(false)
                             .show(ui, |ui| {
-                                self.draw_highlight_settings(ctx, ui);
+                                self.draw_record_settings(ui);
                             });
 
                         CollapsingHeader::new("Export Settings")
@@ -1832,6 +3846,27 @@ impl MyApp {
                                 }
                             }
                         }
+                        FileDialogState::OpenElf => {
+                            if let Some(path) = self.file_dialog.update(ctx).picked() {
+                                self.serial_devices.devices[self.device_idx].elf_path =
+                                    Some(path.to_path_buf());
+                                self.file_dialog_state = FileDialogState::None;
+                            }
+                        }
+                        FileDialogState::OpenReplay => {
+                            if let Some(path) = self.file_dialog.update(ctx).picked() {
+                                self.serial_devices.devices[self.device_idx].replay_path =
+                                    Some(path.to_path_buf());
+                                self.file_dialog_state = FileDialogState::None;
+                            }
+                        }
+                        FileDialogState::Record => {
+                            if let Some(path) = self.file_dialog.update(ctx).picked() {
+                                self.gui_conf.record.file_path = path.to_path_buf();
+                                self.gui_conf.record.file_path.set_extension("csv");
+                                self.file_dialog_state = FileDialogState::None;
+                            }
+                        }
                         FileDialogState::SavePlot => {
                             if let Some(path) = self.file_dialog.update(ctx).picked() {
                                 self.picked_path = path.to_path_buf();
@@ -1853,12 +3888,101 @@ impl MyApp {
                                     file_path: self.picked_path.clone(),
                                     save_absolute_time: self.gui_conf.save_absolute_time,
                                     save_raw_traffic: self.save_raw,
+                                    traffic_format: self.gui_conf.traffic_format,
                                     names: self.labels.clone(),
                                 }) {
                                     log::error!("save_tx thread send failed: {:?}", e);
                                 }
                             }
                         }
+                        FileDialogState::SaveDevices => {
+                            if let Some(path) = self.file_dialog.update(ctx).picked() {
+                                let mut path = path.to_path_buf();
+                                path.set_extension("csv");
+                                self.file_dialog_state = FileDialogState::None;
+
+                                let mut devices = vec![DeviceExport {
+                                    name: self.device.clone(),
+                                    names: self.labels.clone(),
+                                    data: self.data.clone(),
+                                }];
+                                devices.extend(self.device_panes.iter().map(|pane| DeviceExport {
+                                    name: pane.device.clone(),
+                                    names: self.device_labels(&pane.device, &pane.data),
+                                    data: pane.data.clone(),
+                                }));
+                                // the data is copied, writing does not block the GUI
+                                thread::spawn(move || match save_devices_to_csv(&devices, &path) {
+                                    Ok(_) => log::info!("saved all devices to {:?}", path),
+                                    Err(e) => {
+                                        log::error!("failed to save file to {:?}: {:?}", path, e)
+                                    }
+                                });
+                            }
+                        }
+                        FileDialogState::SaveFused => {
+                            if let Some(path) = self.file_dialog.update(ctx).picked() {
+                                let mut path = path.to_path_buf();
+                                path.set_extension("csv");
+                                self.file_dialog_state = FileDialogState::None;
+
+                                let (names, frames) = self.receivers();
+                                let frames: Vec<Vec<CsiFrame>> =
+                                    frames.iter().map(|frames| frames.to_vec()).collect();
+                                let fusion = self.gui_conf.fusion;
+                                let csi_settings = self.csi_settings;
+                                thread::spawn(move || {
+                                    let frames: Vec<&[CsiFrame]> =
+                                        frames.iter().map(|frames| frames.as_slice()).collect();
+                                    let records = align(&frames, &fusion);
+                                    match save_fused_to_csv(&records, &names, csi_settings, &path) {
+                                        Ok(_) => log::info!("saved fused records to {:?}", path),
+                                        Err(e) => {
+                                            log::error!(
+                                                "failed to save file to {:?}: {:?}",
+                                                path,
+                                                e
+                                            )
+                                        }
+                                    }
+                                });
+                            }
+                        }
+                        FileDialogState::ImportProfiles => {
+                            if let Some(path) = self.file_dialog.update(ctx).picked() {
+                                let path = path.to_path_buf();
+                                self.file_dialog_state = FileDialogState::None;
+                                match import_profiles(&path) {
+                                    Ok(imported) => {
+                                        for profile in imported.profiles {
+                                            self.profiles.insert(profile);
+                                        }
+                                        self.store_profiles();
+                                        log::info!("imported device profiles from {:?}", path);
+                                    }
+                                    Err(e) => {
+                                        log::error!(
+                                            "failed to import profiles from {:?}: {:?}",
+                                            path,
+                                            e
+                                        )
+                                    }
+                                }
+                            }
+                        }
+                        FileDialogState::ExportProfiles => {
+                            if let Some(path) = self.file_dialog.update(ctx).picked() {
+                                let mut path = path.to_path_buf();
+                                path.set_extension("json");
+                                self.file_dialog_state = FileDialogState::None;
+                                match export_profiles(&self.profiles, &path) {
+                                    Ok(_) => log::info!("exported device profiles to {:?}", path),
+                                    Err(e) => {
+                                        log::error!("failed to save file to {:?}: {:?}", path, e)
+                                    }
+                                }
+                            }
+                        }
                         FileDialogState::None => {}
                     }
                 });
@@ -1881,6 +4005,25 @@ impl MyApp {
         ui.painter()
             .circle(center, radius, color, egui::Stroke::new(1.0, color_stroke));
     }
+
+    fn paint_motion_indicator(&self, ui: &mut egui::Ui) {
+        // the connection indicator is painted without allocating any space
+        ui.add_space(ui.spacing().interact_size.x);
+        let (text, color) = if self.data.motion.detected {
+            ("Motion detected", Color32::from_rgb(255, 140, 0))
+        } else {
+            ("No motion", ui.visuals().weak_text_color())
+        };
+        let radius = ui.spacing().interact_size.y * 0.375;
+        let (rect, _) = ui.allocate_exact_size(vec2(2.0 * radius, 2.0 * radius), Sense::hover());
+        ui.painter()
+            .circle(rect.center(), radius, color, egui::Stroke::new(1.0, color));
+        ui.label(egui::RichText::new(text).color(color).strong())
+            .on_hover_text(format!(
+                "Motion score: {:.1} % (threshold {:.1} %)",
+                self.data.motion.score, self.motion_settings.threshold
+            ));
+    }
 }
 
 impl eframe::App for MyApp {
@@ -1888,7 +4031,21 @@ impl eframe::App for MyApp {
         if let Ok(read_guard) = self.connected_lock.read() {
             self.connected_to_device = *read_guard;
         }
+        if let Ok(read_guard) = self.recording_lock.read() {
+            self.recording.clone_from(&read_guard);
+        }
+        if let Ok(read_guard) = self.queue_lock.read() {
+            self.command_queue.clone_from(&read_guard);
+        }
+        for pane in self.device_panes.iter_mut() {
+            pane.sync();
+        }
         self.draw_central_panel(ctx);
+        self.draw_device_consoles(ctx);
+        if self.breathing_settings.enabled {
+            self.breathing
+                .update(&self.data, &self.csi_settings, &self.breathing_settings);
+        }
         self.draw_side_panel(ctx, frame);
 
         self.gui_conf.x = ctx.used_size().x;
@@ -0,0 +1,169 @@
+use std::fmt;
+
+use eframe::egui::{lerp, Color32, ColorImage};
+
+// larger windows are decimated, such that the texture stays small
+const MAX_COLUMNS: usize = 2048;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Colormap {
+    Viridis,
+    Inferno,
+    Jet,
+    Grayscale,
+}
+
+impl Colormap {
+    pub const ALL: [Colormap; 4] = [
+        Colormap::Viridis,
+        Colormap::Inferno,
+        Colormap::Jet,
+        Colormap::Grayscale,
+    ];
+
+    fn stops(&self) -> &'static [[u8; 3]] {
+        match self {
+            Colormap::Viridis => &[
+                [68, 1, 84],
+                [59, 82, 139],
+                [33, 145, 140],
+                [94, 201, 98],
+                [253, 231, 37],
+            ],
+            Colormap::Inferno => &[
+                [0, 0, 4],
+                [87, 16, 110],
+                [188, 55, 84],
+                [249, 142, 9],
+                [252, 255, 164],
+            ],
+            Colormap::Jet => &[
+                [0, 0, 143],
+                [0, 0, 255],
+                [0, 255, 255],
+                [255, 255, 0],
+                [255, 0, 0],
+                [128, 0, 0],
+            ],
+            Colormap::Grayscale => &[[0, 0, 0], [255, 255, 255]],
+        }
+    }
+
+    /// Maps `t` in `[0, 1]` to a color by linear interpolation between the stops.
+    pub fn color(&self, t: f32) -> Color32 {
+        let stops = self.stops();
+        let t = if t.is_finite() {
+            t.clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let pos = t * (stops.len() - 1) as f32;
+        let idx = (pos.floor() as usize).min(stops.len() - 2);
+        let frac = pos - idx as f32;
+        let (a, b) = (stops[idx], stops[idx + 1]);
+        let channel = |i: usize| lerp(a[i] as f32..=b[i] as f32, frac).round() as u8;
+        Color32::from_rgb(channel(0), channel(1), channel(2))
+    }
+}
+
+impl fmt::Display for Colormap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Colormap::Viridis => write!(f, "Viridis"),
+            Colormap::Inferno => write!(f, "Inferno"),
+            Colormap::Jet => write!(f, "Jet"),
+            Colormap::Grayscale => write!(f, "Grayscale"),
+        }
+    }
+}
+
+/// Where the heatmap is drawn relative to the line plots.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HeatmapPlacement {
+    Hidden,
+    BelowPlots,
+    ReplacePlots,
+}
+
+impl fmt::Display for HeatmapPlacement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            HeatmapPlacement::Hidden => write!(f, "Hidden"),
+            HeatmapPlacement::BelowPlots => write!(f, "Below Plots"),
+            HeatmapPlacement::ReplacePlots => write!(f, "Replace Plots"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct HeatmapSettings {
+    pub placement: HeatmapPlacement,
+    pub colormap: Colormap,
+    pub auto_scale: bool,
+    pub min: f32,
+    pub max: f32,
+    pub follow: bool,
+}
+
+impl Default for HeatmapSettings {
+    fn default() -> Self {
+        Self {
+            placement: HeatmapPlacement::Hidden,
+            colormap: Colormap::Viridis,
+            auto_scale: true,
+            min: 0.0,
+            max: 40.0,
+            follow: true,
+        }
+    }
+}
+
+/// Renders the samples `window..` of all datasets into an image with time along
+/// the x-axis and the dataset (subcarrier) index along the y-axis.
+///
+/// Returns the image and the value range mapped to the colormap.
+pub fn heatmap_image(
+    dataset: &[Vec<f32>],
+    window: usize,
+    settings: &HeatmapSettings,
+) -> (ColorImage, f32, f32) {
+    let samples = dataset
+        .iter()
+        .map(|set| set.len().saturating_sub(window))
+        .min()
+        .unwrap_or(0);
+    let stride = samples.div_ceil(MAX_COLUMNS).max(1);
+    let columns = samples / stride;
+    let rows = dataset.len();
+    if columns == 0 || rows == 0 {
+        return (
+            ColorImage::new([1, 1], Color32::TRANSPARENT),
+            settings.min,
+            settings.max,
+        );
+    }
+
+    let (min, max) = if settings.auto_scale {
+        dataset
+            .iter()
+            .flat_map(|set| set[window..window + columns * stride].iter())
+            .filter(|v| v.is_finite())
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), v| {
+                (lo.min(*v), hi.max(*v))
+            })
+    } else {
+        (settings.min, settings.max)
+    };
+    let span = if max > min { max - min } else { 1.0 };
+
+    let mut image = ColorImage::new([columns, rows], Color32::TRANSPARENT);
+    for (row, set) in dataset.iter().enumerate() {
+        // the first dataset is at the bottom of the plot
+        let y = rows - 1 - row;
+        for x in 0..columns {
+            let value = set[window + x * stride];
+            image[(x, y)] = settings.colormap.color((value - min) / span);
+        }
+    }
+    (image, min, max)
+}
@@ -1,8 +1,18 @@
 use std::error::Error;
-use std::path::PathBuf;
+use std::fmt;
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
-use csv::{ReaderBuilder, WriterBuilder};
+use csv::{ReaderBuilder, StringRecord, Writer, WriterBuilder};
+use serde::{Deserialize, Serialize};
 
+use crate::commands::redact;
+use crate::csi::CsiSettings;
+use crate::data::{get_epoch_ms, Packet};
+use crate::fusion::FusedRecord;
 use crate::DataContainer;
 
 /// A set of options for saving data to a CSV file.
@@ -11,9 +21,49 @@ pub struct FileOptions {
     pub file_path: PathBuf,
     pub save_absolute_time: bool,
     pub save_raw_traffic: bool,
+    pub traffic_format: TrafficFormat,
     pub names: Vec<String>,
 }
 
+/// File format of the raw traffic log, both formats can be opened again.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TrafficFormat {
+    #[default]
+    Csv,
+    /// One JSON object per packet and line.
+    Ndjson,
+}
+
+impl TrafficFormat {
+    pub const ALL: [TrafficFormat; 2] = [TrafficFormat::Csv, TrafficFormat::Ndjson];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            TrafficFormat::Csv => "csv",
+            TrafficFormat::Ndjson => "ndjson",
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "csv" => Some(TrafficFormat::Csv),
+            "ndjson" | "jsonl" => Some(TrafficFormat::Ndjson),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for TrafficFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TrafficFormat::Csv => write!(f, "CSV"),
+            TrafficFormat::Ndjson => write!(f, "NDJSON"),
+        }
+    }
+}
+
+const TRAFFIC_HEADER: [&str; 4] = ["Time [ms]", "Abs Time [ms]", "Direction", "Payload"];
+
 pub fn open_from_csv(
     data: &mut DataContainer,
     csv_options: &mut FileOptions,
@@ -67,7 +117,11 @@ pub fn open_from_csv(
     Ok(())
 }
 
-pub fn save_to_csv(data: &DataContainer, csv_options: &FileOptions) -> Result<(), Box<dyn Error>> {
+pub fn save_to_csv(
+    data: &DataContainer,
+    session: &SessionFile,
+    csv_options: &FileOptions,
+) -> Result<(), Box<dyn Error>> {
     let mut wtr = WriterBuilder::new()
         .has_headers(false)
         .from_path(&csv_options.file_path)?;
@@ -75,6 +129,32 @@ pub fn save_to_csv(data: &DataContainer, csv_options: &FileOptions) -> Result<()
     let mut header = vec!["Time [ms]".to_string()];
     header.extend_from_slice(&csv_options.names);
     wtr.write_record(header)?;
+    // rows evicted from memory are read back from the session file first
+    let spilled = session.rows_path(data);
+    let missing = data
+        .evicted
+        .rows
+        .saturating_sub(spilled.map_or(0, |(_, rows)| rows));
+    if missing > 0 {
+        log::warn!(
+            "{missing} rows evicted from memory while spilling to the session file was off are missing in {:?}",
+            csv_options.file_path
+        );
+    }
+    if let Some((spill_path, _)) = spilled {
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(true)
+            .from_path(spill_path)?;
+        for record in rdr.records() {
+            let record = record?;
+            let time = if csv_options.save_absolute_time {
+                &record[1]
+            } else {
+                &record[0]
+            };
+            wtr.write_record(std::iter::once(time).chain(record.iter().skip(2)))?;
+        }
+    }
     for j in 0..data.dataset[0].len() {
         let time = if csv_options.save_absolute_time {
             data.absolute_time[j].to_string()
@@ -97,27 +177,745 @@ pub fn save_to_csv(data: &DataContainer, csv_options: &FileOptions) -> Result<()
             .unwrap()
             .to_string()
             .replace(".csv", "");
-        file_name += "raw.csv";
+        file_name += "raw.";
+        file_name += csv_options.traffic_format.extension();
         path.set_file_name(file_name);
-        save_raw(data, &path)?
+        save_raw(data, session, &path, csv_options.traffic_format)?
+    }
+    Ok(())
+}
+
+/// Data of one device for [`save_devices_to_csv`].
+pub struct DeviceExport {
+    pub name: String,
+    pub names: Vec<String>,
+    pub data: DataContainer,
+}
+
+/// Saves the datasets of several devices into one table on their common absolute time axis.
+///
+/// Every row holds the values of one device, the columns of the other devices stay empty.
+pub fn save_devices_to_csv(devices: &[DeviceExport], path: &Path) -> Result<(), Box<dyn Error>> {
+    let mut wtr = WriterBuilder::new().has_headers(false).from_path(path)?;
+    let mut header = vec!["Time [ms]".to_string(), "Abs Time [ms]".to_string()];
+    for device in devices {
+        header.extend(
+            device
+                .names
+                .iter()
+                .map(|name| format!("{}: {}", device.name, name)),
+        );
+    }
+    wtr.write_record(header)?;
+
+    let start = devices
+        .iter()
+        .filter_map(|device| device.data.absolute_time.first())
+        .fold(f64::INFINITY, |start, time| start.min(*time));
+    let mut rows: Vec<(f64, usize, usize)> = devices
+        .iter()
+        .enumerate()
+        .flat_map(|(d, device)| {
+            device
+                .data
+                .absolute_time
+                .iter()
+                .enumerate()
+                .map(move |(j, time)| (*time, d, j))
+        })
+        .collect();
+    // the sort is stable, rows with the same time keep the order of the devices
+    rows.sort_by(|a, b| a.0.total_cmp(&b.0));
+    for (time, d, j) in rows {
+        let mut record = vec![(time - start).to_string(), time.to_string()];
+        for (k, device) in devices.iter().enumerate() {
+            record.extend((0..device.names.len()).map(|i| {
+                if k != d {
+                    return "".to_string();
+                }
+                device
+                    .data
+                    .dataset
+                    .get(i)
+                    .and_then(|set| set.get(j))
+                    .map(|value| value.to_string())
+                    .unwrap_or_default()
+            }));
+        }
+        wtr.write_record(&record)?;
     }
+    wtr.flush()?;
     Ok(())
 }
 
-pub fn save_raw(data: &DataContainer, path: &PathBuf) -> Result<(), Box<dyn Error>> {
+/// Saves fused CSI records, one row per transmission with the values of every receiver
+/// in its own columns. Receivers that missed the transmission leave their columns empty.
+pub fn save_fused_to_csv(
+    records: &[FusedRecord],
+    receivers: &[String],
+    settings: CsiSettings,
+    path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    // the subcarriers of every receiver are taken from its first frame
+    let subcarriers: Vec<Vec<i32>> = (0..receivers.len())
+        .map(|r| {
+            records
+                .iter()
+                .find_map(|record| record.frames[r].as_ref().and_then(|f| settings.compute(f)))
+                .map(|(indices, _)| indices)
+                .unwrap_or_default()
+        })
+        .collect();
+
     let mut wtr = WriterBuilder::new().has_headers(false).from_path(path)?;
-    let header = vec![
+    let mut header = vec![
         "Time [ms]".to_string(),
         "Abs Time [ms]".to_string(),
-        "Raw Traffic".to_string(),
+        "Sequence".to_string(),
     ];
+    for (name, indices) in receivers.iter().zip(&subcarriers) {
+        header.push(format!("{name}: RSSI"));
+        header.extend(
+            indices
+                .iter()
+                .map(|index| format!("{}: {}", name, settings.quantity.column_name(*index))),
+        );
+    }
     wtr.write_record(header)?;
 
-    for j in 0..data.dataset[0].len() {
-        let mut data_to_write = vec![data.time[j].to_string(), data.absolute_time[j].to_string()];
-        data_to_write.push(data.raw_traffic[j].payload.clone());
+    let start = records.first().map_or(0.0, |record| record.absolute_time);
+    for record in records {
+        let mut row = vec![
+            (record.absolute_time - start).to_string(),
+            record.absolute_time.to_string(),
+            record
+                .sequence
+                .map(|sequence| sequence.to_string())
+                .unwrap_or_default(),
+        ];
+        for (frame, indices) in record.frames.iter().zip(&subcarriers) {
+            row.push(
+                frame
+                    .as_ref()
+                    .map(|f| f.rssi.to_string())
+                    .unwrap_or_default(),
+            );
+            match frame.as_ref().and_then(|f| settings.compute(f)) {
+                Some((frame_indices, values)) if frame_indices == *indices => {
+                    row.extend(values.iter().map(|value| value.to_string()));
+                }
+                _ => row.extend(indices.iter().map(|_| "".to_string())),
+            }
+        }
+        wtr.write_record(&row)?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+/// Saves every packet of the session, the ones evicted from memory are read back from the
+/// session file in front of the ones still in memory.
+pub fn save_raw(
+    data: &DataContainer,
+    session: &SessionFile,
+    path: &PathBuf,
+    format: TrafficFormat,
+) -> Result<(), Box<dyn Error>> {
+    let missing = data.evicted.raw_traffic.saturating_sub(session.raw_packets);
+    if missing > 0 {
+        log::warn!(
+            "{missing} packets evicted from memory while spilling to the session file was off are missing in {:?}",
+            path
+        );
+    }
+    let mut spilled = match session.raw_path.as_ref() {
+        Some(spill_path) => Some(
+            ReaderBuilder::new()
+                .has_headers(true)
+                .from_path(spill_path)?,
+        ),
+        None => None,
+    };
+    match format {
+        TrafficFormat::Csv => {
+            let mut wtr = create_raw_file(path)?;
+            if let Some(rdr) = spilled.as_mut() {
+                for record in rdr.records() {
+                    wtr.write_record(&record?)?;
+                }
+            }
+            write_packets(&mut wtr, &data.raw_traffic)?;
+        }
+        TrafficFormat::Ndjson => {
+            let mut writer = BufWriter::new(File::create(path)?);
+            if let Some(rdr) = spilled.as_mut() {
+                for record in rdr.records() {
+                    serde_json::to_writer(&mut writer, &traffic_record(&record?)?)?;
+                    writer.write_all(b"\n")?;
+                }
+            }
+            for packet in data.raw_traffic.iter() {
+                serde_json::to_writer(&mut writer, packet)?;
+                writer.write_all(b"\n")?;
+            }
+            writer.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// Reads a raw traffic log saved as CSV or NDJSON, the format is chosen by the extension.
+/// Passwords in logs of older versions are redacted, such that they are not saved again.
+pub fn open_traffic(path: &Path) -> Result<Vec<Packet>, Box<dyn Error>> {
+    if TrafficFormat::from_path(path) == Some(TrafficFormat::Ndjson) {
+        let reader = BufReader::new(File::open(path)?);
+        let mut packets = vec![];
+        for line in reader.lines() {
+            let line = line?;
+            if !line.trim().is_empty() {
+                let mut packet: Packet = serde_json::from_str(&line)?;
+                packet.payload = redact(&packet.payload).into_owned();
+                packets.push(packet);
+            }
+        }
+        return Ok(packets);
+    }
+    let mut rdr = ReaderBuilder::new().has_headers(true).from_path(path)?;
+    if !is_traffic_header(rdr.headers()?) {
+        return Err("CSV file is not a raw traffic log".into());
+    }
+    let mut packets = vec![];
+    for result in rdr.records() {
+        let mut packet = traffic_record(&result?)?;
+        packet.payload = redact(&packet.payload).into_owned();
+        packets.push(packet);
+    }
+    Ok(packets)
+}
+
+fn traffic_record(record: &StringRecord) -> Result<Packet, Box<dyn Error>> {
+    if record.len() != TRAFFIC_HEADER.len() {
+        return Err("CSV record does not match the expected number of columns".into());
+    }
+    Ok(Packet {
+        relative_time: record[0].parse()?,
+        absolute_time: record[1].parse()?,
+        direction: record[2].parse()?,
+        payload: record[3].to_string(),
+    })
+}
+
+/// Whether the CSV file at `path` is a raw traffic log rather than a dataset.
+pub fn is_traffic_csv(path: &Path) -> bool {
+    ReaderBuilder::new()
+        .has_headers(true)
+        .from_path(path)
+        .and_then(|mut rdr| rdr.headers().map(is_traffic_header))
+        .unwrap_or(false)
+}
+
+fn is_traffic_header(header: &StringRecord) -> bool {
+    header.iter().eq(TRAFFIC_HEADER)
+}
+
+/// Creates the file locked, such that a running recording is not recovered by another instance.
+fn create_locked(path: &Path) -> Result<Writer<File>, Box<dyn Error>> {
+    let file = File::create(path)?;
+    file.lock()?;
+    Ok(WriterBuilder::new().has_headers(false).from_writer(file))
+}
+
+fn create_rows_file(path: &Path, names: &[String]) -> Result<Writer<File>, Box<dyn Error>> {
+    let mut wtr = create_locked(path)?;
+    let mut header = vec!["Time [ms]".to_string(), "Abs Time [ms]".to_string()];
+    header.extend_from_slice(names);
+    wtr.write_record(header)?;
+    Ok(wtr)
+}
+
+fn write_rows(
+    wtr: &mut Writer<File>,
+    data: &DataContainer,
+    rows: Range<usize>,
+) -> Result<(), Box<dyn Error>> {
+    for j in rows {
+        let mut data_to_write = vec![
+            data.time[j].to_string(),
+            data.absolute_time
+                .get(j)
+                .map(|t| t.to_string())
+                .unwrap_or_default(),
+        ];
+        for value in data.dataset.iter() {
+            data_to_write.push(value.get(j).map(|v| v.to_string()).unwrap_or_default());
+        }
         wtr.write_record(&data_to_write)?;
     }
     wtr.flush()?;
     Ok(())
 }
+
+fn create_raw_file(path: &Path) -> Result<Writer<File>, Box<dyn Error>> {
+    let mut wtr = create_locked(path)?;
+    wtr.write_record(TRAFFIC_HEADER)?;
+    Ok(wtr)
+}
+
+fn write_packets(wtr: &mut Writer<File>, packets: &[Packet]) -> Result<(), Box<dyn Error>> {
+    for packet in packets {
+        wtr.write_record([
+            packet.relative_time.to_string(),
+            packet.absolute_time.to_string(),
+            packet.direction.to_string(),
+            packet.payload.clone(),
+        ])?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+// session files of earlier runs are removed at startup after this time
+const STALE_SESSION_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+fn session_directory() -> PathBuf {
+    std::env::temp_dir().join("serial-monitor")
+}
+
+/// CSV files in the temporary directory receiving the data evicted from memory during a
+/// session. A new part is started whenever the layout of the datasets changes.
+///
+/// The files are removed once the session is cleared.
+#[derive(Default)]
+pub struct SessionFile {
+    name: String,
+    part: usize,
+    generation: Option<u64>,
+    columns: usize,
+    data: Option<Writer<File>>,
+    data_path: Option<PathBuf>,
+    /// Number of rows written to the current part.
+    part_rows: usize,
+    raw: Option<Writer<File>>,
+    raw_path: Option<PathBuf>,
+    /// Number of packets written to the raw traffic file.
+    raw_packets: usize,
+    paths: Vec<PathBuf>,
+}
+
+impl SessionFile {
+    /// The current part and its number of rows, if it holds rows of the layout of `data`.
+    fn rows_path(&self, data: &DataContainer) -> Option<(&Path, usize)> {
+        if self.generation != Some(data.generation) || self.columns != data.dataset.len() {
+            return None;
+        }
+        Some((self.data_path.as_deref()?, self.part_rows))
+    }
+
+    pub fn append(
+        &mut self,
+        evicted: &DataContainer,
+        names: &[String],
+    ) -> Result<(), Box<dyn Error>> {
+        let directory = session_directory();
+        if self.name.is_empty() {
+            std::fs::create_dir_all(&directory)?;
+            self.name = format!("session_{}", get_epoch_ms());
+        }
+        if !evicted.time.is_empty()
+            && (self.data.is_none()
+                || self.generation != Some(evicted.generation)
+                || self.columns != evicted.dataset.len())
+        {
+            self.part += 1;
+            let path = directory.join(format!("{}_{}.csv", self.name, self.part));
+            log::info!("spilling data older than the buffer capacity to {:?}", path);
+            self.data = Some(create_rows_file(&path, names)?);
+            self.data_path = Some(path.clone());
+            self.paths.push(path);
+            self.part_rows = 0;
+            self.generation = Some(evicted.generation);
+            self.columns = evicted.dataset.len();
+        }
+        if let Some(wtr) = self.data.as_mut() {
+            write_rows(wtr, evicted, 0..evicted.time.len())?;
+            self.part_rows += evicted.time.len();
+        }
+        if !evicted.raw_traffic.is_empty() {
+            if self.raw.is_none() {
+                let path = directory.join(format!("{}_raw.csv", self.name));
+                self.raw = Some(create_raw_file(&path)?);
+                self.raw_path = Some(path.clone());
+                self.paths.push(path);
+            }
+            if let Some(wtr) = self.raw.as_mut() {
+                write_packets(wtr, &evicted.raw_traffic)?;
+                self.raw_packets += evicted.raw_traffic.len();
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for SessionFile {
+    fn drop(&mut self) {
+        // the writers are closed before their files are removed
+        self.data = None;
+        self.raw = None;
+        for path in self.paths.iter() {
+            if let Err(e) = std::fs::remove_file(path) {
+                log::warn!("failed to remove the session file {:?}: {:?}", path, e);
+            }
+        }
+    }
+}
+
+/// Removes session files left behind by earlier runs, files written to within the last day
+/// may belong to another running instance and are kept.
+pub fn remove_stale_sessions() {
+    let Ok(entries) = std::fs::read_dir(session_directory()) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let stale = entry
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .is_ok_and(|modified| modified.elapsed().unwrap_or_default() > STALE_SESSION_AGE);
+        let is_session = entry.file_name().to_string_lossy().starts_with("session_");
+        if is_session && stale {
+            if let Err(e) = std::fs::remove_file(entry.path()) {
+                log::warn!(
+                    "failed to remove the session file {:?}: {:?}",
+                    entry.path(),
+                    e
+                );
+            }
+        }
+    }
+}
+
+/// When a recording continues in a new part.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Rotation {
+    #[default]
+    Never,
+    /// After the files of the current part exceed the given size in MB.
+    Size(u64),
+    /// After the given number of minutes.
+    Interval(u64),
+}
+
+impl Rotation {
+    pub const ALL: [Rotation; 3] = [Rotation::Never, Rotation::Size(100), Rotation::Interval(60)];
+}
+
+impl fmt::Display for Rotation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Rotation::Never => write!(f, "Never"),
+            Rotation::Size(_) => write!(f, "By Size"),
+            Rotation::Interval(_) => write!(f, "By Time"),
+        }
+    }
+}
+
+/// Recording settings persisted with the GUI settings.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RecordSettings {
+    pub file_path: PathBuf,
+    pub rotation: Rotation,
+}
+
+/// A set of options for recording data while connected.
+#[derive(Debug)]
+pub struct RecordOptions {
+    pub file_path: PathBuf,
+    pub rotation: Rotation,
+    pub names: Vec<String>,
+}
+
+// parts of a running recording carry this additional extension
+const PART_EXTENSION: &str = "part";
+const RECORDING_HEADER: &str = "Time [ms],Abs Time [ms]";
+
+/// Appends the rows and the raw traffic received after the start of a recording to
+/// numbered CSV parts. Every append is flushed, so that a crash loses at most the last row.
+pub struct Recorder {
+    options: RecordOptions,
+    part: usize,
+    part_started: Instant,
+    data: Option<Writer<File>>,
+    raw: Option<Writer<File>>,
+    generation: u64,
+    columns: usize,
+    /// Index of the next row and raw packet to record, counted from the first one received.
+    next_row: usize,
+    next_packet: usize,
+}
+
+impl Recorder {
+    /// Starts a recording of everything `data` receives from now on.
+    pub fn new(options: RecordOptions, data: &DataContainer) -> Self {
+        Self {
+            options,
+            part: 1,
+            part_started: Instant::now(),
+            data: None,
+            raw: None,
+            generation: data.generation,
+            columns: data.dataset.len(),
+            next_row: data.evicted.rows + data.time.len(),
+            next_packet: data.evicted.raw_traffic + data.raw_traffic.len(),
+        }
+    }
+
+    /// Path of the current part of the rows or, with `raw`, of the raw traffic.
+    pub fn current_path(&self, raw: bool) -> PathBuf {
+        let stem = self
+            .options
+            .file_path
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy();
+        let suffix = if raw { "_raw" } else { "" };
+        self.options.file_path.with_file_name(format!(
+            "{stem}_{:03}{suffix}.csv.{PART_EXTENSION}",
+            self.part
+        ))
+    }
+
+    /// Writes what `data` received since the last call, `names` is only called when a
+    /// part is started while the dataset does not match the names of the options.
+    pub fn append(
+        &mut self,
+        data: &DataContainer,
+        names: impl FnOnce() -> Vec<String>,
+    ) -> Result<(), Box<dyn Error>> {
+        let rows_end = data.evicted.rows + data.time.len();
+        let packets_end = data.evicted.raw_traffic + data.raw_traffic.len();
+        if data.generation != self.generation || data.dataset.len() != self.columns {
+            // cleared buffers only contain new data, recomputed ones have been recorded
+            if self.next_row > rows_end {
+                self.next_row = data.evicted.rows;
+            } else {
+                self.next_row = rows_end;
+            }
+            if self.next_packet > packets_end {
+                self.next_packet = data.evicted.raw_traffic;
+            }
+            self.generation = data.generation;
+            self.columns = data.dataset.len();
+            if self.data.is_some() || self.raw.is_some() {
+                self.rotate()?;
+            }
+        }
+
+        let first_row = self.next_row.saturating_sub(data.evicted.rows);
+        if first_row < data.time.len() {
+            if self.data.is_none() {
+                let names = if self.options.names.len() == data.dataset.len() {
+                    self.options.names.clone()
+                } else {
+                    names()
+                };
+                self.data = Some(create_rows_file(&self.current_path(false), &names)?);
+            }
+            if let Some(wtr) = self.data.as_mut() {
+                write_rows(wtr, data, first_row..data.time.len())?;
+            }
+            self.next_row = rows_end;
+        }
+
+        let first_packet = self.next_packet.saturating_sub(data.evicted.raw_traffic);
+        if first_packet < data.raw_traffic.len() {
+            if self.raw.is_none() {
+                self.raw = Some(create_raw_file(&self.current_path(true))?);
+            }
+            if let Some(wtr) = self.raw.as_mut() {
+                write_packets(wtr, &data.raw_traffic[first_packet..])?;
+            }
+            self.next_packet = packets_end;
+        }
+
+        let rotate = match self.options.rotation {
+            Rotation::Never => false,
+            Rotation::Size(megabytes) => self.size()? >= megabytes.max(1) * 1_000_000,
+            Rotation::Interval(minutes) => {
+                self.part_started.elapsed() >= Duration::from_secs(minutes.max(1) * 60)
+            }
+        };
+        if rotate {
+            self.rotate()?;
+        }
+        Ok(())
+    }
+
+    /// Completes the current part.
+    pub fn stop(mut self) -> Result<(), Box<dyn Error>> {
+        self.finish_part()
+    }
+
+    fn size(&self) -> Result<u64, Box<dyn Error>> {
+        let mut size = 0;
+        for wtr in [&self.data, &self.raw].into_iter().flatten() {
+            size += wtr.get_ref().metadata()?.len();
+        }
+        Ok(size)
+    }
+
+    fn rotate(&mut self) -> Result<(), Box<dyn Error>> {
+        self.finish_part()?;
+        self.part += 1;
+        self.part_started = Instant::now();
+        Ok(())
+    }
+
+    fn finish_part(&mut self) -> Result<(), Box<dyn Error>> {
+        for (raw, wtr) in [(false, self.data.take()), (true, self.raw.take())] {
+            if let Some(mut wtr) = wtr {
+                wtr.flush()?;
+                let path = self.current_path(raw);
+                std::fs::rename(&path, path.with_extension(""))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether the file name is one of a part of a [`Recorder`], `{stem}_{NNN}[_raw].csv.part`.
+fn is_recording_part(path: &Path) -> bool {
+    let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+        return false;
+    };
+    let Some(name) = name.strip_suffix(&format!(".csv.{PART_EXTENSION}")) else {
+        return false;
+    };
+    let name = name.strip_suffix("_raw").unwrap_or(name);
+    match name.rsplit_once('_') {
+        Some((stem, part)) => {
+            !stem.is_empty() && part.len() >= 3 && part.chars().all(|c| c.is_ascii_digit())
+        }
+        None => false,
+    }
+}
+
+/// Completes the parts in `directory` left behind by a recording that did not stop, e.g.
+/// because of a crash. The incomplete last line is removed and the part extension dropped.
+///
+/// Only parts named and started like the ones of a [`Recorder`] are touched, parts still
+/// locked by a running recording and parts whose completed file already exists are kept.
+pub fn recover_recordings(directory: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let mut recovered = vec![];
+    for entry in std::fs::read_dir(directory)? {
+        let path = entry?.path();
+        if !is_recording_part(&path) {
+            continue;
+        }
+        match recover_part(&path) {
+            Ok(Some(target)) => recovered.push(target),
+            Ok(None) => {}
+            Err(e) => log::error!("failed to recover {:?}: {:?}", path, e),
+        }
+    }
+    Ok(recovered)
+}
+
+fn recover_part(path: &Path) -> Result<Option<PathBuf>, Box<dyn Error>> {
+    // the last complete line is searched for in this many bytes at the end of the file
+    const TAIL: u64 = 1 << 20;
+    let target = path.with_extension("");
+    if target.exists() {
+        log::warn!("not recovering {:?}, {:?} already exists", path, target);
+        return Ok(None);
+    }
+    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+    match file.try_lock() {
+        Ok(()) => {}
+        // written by a recording of another instance
+        Err(TryLockError::WouldBlock) => return Ok(None),
+        Err(TryLockError::Error(e)) => return Err(e.into()),
+    }
+    // both the rows and the raw traffic start with the same columns
+    let mut header = [0; RECORDING_HEADER.len()];
+    if file.read_exact(&mut header).is_err() || header != RECORDING_HEADER.as_bytes() {
+        log::warn!("not recovering {:?}, it is no recording", path);
+        return Ok(None);
+    }
+    let len = file.metadata()?.len();
+    let start = len.saturating_sub(TAIL);
+    let mut tail = vec![];
+    file.seek(SeekFrom::Start(start))?;
+    file.read_to_end(&mut tail)?;
+    match tail.iter().rposition(|b| *b == b'\n') {
+        Some(last_newline) => file.set_len(start + last_newline as u64 + 1)?,
+        None if start == 0 => {
+            log::warn!("not recovering {:?}, its header is incomplete", path);
+            return Ok(None);
+        }
+        None => {}
+    }
+    drop(file);
+    std::fs::rename(path, &target)?;
+    Ok(Some(target))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_directory(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("{name}_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    #[test]
+    fn recovers_only_own_parts() {
+        let directory = temp_directory("recover_recordings");
+        let foreign = directory.join("download.csv.part");
+        std::fs::write(&foreign, "partial download").unwrap();
+        let unnumbered = directory.join("rec.csv.part");
+        std::fs::write(&unnumbered, "Time [ms],Abs Time [ms],a\n1,2,").unwrap();
+        let other = directory.join("other_001.csv.part");
+        std::fs::write(&other, "no recording\n").unwrap();
+        std::fs::write(
+            directory.join("rec_001.csv.part"),
+            "Time [ms],Abs Time [ms],a\n1,2,3\n4,5,",
+        )
+        .unwrap();
+        std::fs::write(
+            directory.join("rec_002_raw.csv.part"),
+            "Time [ms],Abs Time [ms]",
+        )
+        .unwrap();
+        std::fs::write(
+            directory.join("rec_003.csv.part"),
+            "Time [ms],Abs Time [ms]\n",
+        )
+        .unwrap();
+        std::fs::write(directory.join("rec_003.csv"), "finished").unwrap();
+        let locked = directory.join("rec_004_raw.csv.part");
+        let writer = create_raw_file(&locked).unwrap();
+
+        let recovered = recover_recordings(&directory).unwrap();
+
+        assert_eq!(recovered, vec![directory.join("rec_001.csv")]);
+        assert_eq!(
+            std::fs::read_to_string(&recovered[0]).unwrap(),
+            "Time [ms],Abs Time [ms],a\n1,2,3\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(&foreign).unwrap(),
+            "partial download"
+        );
+        assert!(unnumbered.exists() && other.exists() && locked.exists());
+        assert!(directory.join("rec_002_raw.csv.part").exists());
+        assert!(directory.join("rec_003.csv.part").exists());
+        assert_eq!(
+            std::fs::read_to_string(directory.join("rec_003.csv")).unwrap(),
+            "finished"
+        );
+        drop(writer);
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
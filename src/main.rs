@@ -6,28 +6,53 @@ extern crate preferences;
 extern crate serde;
 
 use std::cmp::max;
-use std::path::PathBuf;
-use std::sync::mpsc::{Receiver, Sender};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender};
 use std::sync::{mpsc, Arc, RwLock};
 use std::time::Duration;
 use std::{env, thread};
 
-use crate::data::{DataContainer, Packet};
+use crate::commands::ConfigParser;
+use crate::csi::{CsiLine, CsiParser, CsiQuantity, CsiSettings};
+use crate::data::{DataContainer, Packet, StorageSettings};
+use crate::framing::FramingStats;
 use crate::gui::{load_gui_settings, MyApp, RIGHT_PANEL_WIDTH};
-use crate::io::{open_from_csv, save_to_csv, FileOptions};
+use crate::io::{
+    is_traffic_csv, open_from_csv, open_traffic, recover_recordings, remove_stale_sessions,
+    save_to_csv, FileOptions, RecordOptions, Recorder, SessionFile, TrafficFormat,
+};
+use crate::motion::{MotionDetector, MotionSettings};
+use crate::queue::CommandQueue;
+use crate::replay::ReplayControl;
 use crate::serial::{load_serial_settings, serial_thread, Device};
 use eframe::egui::{vec2, ViewportBuilder, Visuals};
 use eframe::{egui, icon_data};
 use preferences::AppInfo;
 
+mod breathing;
 mod color_picker;
+mod commands;
+mod csi;
 mod custom_highlighter;
 mod data;
+mod devices;
+mod filter;
+mod framing;
+mod fusion;
 mod gui;
+mod heatmap;
 mod io;
+mod motion;
+mod network;
+mod profiles;
+mod queue;
+mod replay;
+mod secrets;
 mod serial;
 mod settings_window;
+mod spectrum;
 mod toggle;
+mod transport;
 mod update;
 
 const APP_INFO: AppInfo = AppInfo {
@@ -36,6 +61,8 @@ const APP_INFO: AppInfo = AppInfo {
 };
 const PREFERENCES_KEY: &str = "config/gui";
 const PREFERENCES_KEY_SERIAL: &str = "config/serial_devices";
+const PREFERENCES_KEY_PROFILES: &str = "config/device_profiles";
+const PREFERENCES_KEY_SECRETS: &str = "config/device_secrets";
 
 fn split(payload: &str) -> Vec<f32> {
     let mut split_data: Vec<&str> = vec![];
@@ -49,42 +76,75 @@ fn split(payload: &str) -> Vec<f32> {
         .collect()
 }
 
-// fn extract_csi_data(payload: &str) -> Vec<i32> {
-//     // Find the start of the "csi raw data" section
-//     if let Some(start) = payload.find("csi raw data:") {
-//         // Extract the part after "csi raw data:"
-//         let raw_data = &payload[start + "csi raw data:".len()..];
-        
-//         // Remove brackets and split the numbers by commas
-//         raw_data
-//             .trim()
-//             .trim_start_matches('[')
-//             .trim_end_matches(']')
-//             .split(',')
-//             .filter_map(|x| x.trim().parse::<i32>().ok()) // Parse each number as i32
-//             .collect()
-//     } else {
-//         // If "csi raw data" is not found, return an empty vector
-//         vec![]
-//     }
-// }
-
-// // Function to extract the "csi raw data" section from the payload
-// // This function assumes that the payload is a string and that the "csi raw data" section is formatted correctly
-// fn extract_csi_data(payload: &str) -> &str {
-//     // Find the start of the "csi raw data" section
-//     if let Some(start) = payload.find("csi raw data:") {
-//         // Extract the part after "csi raw data:"
-//         let raw_data = &payload[start + "csi raw data:".len()..];
-        
-//         // Trim and return the raw data section
-//         raw_data.trim()
-//     } else {
-//         // If "csi raw data" is not found, return an empty string
-//         ""
-//     }
-// }
+/// Recomputes the dataset from all received CSI frames, e.g. after choosing another quantity.
+fn rebuild_csi_dataset(data: &mut DataContainer, settings: CsiSettings) {
+    let Some((subcarriers, _)) = data
+        .csi_frames
+        .iter()
+        .rev()
+        .find_map(|f| settings.compute(f))
+    else {
+        return;
+    };
+    data.time.clear();
+    data.absolute_time.clear();
+    data.dataset = vec![vec![]; max(subcarriers.len(), 1)];
+    for frame in data.csi_frames.iter() {
+        let Some((indices, values)) = settings.compute(frame) else {
+            continue;
+        };
+        if indices != subcarriers {
+            continue;
+        }
+        for (set, value) in data.dataset.iter_mut().zip(values) {
+            set.push(value);
+        }
+        data.time.push(frame.relative_time);
+        data.absolute_time.push(frame.absolute_time);
+    }
+    data.csi_subcarriers = subcarriers;
+    data.invalidate();
+}
+
+/// Shows a saved raw traffic log in the console, returns whether it could be opened.
+fn load_traffic(
+    data: &mut DataContainer,
+    session_file: &mut SessionFile,
+    path: &Path,
+    load_names_tx: &Sender<Vec<String>>,
+) -> bool {
+    match open_traffic(path) {
+        Ok(packets) => {
+            data.reset();
+            *session_file = SessionFile::default();
+            data.raw_traffic = packets;
+            data.loaded_from_file = true;
+            log::info!("opened {:?}", path);
+            load_names_tx
+                .send(vec!["Column 0".to_string()])
+                .expect("unable to send names on channel after loading");
+            true
+        }
+        Err(err) => {
+            log::error!("failed opening {:?}: {:?}", path, err);
+            false
+        }
+    }
+}
+
+/// Column names of the datasets, the subcarrier index is used for CSI data.
+fn column_names(data: &DataContainer, settings: CsiSettings) -> Vec<String> {
+    (0..data.dataset.len())
+        .map(|i| match data.csi_subcarriers.get(i) {
+            Some(index) if data.csi_subcarriers.len() == data.dataset.len() => {
+                settings.quantity.column_name(*index)
+            }
+            _ => format!("Column {i}"),
+        })
+        .collect()
+}
 
+#[allow(clippy::too_many_arguments)]
 fn main_thread(
     sync_tx: Sender<bool>,
     data_lock: Arc<RwLock<DataContainer>>,
@@ -93,100 +153,162 @@ fn main_thread(
     load_rx: Receiver<PathBuf>,
     load_names_tx: Sender<Vec<String>>,
     clear_rx: Receiver<bool>,
+    csi_settings_rx: Receiver<CsiSettings>,
+    motion_settings_rx: Receiver<MotionSettings>,
+    mut storage_settings: StorageSettings,
+    storage_settings_rx: Receiver<StorageSettings>,
+    record_rx: Receiver<Option<RecordOptions>>,
+    recording_lock: Arc<RwLock<Option<PathBuf>>>,
 ) {
     // reads data from mutex, samples and saves if needed
     let mut data = DataContainer::default();
+    let mut session_file = SessionFile::default();
+    let mut recorder: Option<Recorder> = None;
     let mut failed_format_counter = 0;
+    let mut csi_parser = CsiParser::default();
+    let mut config_parser = ConfigParser::default();
+    let mut csi_settings = CsiSettings::default();
+    let mut motion_detector = MotionDetector::default();
 
     let mut file_opened = false;
 
     loop {
         if let Ok(cl) = clear_rx.recv_timeout(Duration::from_millis(1)) {
             if cl {
-                data = DataContainer::default();
+                data.reset();
+                session_file = SessionFile::default();
                 failed_format_counter = 0;
+                csi_parser.reset();
+                config_parser.reset();
+                motion_detector.reset();
+            }
+        }
+        if let Ok(settings) = motion_settings_rx.try_recv() {
+            motion_detector.set_settings(settings);
+            data.motion = motion_detector.status().clone();
+        }
+        if let Ok(settings) = csi_settings_rx.try_recv() {
+            csi_settings = settings;
+            rebuild_csi_dataset(&mut data, csi_settings);
+        }
+        if let Ok(settings) = storage_settings_rx.try_recv() {
+            storage_settings = settings;
+        }
+        if let Ok(options) = record_rx.try_recv() {
+            if let Some(recorder) = recorder.take() {
+                match recorder.stop() {
+                    Ok(_) => log::info!("stopped recording"),
+                    Err(e) => log::error!("failed to complete the recording: {:?}", e),
+                }
+            }
+            if let Some(options) = options {
+                log::info!("recording to {:?}", options.file_path);
+                recorder = Some(Recorder::new(options, &data));
             }
         }
         if !file_opened {
-            if let Ok(packet) = raw_data_rx.recv_timeout(Duration::from_millis(1)) {
+            let received = raw_data_rx.recv_timeout(Duration::from_millis(1));
+            if let Err(RecvTimeoutError::Disconnected) = received {
+                // the serial thread has ended, e.g. the pane of the device was closed
+                return;
+            }
+            if let Ok(packet) = received {
                 data.loaded_from_file = false;
                 if !packet.payload.is_empty() {
                     sync_tx.send(true).expect("unable to send sync tx");
                     data.raw_traffic.push(packet.clone());
-                    // let extracted_data = extract_csi_data(&packet.payload);
-                    // let split_data = split(extracted_data);
-                    let split_data = split(&packet.payload);
-                    //log::debug!("split data: {:?}", split_data);
-                    //println!("split data: {:?}", split_data);
-                    
-                    //here we might use defmt-print!!!!!!!!!!!!!!!
-                    if data.dataset.is_empty() || failed_format_counter > 10 {
-                        // resetting dataset
-                        data.dataset = vec![vec![]; max(split_data.len(), 1)];
-                        failed_format_counter = 0;
-                        // log::error!("resetting dataset. split length = {}, length data.dataset = {}", split_data.len(), data.dataset.len());
-                    } else if split_data.len() == data.dataset.len() {
-                        // appending data
-                        for (i, set) in data.dataset.iter_mut().enumerate() {
-                            set.push(split_data[i]);
-                            failed_format_counter = 0;
+
+                    let config_line = match config_parser.feed(&packet) {
+                        Some(config) => {
+                            data.device_config = Some(config.clone());
+                            true
                         }
-                        data.time.push(packet.relative_time);
-                        data.absolute_time.push(packet.absolute_time);
-                        if data.time.len() != data.dataset[0].len() {
+                        None => false,
+                    };
+
+                    let csi_line = csi_parser.feed(&packet);
+                    data.csi_stats.clone_from(&csi_parser.stats);
+                    let split_data = match csi_line {
+                        CsiLine::Frame(frame) => {
+                            let amplitude = CsiSettings {
+                                quantity: CsiQuantity::Amplitude,
+                                ..csi_settings
+                            };
+                            if let Some((_, amplitudes)) = amplitude.compute(&frame) {
+                                if let Some(event) = motion_detector.feed(
+                                    &amplitudes,
+                                    frame.relative_time,
+                                    frame.absolute_time,
+                                ) {
+                                    log::info!("{}", event.payload);
+                                    data.raw_traffic.push(event);
+                                }
+                                data.motion = motion_detector.status().clone();
+                            }
+                            let row = csi_settings.compute(&frame);
+                            data.csi_frames.push(frame);
+                            row.map(|(indices, values)| {
+                                data.csi_subcarriers = indices;
+                                values
+                            })
+                        }
+                        CsiLine::Consumed => None,
+                        // lines of the configuration are not plotted
+                        CsiLine::Ignored if config_line => None,
+                        CsiLine::Ignored => Some(split(&packet.payload)),
+                    };
+
+                    if let Some(split_data) = split_data {
+                        if data.dataset.is_empty() || failed_format_counter > 10 {
                             // resetting dataset
-                            data.time = vec![];
                             data.dataset = vec![vec![]; max(split_data.len(), 1)];
+                            data.invalidate();
+                            failed_format_counter = 0;
+                            // log::error!("resetting dataset. split length = {}, length data.dataset = {}", split_data.len(), data.dataset.len());
+                        } else if split_data.len() == data.dataset.len() {
+                            // appending data
+                            for (i, set) in data.dataset.iter_mut().enumerate() {
+                                set.push(split_data[i]);
+                                failed_format_counter = 0;
+                            }
+                            data.time.push(packet.relative_time);
+                            data.absolute_time.push(packet.absolute_time);
+                            if data.time.len() != data.dataset[0].len() {
+                                // resetting dataset
+                                data.time = vec![];
+                                data.dataset = vec![vec![]; max(split_data.len(), 1)];
+                                data.invalidate();
+                            }
+                        } else {
+                            // not same length
+                            failed_format_counter += 1;
+                            // log::error!("not same length in main! length split_data = {}, length data.dataset = {}", split_data.len(), data.dataset.len())
                         }
-                    } else {
-                        // not same length
-                        failed_format_counter += 1;
-                        // log::error!("not same length in main! length split_data = {}, length data.dataset = {}", split_data.len(), data.dataset.len())
                     }
                 }
-                // if !packet.payload.is_empty() {
-                //     sync_tx.send(true).expect("unable to send sync tx");
-                //     data.raw_traffic.push(packet.clone());
-                
-                //     // Extract CSI data directly
-                //     let extracted_data = extract_csi_data(&packet.payload);
-                
-                //     // Use the extracted data directly instead of calling split
-                //     if data.dataset.is_empty() || failed_format_counter > 10 {
-                //         // Resetting dataset
-                //         data.dataset = vec![vec![]; max(extracted_data.len(), 1)];
-                //         failed_format_counter = 0;
-                //     } else if extracted_data.len() == data.dataset.len() {
-                //         // Appending data
-                //         for (i, set) in data.dataset.iter_mut().enumerate() {
-                //             set.push(extracted_data[i] as f32); // Convert i32 to f32 if needed
-                //             failed_format_counter = 0;
-                //         }
-                //         data.time.push(packet.relative_time);
-                //         data.absolute_time.push(packet.absolute_time);
-                //         if data.time.len() != data.dataset[0].len() {
-                //             // Resetting dataset
-                //             data.time = vec![];
-                //             data.dataset = vec![vec![]; max(extracted_data.len(), 1)];
-                //         }
-                //     } else {
-                //         // Not same length
-                //         failed_format_counter += 1;
-                //     }
-                // }
             }
         }
         if let Ok(fp) = load_rx.recv_timeout(Duration::from_millis(10)) {
             if let Some(file_ending) = fp.extension() {
                 match file_ending.to_str().unwrap() {
+                    "csv" if is_traffic_csv(&fp) => {
+                        file_opened =
+                            load_traffic(&mut data, &mut session_file, &fp, &load_names_tx);
+                    }
+                    "ndjson" | "jsonl" => {
+                        file_opened =
+                            load_traffic(&mut data, &mut session_file, &fp, &load_names_tx);
+                    }
                     "csv" => {
                         file_opened = true;
                         let mut file_options = FileOptions {
                             file_path: fp.clone(),
                             save_absolute_time: false,
                             save_raw_traffic: false,
+                            traffic_format: TrafficFormat::Csv,
                             names: vec![],
                         };
+                        data.invalidate();
                         match open_from_csv(&mut data, &mut file_options) {
                             Ok(_) => {
                                 log::info!("opened {:?}", fp);
@@ -213,12 +335,39 @@ fn main_thread(
             file_opened = false;
         }
 
+        // everything has to be recorded before it is evicted
+        if let Some(active) = recorder.as_mut() {
+            if let Err(e) = active.append(&data, || column_names(&data, csi_settings)) {
+                log::error!("failed to record data, recording stopped: {:?}", e);
+                recorder = None;
+            }
+        }
+        if let Ok(mut write_guard) = recording_lock.write() {
+            let path = recorder.as_ref().map(|r| r.current_path(false));
+            if *write_guard != path {
+                *write_guard = path;
+            }
+        }
+
+        // data loaded from a file is shown in full
+        if !data.loaded_from_file {
+            if let Some(evicted) = data.evict(storage_settings.capacity) {
+                if storage_settings.spill {
+                    if let Err(e) =
+                        session_file.append(&evicted, &column_names(&data, csi_settings))
+                    {
+                        log::error!("failed to spill data to the session file: {:?}", e);
+                    }
+                }
+            }
+        }
+
         if let Ok(mut write_guard) = data_lock.write() {
-            *write_guard = data.clone();
+            write_guard.sync_from(&data);
         }
 
         if let Ok(csv_options) = save_rx.recv_timeout(Duration::from_millis(1)) {
-            match save_to_csv(&data, &csv_options) {
+            match save_to_csv(&data, &session_file, &csv_options) {
                 Ok(_) => {
                     log::info!("saved data file to {:?} ", csv_options.file_path);
                 }
@@ -244,6 +393,24 @@ fn main() {
     let devices_lock = Arc::new(RwLock::new(vec![gui_settings.device.clone()]));
     let data_lock = Arc::new(RwLock::new(DataContainer::default()));
     let connected_lock = Arc::new(RwLock::new(false));
+    let framing_stats_lock = Arc::new(RwLock::new(FramingStats::default()));
+    let recording_lock = Arc::new(RwLock::new(None));
+    let replay_lock = Arc::new(RwLock::new(ReplayControl::default()));
+    let queue_lock = Arc::new(RwLock::new(CommandQueue::default()));
+
+    remove_stale_sessions();
+
+    // parts of a recording interrupted by a crash are completed on the next start
+    if let Some(directory) = gui_settings.record.file_path.parent() {
+        if directory.is_dir() {
+            match recover_recordings(directory) {
+                Ok(recovered) => recovered
+                    .iter()
+                    .for_each(|path| log::info!("recovered partial recording {:?}", path)),
+                Err(e) => log::error!("failed to recover recordings: {:?}", e),
+            }
+        }
+    }
 
     let (save_tx, save_rx): (Sender<FileOptions>, Receiver<FileOptions>) = mpsc::channel();
     let (load_tx, load_rx): (Sender<PathBuf>, Receiver<PathBuf>) = mpsc::channel();
@@ -253,6 +420,20 @@ fn main() {
     let (clear_tx, clear_rx): (Sender<bool>, Receiver<bool>) = mpsc::channel();
     let (raw_data_tx, raw_data_rx): (Sender<Packet>, Receiver<Packet>) = mpsc::channel();
     let (sync_tx, sync_rx): (Sender<bool>, Receiver<bool>) = mpsc::channel();
+    let (csi_settings_tx, csi_settings_rx): (Sender<CsiSettings>, Receiver<CsiSettings>) =
+        mpsc::channel();
+    let (motion_settings_tx, motion_settings_rx): (
+        Sender<MotionSettings>,
+        Receiver<MotionSettings>,
+    ) = mpsc::channel();
+    let (storage_settings_tx, storage_settings_rx): (
+        Sender<StorageSettings>,
+        Receiver<StorageSettings>,
+    ) = mpsc::channel();
+    let (record_tx, record_rx): (
+        Sender<Option<RecordOptions>>,
+        Receiver<Option<RecordOptions>>,
+    ) = mpsc::channel();
 
     // // Simulated data stream
     // let simulated_data_tx = raw_data_tx.clone();
@@ -272,6 +453,9 @@ fn main() {
     let serial_device_lock = device_lock.clone();
     let serial_devices_lock = devices_lock.clone();
     let serial_connected_lock = connected_lock.clone();
+    let serial_framing_stats_lock = framing_stats_lock.clone();
+    let serial_replay_lock = replay_lock.clone();
+    let serial_queue_lock = queue_lock.clone();
 
     let _serial_thread_handler = thread::spawn(|| {
         serial_thread(
@@ -280,12 +464,17 @@ fn main() {
             serial_device_lock,
             serial_devices_lock,
             serial_connected_lock,
+            serial_framing_stats_lock,
+            serial_replay_lock,
+            serial_queue_lock,
         );
     });
 
     let main_data_lock = data_lock.clone();
+    let main_storage_settings = gui_settings.storage;
+    let main_recording_lock = recording_lock.clone();
 
-    let _main_thread_handler = thread::spawn(|| {
+    let _main_thread_handler = thread::spawn(move || {
         main_thread(
             sync_tx,
             main_data_lock,
@@ -294,6 +483,12 @@ fn main() {
             load_rx,
             loaded_names_tx,
             clear_rx,
+            csi_settings_rx,
+            motion_settings_rx,
+            main_storage_settings,
+            storage_settings_rx,
+            record_rx,
+            main_recording_lock,
         );
     });
 
@@ -319,6 +514,10 @@ fn main() {
     let gui_device_lock = device_lock;
     let gui_devices_lock = devices_lock;
     let gui_connected_lock = connected_lock;
+    let gui_framing_stats_lock = framing_stats_lock;
+    let gui_recording_lock = recording_lock;
+    let gui_replay_lock = replay_lock;
+    let gui_queue_lock = queue_lock;
 
     if let Err(e) = eframe::run_native(
         "Serial Monitor",
@@ -344,12 +543,20 @@ fn main() {
                 gui_devices_lock,
                 saved_serial_device_configs,
                 gui_connected_lock,
+                gui_framing_stats_lock,
+                gui_recording_lock,
+                gui_replay_lock,
+                gui_queue_lock,
                 gui_settings,
                 save_tx,
                 load_tx,
                 loaded_names_rx,
                 send_tx,
                 clear_tx,
+                csi_settings_tx,
+                motion_settings_tx,
+                storage_settings_tx,
+                record_tx,
             )))
         }),
     ) {
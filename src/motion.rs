@@ -0,0 +1,132 @@
+use std::collections::VecDeque;
+
+use crate::data::{Packet, SerialDirection};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MotionSettings {
+    pub enabled: bool,
+    /// Number of CSI frames in the sliding window.
+    pub window: usize,
+    /// Motion is detected while the score is above this value.
+    pub threshold: f32,
+}
+
+impl Default for MotionSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            window: 50,
+            threshold: 5.0,
+        }
+    }
+}
+
+/// Latest result of the [`MotionDetector`], shared with the GUI.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MotionStatus {
+    /// Mean relative standard deviation of the subcarrier amplitudes in the window in percent.
+    pub score: f32,
+    /// Mean deviation of the latest frame from the moving average in percent.
+    pub deviation: f32,
+    pub detected: bool,
+    /// Variance of every subcarrier amplitude in the window.
+    pub variance: Vec<f32>,
+}
+
+/// Sliding-window statistics over the CSI amplitudes of consecutive frames.
+#[derive(Clone, Debug, Default)]
+pub struct MotionDetector {
+    settings: MotionSettings,
+    window: VecDeque<Vec<f32>>,
+    status: MotionStatus,
+}
+
+impl MotionDetector {
+    pub fn status(&self) -> &MotionStatus {
+        &self.status
+    }
+
+    pub fn set_settings(&mut self, settings: MotionSettings) {
+        if settings.window != self.settings.window || !settings.enabled {
+            self.reset();
+        }
+        self.settings = settings;
+    }
+
+    pub fn reset(&mut self) {
+        self.window.clear();
+        self.status = MotionStatus::default();
+    }
+
+    /// Adds the amplitudes of the next frame and returns an event for the raw traffic log
+    /// whenever motion starts or stops.
+    pub fn feed(
+        &mut self,
+        amplitudes: &[f32],
+        relative_time: f64,
+        absolute_time: f64,
+    ) -> Option<Packet> {
+        if !self.settings.enabled || amplitudes.is_empty() {
+            return None;
+        }
+        // a different number of subcarriers starts a new window
+        if self
+            .window
+            .front()
+            .is_some_and(|front| front.len() != amplitudes.len())
+        {
+            self.reset();
+        }
+        self.window.push_back(amplitudes.to_vec());
+        while self.window.len() > self.settings.window.max(2) {
+            self.window.pop_front();
+        }
+        if self.window.len() < self.settings.window.max(2) {
+            return None;
+        }
+
+        let n = self.window.len() as f32;
+        let subcarriers = amplitudes.len();
+        let mut variance = Vec::with_capacity(subcarriers);
+        let mut score = 0.0;
+        let mut deviation = 0.0;
+        for sc in 0..subcarriers {
+            let mean = self.window.iter().map(|frame| frame[sc]).sum::<f32>() / n;
+            let var = self
+                .window
+                .iter()
+                .map(|frame| (frame[sc] - mean).powi(2))
+                .sum::<f32>()
+                / n;
+            // subcarriers without energy carry no information
+            if mean > f32::EPSILON {
+                score += var.sqrt() / mean;
+                deviation += (amplitudes[sc] - mean).abs() / mean;
+            }
+            variance.push(var);
+        }
+        let score = 100.0 * score / subcarriers as f32;
+        let detected_before = self.status.detected;
+        self.status = MotionStatus {
+            score,
+            deviation: 100.0 * deviation / subcarriers as f32,
+            detected: score > self.settings.threshold,
+            variance,
+        };
+
+        if self.status.detected == detected_before {
+            return None;
+        }
+        let state = if self.status.detected {
+            "motion detected"
+        } else {
+            "motion ended"
+        };
+        Some(Packet {
+            relative_time,
+            absolute_time,
+            direction: SerialDirection::Event,
+            payload: format!("{state} (score {:.1} %)", self.status.score),
+        })
+    }
+}
@@ -0,0 +1,253 @@
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs, UdpSocket};
+use std::time::Duration;
+
+use crate::transport::{Transport, READ_TIMEOUT};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+// largest possible UDP payload
+const MAX_DATAGRAM_LEN: usize = 65536;
+
+/// Network sources, e.g. ESP32 nodes streaming CSI over Wi-Fi.
+///
+/// Their device names are URLs like `tcp://192.168.4.1:5000` for a TCP client or
+/// `udp://0.0.0.0:5000` for a UDP listener.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NetworkProtocol {
+    #[default]
+    Tcp,
+    Udp,
+}
+
+impl NetworkProtocol {
+    pub const ALL: [NetworkProtocol; 2] = [NetworkProtocol::Tcp, NetworkProtocol::Udp];
+
+    pub fn scheme(&self) -> &'static str {
+        match *self {
+            NetworkProtocol::Tcp => "tcp",
+            NetworkProtocol::Udp => "udp",
+        }
+    }
+
+    pub fn address_hint(&self) -> &'static str {
+        match *self {
+            NetworkProtocol::Tcp => "192.168.4.1:5000",
+            NetworkProtocol::Udp => "0.0.0.0:5000",
+        }
+    }
+}
+
+impl fmt::Display for NetworkProtocol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            NetworkProtocol::Tcp => write!(f, "TCP client"),
+            NetworkProtocol::Udp => write!(f, "UDP listener"),
+        }
+    }
+}
+
+/// Splits the device name of a network source into protocol and address.
+pub fn network_endpoint(name: &str) -> Option<(NetworkProtocol, &str)> {
+    NetworkProtocol::ALL.into_iter().find_map(|protocol| {
+        name.strip_prefix(protocol.scheme())
+            .and_then(|rest| rest.strip_prefix("://"))
+            .map(|address| (protocol, address))
+    })
+}
+
+/// Device name of a network source, the address is only checked for a host and a port.
+pub fn network_device(protocol: NetworkProtocol, address: &str) -> Result<String, String> {
+    let address = address.trim();
+    let (host, port) = address
+        .rsplit_once(':')
+        .ok_or_else(|| "the address needs a port, e.g. host:5000".to_string())?;
+    if host.is_empty() {
+        return Err("the address needs a host".to_string());
+    }
+    port.parse::<u16>()
+        .map_err(|_| format!("invalid port: {port}"))?;
+    Ok(format!("{}://{}", protocol.scheme(), address))
+}
+
+// read timeouts are reported as WouldBlock on UNIX and TimedOut on Windows
+fn map_timeout(err: io::Error) -> io::Error {
+    if err.kind() == io::ErrorKind::WouldBlock {
+        io::ErrorKind::TimedOut.into()
+    } else {
+        err
+    }
+}
+
+/// TCP client, the received byte stream is treated like the one of a serial port.
+pub struct TcpSource {
+    stream: TcpStream,
+}
+
+impl TcpSource {
+    pub fn connect(address: &str) -> io::Result<Self> {
+        let mut last_err = io::Error::new(io::ErrorKind::NotFound, "address did not resolve");
+        for addr in address.to_socket_addrs()? {
+            match TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT) {
+                Ok(stream) => {
+                    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+                    stream.set_nodelay(true)?;
+                    return Ok(Self { stream });
+                }
+                Err(err) => last_err = err,
+            }
+        }
+        Err(last_err)
+    }
+}
+
+impl Read for TcpSource {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.stream.read(buf).map_err(map_timeout)
+    }
+}
+
+impl Write for TcpSource {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stream.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+impl Transport for TcpSource {}
+
+/// UDP listener, every datagram is read at once.
+///
+/// Sent commands go to the sender of the last datagram.
+pub struct UdpSource {
+    socket: UdpSocket,
+    peer: Option<SocketAddr>,
+    /// Terminates datagrams without a line break, for the text framing.
+    lines: bool,
+    datagram: Vec<u8>,
+    /// Rest of the last datagram, if it did not fit into the buffer of the read.
+    unread: Vec<u8>,
+}
+
+impl UdpSource {
+    pub fn bind(address: &str, lines: bool) -> io::Result<Self> {
+        let socket = UdpSocket::bind(address)?;
+        socket.set_read_timeout(Some(READ_TIMEOUT))?;
+        Ok(Self {
+            socket,
+            peer: None,
+            lines,
+            datagram: vec![0; MAX_DATAGRAM_LEN],
+            unread: vec![],
+        })
+    }
+}
+
+impl Read for UdpSource {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.unread.is_empty() {
+            let (len, peer) = self
+                .socket
+                .recv_from(&mut self.datagram)
+                .map_err(map_timeout)?;
+            self.peer = Some(peer);
+            if len == 0 {
+                // an empty datagram must not be mistaken for the end of the stream
+                return Err(io::ErrorKind::TimedOut.into());
+            }
+            self.unread.extend_from_slice(&self.datagram[..len]);
+            if self.lines && self.datagram[len - 1] != b'\n' {
+                self.unread.extend_from_slice(b"\r\n");
+            }
+        }
+        // datagrams longer than the buffer are continued by the next reads
+        let len = self.unread.len().min(buf.len());
+        buf[..len].copy_from_slice(&self.unread[..len]);
+        self.unread.drain(..len);
+        Ok(len)
+    }
+}
+
+impl Write for UdpSource {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.peer {
+            Some(peer) => self.socket.send_to(buf, peer),
+            None => Err(io::Error::other(
+                "no datagram received yet, the sender is unknown",
+            )),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Transport for UdpSource {}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+    use std::thread;
+
+    use super::*;
+
+    fn read_lines(source: impl Read, count: usize) -> Vec<String> {
+        let mut reader = BufReader::new(source);
+        (0..count)
+            .map(|_| {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                line
+            })
+            .collect()
+    }
+
+    #[test]
+    fn tcp_source_joins_lines_split_across_writes() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let sender = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            for chunk in ["first li", "ne\r\nsecond", " line\r\n"] {
+                stream.write_all(chunk.as_bytes()).unwrap();
+                stream.flush().unwrap();
+                thread::sleep(Duration::from_millis(10));
+            }
+        });
+        let source = TcpSource::connect(&address).unwrap();
+        assert_eq!(read_lines(source, 2), ["first line\r\n", "second line\r\n"]);
+        sender.join().unwrap();
+    }
+
+    #[test]
+    fn udp_source_splits_lines_of_one_datagram() {
+        let source = UdpSource::bind("127.0.0.1:0", true).unwrap();
+        let address = source.socket.local_addr().unwrap();
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+        sender
+            .send_to(b"first line\nsecond line\n", address)
+            .unwrap();
+        assert_eq!(read_lines(source, 2), ["first line\n", "second line\n"]);
+    }
+
+    #[test]
+    fn udp_source_continues_datagram_longer_than_buffer() {
+        let mut source = UdpSource::bind("127.0.0.1:0", true).unwrap();
+        let address = source.socket.local_addr().unwrap();
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let line = "x".repeat(3000);
+        sender.send_to(line.as_bytes(), address).unwrap();
+        let mut received = vec![];
+        let mut buf = [0; 1024];
+        while !received.ends_with(b"\r\n") {
+            let len = source.read(&mut buf).unwrap();
+            received.extend_from_slice(&buf[..len]);
+        }
+        assert_eq!(received, format!("{line}\r\n").as_bytes());
+    }
+}
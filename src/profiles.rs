@@ -0,0 +1,133 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+use preferences::Preferences;
+use serde::{Deserialize, Serialize};
+
+use crate::commands::{CsiFlags, NetworkArchitecture, TrafficType, WifiMode};
+use crate::secrets::WifiPasswords;
+use crate::{APP_INFO, PREFERENCES_KEY_PROFILES};
+
+/// Named configuration of the board for one experiment, as set in the side panel.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DeviceProfile {
+    pub name: String,
+    /// Port the profile is loaded for when the port is selected.
+    #[serde(default)]
+    pub port: Option<String>,
+    pub traffic_enable: bool,
+    pub traffic_type: TrafficType,
+    pub traffic_interval: String,
+    pub network_architecture: NetworkArchitecture,
+    pub csi_flags: CsiFlags,
+    pub enable_set_wifi: bool,
+    pub wifi_mode: WifiMode,
+    pub wifi_max_connections: String,
+    pub wifi_hide_ssid: bool,
+    pub wifi_ap_ssid: String,
+    /// The passwords are only kept in the encrypted password store, never in the profiles.
+    #[serde(skip)]
+    pub wifi_ap_password: String,
+    pub wifi_sta_ssid: String,
+    #[serde(skip)]
+    pub wifi_sta_password: String,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct DeviceProfiles {
+    pub profiles: Vec<DeviceProfile>,
+}
+
+impl DeviceProfiles {
+    /// Adds the profile, or replaces the one with the same name.
+    pub fn insert(&mut self, profile: DeviceProfile) -> usize {
+        match self.profiles.iter().position(|p| p.name == profile.name) {
+            Some(idx) => {
+                self.profiles[idx] = profile;
+                idx
+            }
+            None => {
+                self.profiles.push(profile);
+                self.profiles.len() - 1
+            }
+        }
+    }
+
+    /// Binds the profile to the port, a port is bound to one profile at most.
+    pub fn bind(&mut self, idx: usize, port: Option<String>) {
+        if port.is_some() {
+            for profile in self.profiles.iter_mut() {
+                if profile.port == port {
+                    profile.port = None;
+                }
+            }
+        }
+        if let Some(profile) = self.profiles.get_mut(idx) {
+            profile.port = port;
+        }
+    }
+
+    /// Wi-Fi passwords of the profiles that have any, by profile name.
+    pub fn passwords(&self) -> BTreeMap<String, WifiPasswords> {
+        self.profiles
+            .iter()
+            .filter(|p| !p.wifi_ap_password.is_empty() || !p.wifi_sta_password.is_empty())
+            .map(|p| {
+                let passwords = WifiPasswords {
+                    ap_password: p.wifi_ap_password.clone(),
+                    sta_password: p.wifi_sta_password.clone(),
+                };
+                (p.name.clone(), passwords)
+            })
+            .collect()
+    }
+
+    /// Sets the passwords of the profiles that are in `passwords`.
+    pub fn set_passwords(&mut self, passwords: &BTreeMap<String, WifiPasswords>) {
+        for profile in self.profiles.iter_mut() {
+            if let Some(stored) = passwords.get(&profile.name) {
+                profile.wifi_ap_password = stored.ap_password.clone();
+                profile.wifi_sta_password = stored.sta_password.clone();
+            }
+        }
+    }
+
+    pub fn clear_passwords(&mut self) {
+        for profile in self.profiles.iter_mut() {
+            profile.wifi_ap_password.clear();
+            profile.wifi_sta_password.clear();
+        }
+    }
+
+    /// Index of the profile bound to the port.
+    pub fn bound_to(&self, port: &str) -> Option<usize> {
+        self.profiles
+            .iter()
+            .position(|profile| profile.port.as_deref() == Some(port))
+    }
+}
+
+pub fn load_profiles() -> DeviceProfiles {
+    DeviceProfiles::load(&APP_INFO, PREFERENCES_KEY_PROFILES).unwrap_or_default()
+}
+
+pub fn save_profiles(profiles: &DeviceProfiles) {
+    if profiles.save(&APP_INFO, PREFERENCES_KEY_PROFILES).is_err() {
+        log::error!("failed to save device profiles");
+    }
+}
+
+/// Writes the profiles to a JSON file, e.g. to share them with other machines.
+pub fn export_profiles(profiles: &DeviceProfiles, path: &Path) -> Result<(), Box<dyn Error>> {
+    let writer = BufWriter::new(File::create(path)?);
+    serde_json::to_writer_pretty(writer, profiles)?;
+    Ok(())
+}
+
+pub fn import_profiles(path: &Path) -> Result<DeviceProfiles, Box<dyn Error>> {
+    let reader = BufReader::new(File::open(path)?);
+    Ok(serde_json::from_reader(reader)?)
+}
@@ -0,0 +1,356 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::commands::{is_prompt, redact, ConfigParser, EspCommand};
+
+/// Time the reply to a queued command is waited for.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(3);
+
+const ERROR_WORDS: [&str; 7] = [
+    "error",
+    "invalid",
+    "unknown",
+    "failed",
+    "fail",
+    "unrecognized",
+    "usage",
+];
+const OK_WORDS: [&str; 7] = [
+    "ok",
+    "success",
+    "successfully",
+    "done",
+    "applied",
+    "updated",
+    "saved",
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CommandStatus {
+    Pending,
+    /// Written to the device, waiting for the reply.
+    Sent,
+    Ok,
+    /// The device replied with an error, or the command could not be written.
+    Failed(String),
+    Timeout,
+    /// Not sent, because a previous command of the sequence did not succeed.
+    Aborted,
+}
+
+impl CommandStatus {
+    pub fn is_done(&self) -> bool {
+        !matches!(*self, CommandStatus::Pending | CommandStatus::Sent)
+    }
+}
+
+impl fmt::Display for CommandStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CommandStatus::Pending => write!(f, "pending"),
+            CommandStatus::Sent => write!(f, "waiting for reply"),
+            CommandStatus::Ok => write!(f, "ok"),
+            CommandStatus::Failed(reason) => write!(f, "failed: {reason}"),
+            CommandStatus::Timeout => write!(f, "timeout"),
+            CommandStatus::Aborted => write!(f, "aborted"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct QueuedCommand {
+    pub command: EspCommand,
+    pub status: CommandStatus,
+}
+
+fn starts_with_word(line: &str, words: &[&str]) -> bool {
+    line.split(|c: char| !c.is_ascii_alphanumeric())
+        .find(|word| !word.is_empty())
+        .is_some_and(|word| words.iter().any(|w| word.eq_ignore_ascii_case(w)))
+}
+
+/// The line without the prompt of the device CLI in front, e.g. `esp-csi> set-wifi ...`.
+fn strip_prompt(line: &str) -> &str {
+    match line.split_once("> ") {
+        Some((prompt, rest)) if !prompt.is_empty() && !prompt.contains(char::is_whitespace) => {
+            rest.trim_start()
+        }
+        _ => line,
+    }
+}
+
+/// Status of `command` after the device printed `line`, `None` if the line is no reply.
+fn reply_status(
+    command: &EspCommand,
+    line: &str,
+    config: &mut ConfigParser,
+) -> Option<CommandStatus> {
+    let line = line.trim();
+    if is_prompt(line) {
+        return Some(CommandStatus::Ok);
+    }
+    let line = strip_prompt(line);
+    let echo = command.to_string();
+    // the CLI echoes the typed command
+    if line.is_empty() || line == echo {
+        return None;
+    }
+    // errors of the argument parser start with the command name, e.g. `set-wifi: invalid option`
+    let name = echo.split(' ').next().unwrap_or_default();
+    let reply = line
+        .strip_prefix(name)
+        .and_then(|reply| reply.strip_prefix(':'))
+        .unwrap_or(line);
+    if starts_with_word(reply, &ERROR_WORDS) {
+        return Some(CommandStatus::Failed(redact(line).into_owned()));
+    }
+    let config_line = *command == EspCommand::ShowConfig && config.parse_line(line);
+    (config_line || starts_with_word(reply, &OK_WORDS)).then_some(CommandStatus::Ok)
+}
+
+/// Commands written one at a time by the serial thread, each once the previous one has been
+/// answered. The sequence stops at the first command that fails or times out.
+#[derive(Clone, Debug, Default)]
+pub struct CommandQueue {
+    pub commands: Vec<QueuedCommand>,
+    /// Time the command waiting for its reply was written.
+    written: Option<Instant>,
+    /// Reply to `show-config` read so far.
+    config: ConfigParser,
+}
+
+impl CommandQueue {
+    /// Replaces the previous sequence, returns false while it is still running.
+    pub fn submit(&mut self, commands: Vec<EspCommand>) -> bool {
+        if self.is_busy() {
+            return false;
+        }
+        self.commands = commands
+            .into_iter()
+            .map(|command| QueuedCommand {
+                command,
+                status: CommandStatus::Pending,
+            })
+            .collect();
+        self.written = None;
+        true
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.commands.iter().any(|queued| !queued.status.is_done())
+    }
+
+    /// Marks the commands not answered yet as aborted.
+    pub fn abort(&mut self) {
+        for queued in self.commands.iter_mut() {
+            if !queued.status.is_done() {
+                queued.status = CommandStatus::Aborted;
+            }
+        }
+        self.written = None;
+    }
+
+    fn current(&mut self) -> Option<&mut QueuedCommand> {
+        self.commands
+            .iter_mut()
+            .find(|queued| queued.status == CommandStatus::Sent)
+    }
+
+    fn finish(&mut self, status: CommandStatus) {
+        let ok = status == CommandStatus::Ok;
+        if let Some(queued) = self.current() {
+            queued.status = status;
+        }
+        self.written = None;
+        if !ok {
+            self.abort();
+        }
+    }
+
+    /// Next command to write, `None` while the previous one waits for its reply.
+    pub fn next_command(&mut self) -> Option<EspCommand> {
+        if self.current().is_some() {
+            return None;
+        }
+        let queued = self
+            .commands
+            .iter_mut()
+            .find(|queued| queued.status == CommandStatus::Pending)?;
+        queued.status = CommandStatus::Sent;
+        Some(queued.command.clone())
+    }
+
+    /// The command returned by `next_command` has been written to the device.
+    pub fn written(&mut self, now: Instant) {
+        let expects_reply = self
+            .current()
+            .is_some_and(|queued| queued.command.expects_reply());
+        if expects_reply {
+            self.written = Some(now);
+            self.config.reset();
+        } else {
+            self.finish(CommandStatus::Ok);
+        }
+    }
+
+    /// The command returned by `next_command` could not be written.
+    pub fn write_failed(&mut self, reason: String) {
+        self.finish(CommandStatus::Failed(reason));
+    }
+
+    /// Checks a received line for the reply to the command that waits for one.
+    pub fn receive(&mut self, line: &str) {
+        if self.written.is_none() {
+            return;
+        }
+        let Some(queued) = self
+            .commands
+            .iter()
+            .find(|queued| queued.status == CommandStatus::Sent)
+        else {
+            return;
+        };
+        if let Some(status) = reply_status(&queued.command, line, &mut self.config) {
+            self.finish(status);
+        }
+    }
+
+    pub fn check_timeout(&mut self, now: Instant) {
+        if self
+            .written
+            .is_some_and(|written| now.duration_since(written) > REPLY_TIMEOUT)
+        {
+            self.finish(CommandStatus::Timeout);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::{TrafficType, WifiOption, REDACTED};
+
+    fn set_traffic() -> EspCommand {
+        EspCommand::SetTraffic {
+            traffic_type: TrafficType::Udp,
+            interval: Some(100),
+        }
+    }
+
+    fn status(command: EspCommand, line: &str) -> Option<CommandStatus> {
+        reply_status(&command, line, &mut ConfigParser::default())
+    }
+
+    /// Queue with the first of `commands` written at `now`.
+    fn running(commands: Vec<EspCommand>, now: Instant) -> CommandQueue {
+        let mut queue = CommandQueue::default();
+        assert!(queue.submit(commands));
+        assert!(queue.next_command().is_some());
+        queue.written(now);
+        queue
+    }
+
+    fn statuses(queue: &CommandQueue) -> Vec<CommandStatus> {
+        queue
+            .commands
+            .iter()
+            .map(|queued| queued.status.clone())
+            .collect()
+    }
+
+    #[test]
+    fn echo_is_no_reply() {
+        let echo = set_traffic().to_string();
+        assert_eq!(status(set_traffic(), &echo), None);
+        assert_eq!(status(set_traffic(), &format!("esp-csi> {echo}")), None);
+        assert_eq!(status(set_traffic(), ""), None);
+    }
+
+    #[test]
+    fn ok_reply() {
+        for line in ["OK", "Applied traffic settings", "esp-csi> done"] {
+            assert_eq!(
+                status(set_traffic(), line),
+                Some(CommandStatus::Ok),
+                "{line}"
+            );
+        }
+        for line in ["Config reset", "Password unset", "Traffic type set to udp"] {
+            assert_eq!(status(set_traffic(), line), None, "{line}");
+        }
+    }
+
+    #[test]
+    fn error_reply() {
+        let command = EspCommand::SetWifi(WifiOption::StaPassword("secret123".to_string()));
+        assert_eq!(
+            status(
+                command.clone(),
+                "set-wifi: invalid --sta-password=secret123"
+            ),
+            Some(CommandStatus::Failed(format!(
+                "set-wifi: invalid --sta-password={REDACTED}"
+            )))
+        );
+        assert_eq!(
+            status(command.clone(), "esp-csi> Unrecognized command"),
+            Some(CommandStatus::Failed("Unrecognized command".to_string()))
+        );
+        assert_eq!(status(command, "No error occurred"), None);
+    }
+
+    #[test]
+    fn prompt_reply() {
+        assert_eq!(status(set_traffic(), "esp-csi>"), Some(CommandStatus::Ok));
+        assert_eq!(
+            status(EspCommand::ShowConfig, "esp-csi> "),
+            Some(CommandStatus::Ok)
+        );
+    }
+
+    #[test]
+    fn timeout_aborts_sequence() {
+        let now = Instant::now();
+        let mut queue = running(vec![set_traffic(), EspCommand::ShowConfig], now);
+        queue.check_timeout(now + REPLY_TIMEOUT / 2);
+        assert!(queue.is_busy());
+        queue.check_timeout(now + REPLY_TIMEOUT * 2);
+        assert_eq!(
+            statuses(&queue),
+            [CommandStatus::Timeout, CommandStatus::Aborted]
+        );
+        assert!(!queue.is_busy());
+    }
+
+    #[test]
+    fn failure_aborts_sequence() {
+        let now = Instant::now();
+        let mut queue = running(
+            vec![set_traffic(), EspCommand::ShowConfig, EspCommand::Start],
+            now,
+        );
+        queue.receive("OK");
+        assert_eq!(queue.next_command(), Some(EspCommand::ShowConfig));
+        queue.written(now);
+        queue.receive("Error: not connected");
+        assert_eq!(
+            statuses(&queue),
+            [
+                CommandStatus::Ok,
+                CommandStatus::Failed("Error: not connected".to_string()),
+                CommandStatus::Aborted
+            ]
+        );
+        assert_eq!(queue.next_command(), None);
+    }
+
+    #[test]
+    fn submit_while_busy() {
+        let mut queue = running(vec![set_traffic()], Instant::now());
+        assert!(!queue.submit(vec![EspCommand::ShowConfig]));
+        assert_eq!(queue.commands[0].command, set_traffic());
+        queue.receive("esp-csi>");
+        assert!(queue.submit(vec![EspCommand::ShowConfig]));
+        assert_eq!(statuses(&queue), [CommandStatus::Pending]);
+    }
+}
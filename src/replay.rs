@@ -0,0 +1,290 @@
+use std::collections::VecDeque;
+use std::error::Error;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::data::{get_epoch_ms, Packet, SerialDirection};
+use crate::framing::Framing;
+use crate::io::open_traffic;
+use crate::transport::{Transport, READ_TIMEOUT};
+
+/// Name of the virtual device in the device list that replays a raw traffic log.
+pub const REPLAY_DEVICE: &str = "Replay (virtual device)";
+
+/// Playback controls of the replay, shared between the GUI and the serial thread.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReplayControl {
+    /// Playback speed relative to the original timing.
+    pub speed: f64,
+    pub paused: bool,
+    /// Start again from the beginning after the last packet.
+    pub looping: bool,
+    /// Requested position in ms since the first packet, taken by the replay.
+    pub seek: Option<f64>,
+    /// Current position and length of the log in ms, set by the replay.
+    pub position: f64,
+    pub duration: f64,
+}
+
+impl Default for ReplayControl {
+    fn default() -> Self {
+        Self {
+            speed: 1.0,
+            paused: false,
+            looping: false,
+            seek: None,
+            position: 0.0,
+            duration: 0.0,
+        }
+    }
+}
+
+/// Plays back the received packets of a raw traffic log with their original timing.
+///
+/// The relative time of the emitted packets continues across seeks and loops, so that the
+/// time axis downstream stays monotonic, the absolute time is kept from the log.
+/// Places the packets on a monotonic timeline starting at zero. The relative time restarts in
+/// logs spanning a reconnect or concatenated recordings, such a segment is placed after the
+/// previous one by the absolute time, or right after it if the clocks disagree.
+fn timeline(packets: &[Packet]) -> Vec<f64> {
+    let mut offsets = Vec::with_capacity(packets.len());
+    let mut offset = 0.0;
+    for (previous, packet) in packets.iter().zip(packets.iter().skip(1)) {
+        offsets.push(offset);
+        let step = packet.relative_time - previous.relative_time;
+        offset += if step >= 0.0 {
+            step
+        } else {
+            (packet.absolute_time - previous.absolute_time).max(0.0)
+        };
+    }
+    if !packets.is_empty() {
+        offsets.push(offset);
+    }
+    offsets
+}
+
+pub struct Replay {
+    packets: Vec<Packet>,
+    /// Position of each packet in the replay, see [`timeline`].
+    offsets: Vec<f64>,
+    next: usize,
+    position: f64,
+    last_update: Instant,
+    last_offset: Option<f64>,
+    time: f64,
+}
+
+impl Replay {
+    pub fn open(path: &Path) -> Result<Self, Box<dyn Error>> {
+        // sent commands and events are generated again while replaying
+        let packets: Vec<Packet> = open_traffic(path)?
+            .into_iter()
+            .filter(|packet| packet.direction == SerialDirection::Receive)
+            .collect();
+        if packets.is_empty() {
+            return Err("the log does not contain any received packets".into());
+        }
+        Ok(Self {
+            offsets: timeline(&packets),
+            packets,
+            next: 0,
+            position: 0.0,
+            last_update: Instant::now(),
+            last_offset: None,
+            time: 0.0,
+        })
+    }
+
+    /// Relative time of the last emitted packet.
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    pub fn duration(&self) -> f64 {
+        self.offsets[self.offsets.len() - 1]
+    }
+
+    fn seek(&mut self, position: f64) {
+        self.position = position.clamp(0.0, self.duration());
+        self.next = self
+            .offsets
+            .partition_point(|offset| *offset < self.position);
+        self.last_offset = None;
+    }
+
+    /// Advances the replay clock according to `control` and returns the packets that are due.
+    pub fn poll(&mut self, control: &mut ReplayControl) -> Vec<Packet> {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_update).as_secs_f64() * 1000.0;
+        self.last_update = now;
+        if let Some(position) = control.seek.take() {
+            self.seek(position);
+        }
+        if !control.paused {
+            self.position += elapsed * control.speed.max(0.0);
+        }
+
+        let mut due = vec![];
+        while let Some(packet) = self.packets.get(self.next) {
+            let offset = self.offsets[self.next];
+            if offset > self.position {
+                break;
+            }
+            if let Some(last) = self.last_offset {
+                self.time += offset - last;
+            }
+            self.last_offset = Some(offset);
+            due.push(Packet {
+                relative_time: self.time,
+                ..packet.clone()
+            });
+            self.next += 1;
+        }
+        if self.next >= self.packets.len() {
+            if control.looping {
+                self.seek(0.0);
+            } else {
+                self.position = self.duration();
+            }
+        }
+
+        control.position = self.position;
+        control.duration = self.duration();
+        due
+    }
+}
+
+/// Transport of the virtual replay device, every read returns one line of the log or the rest
+/// of a line longer than the buffer of the previous read.
+pub struct ReplaySource {
+    replay: Replay,
+    control: Arc<RwLock<ReplayControl>>,
+    pending: VecDeque<Packet>,
+    /// Rest of the last line, if it did not fit into the buffer of the read.
+    unread: Vec<u8>,
+    timestamp: Option<(f64, f64)>,
+}
+
+impl ReplaySource {
+    pub fn open(path: &Path, control: Arc<RwLock<ReplayControl>>) -> Result<Self, Box<dyn Error>> {
+        Ok(Self {
+            replay: Replay::open(path).map_err(|e| format!("{:?}: {}", path, e))?,
+            control,
+            pending: VecDeque::new(),
+            unread: vec![],
+            timestamp: None,
+        })
+    }
+}
+
+impl Read for ReplaySource {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if !self.unread.is_empty() {
+            let len = self.unread.len().min(buf.len());
+            buf[..len].copy_from_slice(&self.unread[..len]);
+            self.unread.drain(..len);
+            return Ok(len);
+        }
+        let start = Instant::now();
+        while self.pending.is_empty() {
+            if start.elapsed() > READ_TIMEOUT {
+                return Err(io::ErrorKind::TimedOut.into());
+            }
+            if let Ok(mut control) = self.control.write() {
+                self.pending.extend(self.replay.poll(&mut control));
+            }
+            if self.pending.is_empty() {
+                std::thread::sleep(Duration::from_millis(1));
+            }
+        }
+        let Some(packet) = self.pending.pop_front() else {
+            return Ok(0);
+        };
+        let line = format!("{}\r\n", packet.payload);
+        // lines longer than the buffer are continued by the next reads
+        let len = line.len().min(buf.len());
+        buf[..len].copy_from_slice(&line.as_bytes()[..len]);
+        self.unread.extend_from_slice(&line.as_bytes()[len..]);
+        self.timestamp = Some((packet.relative_time, packet.absolute_time));
+        Ok(len)
+    }
+}
+
+impl Write for ReplaySource {
+    // sent commands are only logged, there is no device to receive them
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Transport for ReplaySource {
+    fn timestamp(&self) -> Option<(f64, f64)> {
+        // sent commands are placed at the time of the last replayed packet
+        Some(
+            self.timestamp
+                .unwrap_or((self.replay.time(), get_epoch_ms() as f64)),
+        )
+    }
+
+    fn framing(&self) -> Option<Framing> {
+        Some(Framing::Text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packet(relative_time: f64, absolute_time: f64) -> Packet {
+        Packet {
+            relative_time,
+            absolute_time,
+            direction: SerialDirection::Receive,
+            payload: String::new(),
+        }
+    }
+
+    #[test]
+    fn timeline_continues_after_reset_relative_time() {
+        let packets = [
+            packet(100.0, 1000.0),
+            packet(150.0, 1050.0),
+            // reconnected 30 ms later
+            packet(0.0, 1080.0),
+            packet(20.0, 1100.0),
+            // concatenated recording with an earlier absolute time
+            packet(5.0, 500.0),
+        ];
+        assert_eq!(timeline(&packets), vec![0.0, 50.0, 80.0, 100.0, 100.0]);
+    }
+
+    #[test]
+    fn seek_past_reset_relative_time() {
+        let mut replay = Replay {
+            offsets: vec![],
+            packets: vec![
+                packet(100.0, 1000.0),
+                packet(0.0, 1100.0),
+                packet(50.0, 1150.0),
+            ],
+            next: 0,
+            position: 0.0,
+            last_update: Instant::now(),
+            last_offset: None,
+            time: 0.0,
+        };
+        replay.offsets = timeline(&replay.packets);
+        assert_eq!(replay.duration(), 150.0);
+        replay.seek(120.0);
+        assert_eq!(replay.next, 2);
+        replay.seek(-10.0);
+        assert_eq!(replay.next, 0);
+    }
+}
@@ -0,0 +1,83 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+
+use argon2::Argon2;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use preferences::Preferences;
+use serde::{Deserialize, Serialize};
+
+use crate::{APP_INFO, PREFERENCES_KEY_SECRETS};
+
+pub const MIN_PASSPHRASE_LEN: usize = 8;
+const SALT_LEN: usize = 16;
+
+/// Wi-Fi passwords of one device profile.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct WifiPasswords {
+    pub ap_password: String,
+    pub sta_password: String,
+}
+
+/// Wi-Fi passwords of the device profiles by profile name, encrypted with a key derived from a
+/// passphrase. The passwords are never written to disk in any other form.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EncryptedSecrets {
+    salt: Vec<u8>,
+    nonce: Vec<u8>,
+    ciphertext: Vec<u8>,
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<Key, Box<dyn Error>> {
+    let mut key = Key::default();
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|err| format!("key derivation failed: {err}"))?;
+    Ok(key)
+}
+
+impl EncryptedSecrets {
+    pub fn encrypt(
+        passwords: &BTreeMap<String, WifiPasswords>,
+        passphrase: &str,
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut salt = vec![0; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let cipher = ChaCha20Poly1305::new(&derive_key(passphrase, &salt)?);
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let plaintext = serde_json::to_vec(passwords)?;
+        let ciphertext = cipher
+            .encrypt(&nonce, plaintext.as_slice())
+            .map_err(|_| "encryption failed")?;
+        Ok(Self {
+            salt,
+            nonce: nonce.to_vec(),
+            ciphertext,
+        })
+    }
+
+    pub fn decrypt(
+        &self,
+        passphrase: &str,
+    ) -> Result<BTreeMap<String, WifiPasswords>, Box<dyn Error>> {
+        if self.nonce.len() != Nonce::default().len() {
+            return Err("corrupted password store".into());
+        }
+        let cipher = ChaCha20Poly1305::new(&derive_key(passphrase, &self.salt)?);
+        let plaintext = cipher
+            .decrypt(Nonce::from_slice(&self.nonce), self.ciphertext.as_slice())
+            .map_err(|_| "wrong passphrase")?;
+        Ok(serde_json::from_slice(&plaintext)?)
+    }
+}
+
+pub fn load_secrets() -> Option<EncryptedSecrets> {
+    EncryptedSecrets::load(&APP_INFO, PREFERENCES_KEY_SECRETS).ok()
+}
+
+pub fn save_secrets(secrets: &EncryptedSecrets) {
+    if secrets.save(&APP_INFO, PREFERENCES_KEY_SECRETS).is_err() {
+        log::error!("failed to save the password store");
+    }
+}
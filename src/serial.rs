@@ -1,17 +1,23 @@
 use eframe::egui::Color32;
 use preferences::Preferences;
 use serde::{Deserialize, Serialize};
-use serialport::{DataBits, FlowControl, Parity, SerialPort, StopBits};
+use serialport::{DataBits, FlowControl, Parity, StopBits};
+use std::borrow::Cow;
 use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
 use std::sync::mpsc::{Receiver, Sender};
 use std::sync::{Arc, RwLock};
 use std::time::{Duration, Instant};
-use defmt_decoder::{DecodeError, Frame, Locations, Table};
-
+use defmt_decoder::{DecodeError, StreamDecoder};
 
 use crate::color_picker::COLORS;
+use crate::commands::redact;
 //use std::fmt::Write; // Import the Write trait for String
 use crate::data::{get_epoch_ms, SerialDirection};
+use crate::framing::{DefmtTable, FrameDecoder, Framing, FramingStats, Message};
+use crate::queue::CommandQueue;
+use crate::replay::ReplayControl;
+use crate::transport::{available_devices, connect, is_available, is_disconnect, Transport};
 use crate::{Packet, APP_INFO, PREFERENCES_KEY_SERIAL};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -76,6 +82,14 @@ pub struct Device {
     pub parity: Parity,
     pub stop_bits: StopBits,
     pub timeout: Duration,
+    #[serde(default)]
+    pub framing: Framing,
+    /// ELF of the firmware, required for the defmt framing.
+    #[serde(default)]
+    pub elf_path: Option<PathBuf>,
+    /// Raw traffic log played back by the virtual replay device.
+    #[serde(default)]
+    pub replay_path: Option<PathBuf>,
 }
 
 impl Default for Device {
@@ -88,12 +102,15 @@ impl Default for Device {
             parity: Parity::None,
             stop_bits: StopBits::One,
             timeout: Duration::from_millis(0),
+            framing: Framing::Text,
+            elf_path: None,
+            replay_path: None,
         }
     }
 }
 
 fn serial_write(
-    port: &mut BufReader<Box<dyn SerialPort>>,
+    port: &mut BufReader<Box<dyn Transport>>,
     cmd: &[u8],
 ) -> Result<usize, std::io::Error> {
     let write_port = port.get_mut();
@@ -101,18 +118,47 @@ fn serial_write(
 }
 
 fn serial_read(
-    port: &mut BufReader<Box<dyn SerialPort>>,
+    port: &mut BufReader<Box<dyn Transport>>,
     serial_buf: &mut String,
 ) -> Result<usize, std::io::Error> {
     port.read_line(serial_buf)
 }
-/*
+
+/// Packet stamped with the clock of the transport, or the time since `t_zero`.
+fn new_packet(
+    port: &BufReader<Box<dyn Transport>>,
+    t_zero: Instant,
+    direction: SerialDirection,
+    payload: String,
+) -> Packet {
+    let (relative_time, absolute_time) = port.get_ref().timestamp().unwrap_or_else(|| {
+        (
+            Instant::now().duration_since(t_zero).as_millis() as f64,
+            get_epoch_ms() as f64,
+        )
+    });
+    Packet {
+        relative_time,
+        absolute_time,
+        direction,
+        // every packet is shown in the console and may be logged, so passwords are never kept
+        payload: match redact(&payload) {
+            Cow::Borrowed(_) => payload,
+            Cow::Owned(redacted) => redacted,
+        },
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn serial_thread(
     send_rx: Receiver<String>,
     raw_data_tx: Sender<Packet>,
     device_lock: Arc<RwLock<Device>>,
     devices_lock: Arc<RwLock<Vec<String>>>,
     connected_lock: Arc<RwLock<bool>>,
+    framing_stats_lock: Arc<RwLock<FramingStats>>,
+    replay_lock: Arc<RwLock<ReplayControl>>,
+    queue_lock: Arc<RwLock<CommandQueue>>,
 ) {
     let mut last_connected_device = Device::default();
 
@@ -121,18 +167,20 @@ pub fn serial_thread(
             .display(false)
             .reason("Serial Connection")
             .app_name("Serial Monitor")
+            //.app_reverse_domain("io.github.myprog")
             .create();
 
         if let Ok(mut connected) = connected_lock.write() {
             *connected = false;
         }
 
-        let device = get_device(&devices_lock, &device_lock, &last_connected_device);
+        let Some(mut device) = get_device(&devices_lock, &device_lock, &last_connected_device)
+        else {
+            // the GUI has closed the device
+            return;
+        };
 
-        let mut port = match serialport::new(&device.name, device.baud_rate)
-            .timeout(Duration::from_millis(100))
-            .open()
-        {
+        let mut port = match connect(&device, &replay_lock) {
             Ok(p) => {
                 if let Ok(mut connected) = connected_lock.write() {
                     *connected = true;
@@ -151,109 +199,51 @@ pub fn serial_thread(
                     write_guard.name.clear();
                 }
                 log::error!("Error connecting: {}", err);
+                // network sources are always listed, retry them at a slower pace
+                if device.name == last_connected_device.name {
+                    std::thread::sleep(Duration::from_secs(1));
+                }
                 continue;
             }
         };
-
-        let t_zero = Instant::now();
-
-        // Load the defmt symbol table (you need to provide the ELF file path)
-        let elf_path = "D:/Study/GP/ESP/async_main.elf"; // Replace with the actual path to your ELF file
-        let elf_data = match std::fs::read(elf_path) {
-            Ok(data) => data,
-            Err(e) => {
-                log::error!("Failed to read ELF file: {}", e);
-                break;
-            }
-        };
-
-        let table = match Table::parse(&elf_data) {
-            Ok(t) => t,
-            Err(e) => {
-                log::error!("Failed to parse defmt table: {}", e);
-                break;
-            }
-        };
-
-        let _awake = keepawake::Builder::default()
-            .display(true)
-            .reason("Serial Connection")
-            .app_name("Serial Monitor")
-            .create();
-
-        'connected_loop: loop {
-            let devices = available_devices();
-            if let Ok(mut write_guard) = devices_lock.write() {
-                *write_guard = devices.clone();
-            }
-
-            if disconnected(&device, &devices, &device_lock, &mut last_connected_device) {
-                break 'connected_loop;
-            }
-
-            perform_writes(&mut port, &send_rx, &raw_data_tx, t_zero);
-            if let Some(ref table) = table {
-                perform_reads(&mut port, &raw_data_tx, t_zero, table);
-            } else {
-                log::error!("Defmt table is not available");
-            }
-
-            //std::thread::sleep(Duration::from_millis(10));
+        if let Some(framing) = port.get_ref().framing() {
+            device.framing = framing;
         }
-        std::mem::drop(port);
-    }
-}*/
 
-pub fn serial_thread(
-    send_rx: Receiver<String>,
-    raw_data_tx: Sender<Packet>,
-    device_lock: Arc<RwLock<Device>>,
-    devices_lock: Arc<RwLock<Vec<String>>>,
-    connected_lock: Arc<RwLock<bool>>,
-) {
-    let mut last_connected_device = Device::default();
-
-    loop {
-        let _not_awake = keepawake::Builder::default()
-            .display(false)
-            .reason("Serial Connection")
-            .app_name("Serial Monitor")
-            //.app_reverse_domain("io.github.myprog")
-            .create();
-
-        if let Ok(mut connected) = connected_lock.write() {
-            *connected = false;
-        }
-
-        let device = get_device(&devices_lock, &device_lock, &last_connected_device);
-
-        let mut port = match serialport::new(&device.name, device.baud_rate)
-            .timeout(Duration::from_millis(100))
-            .open()
-        {
-            Ok(p) => {
-                if let Ok(mut connected) = connected_lock.write() {
-                    *connected = true;
+        // the table is loaded once per connection, the stream decoder borrows it
+        let defmt_table = match (device.framing, &device.elf_path) {
+            (Framing::Defmt, Some(elf_path)) => match DefmtTable::load(elf_path) {
+                Ok(table) => Some(table),
+                Err(err) => {
+                    if let Ok(mut write_guard) = device_lock.write() {
+                        write_guard.name.clear();
+                    }
+                    log::error!("Error loading defmt table: {}", err);
+                    continue;
                 }
-
-                log::info!(
-                    "Connected to serial port: {} @ baud = {}",
-                    device.name,
-                    device.baud_rate
-                );
-
-                BufReader::new(p)
-            }
-            Err(err) => {
+            },
+            (Framing::Defmt, None) => {
                 if let Ok(mut write_guard) = device_lock.write() {
                     write_guard.name.clear();
                 }
-                log::error!("Error connecting: {}", err);
+                log::error!("No ELF file selected for defmt decoding");
                 continue;
             }
+            _ => None,
         };
+        let mut defmt_decoder = defmt_table
+            .as_ref()
+            .map(|defmt_table| defmt_table.table.new_stream_decoder());
 
         let t_zero = Instant::now();
+        let mut frame_decoder = FrameDecoder::new(device.framing);
+
+        if let Some(greeting) = port.get_ref().greeting() {
+            let packet = new_packet(&port, t_zero, SerialDirection::Event, greeting);
+            if let Err(err) = raw_data_tx.send(packet) {
+                log::error!("raw_data_tx thread send failed: {:?}", err);
+            }
+        }
 
         let _awake = keepawake::Builder::default()
             .display(true)
@@ -262,10 +252,17 @@ pub fn serial_thread(
             //.app_reverse_domain("io.github.myprog")
             .create();
 
+        let mut devices = available_devices();
+        let mut devices_updated = Instant::now();
+
         'connected_loop: loop {
-            let devices = available_devices();
-            if let Ok(mut write_guard) = devices_lock.write() {
-                *write_guard = devices.clone();
+            // enumerating the ports is slow compared to reading a line
+            if devices_updated.elapsed() > Duration::from_millis(500) {
+                devices = available_devices();
+                if let Ok(mut write_guard) = devices_lock.write() {
+                    *write_guard = devices.clone();
+                }
+                devices_updated = Instant::now();
             }
 
             if disconnected(&device, &devices, &device_lock, &mut last_connected_device) {
@@ -273,46 +270,86 @@ pub fn serial_thread(
             }
 
             perform_writes(&mut port, &send_rx, &raw_data_tx, t_zero);
-            perform_reads(&mut port, &raw_data_tx, t_zero);
+            perform_queued_writes(&mut port, &queue_lock, &raw_data_tx, t_zero);
+            let result = if let (Some(table), Some(decoder)) = (&defmt_table, &mut defmt_decoder) {
+                perform_defmt_reads(
+                    &mut port,
+                    &raw_data_tx,
+                    &queue_lock,
+                    t_zero,
+                    table,
+                    decoder.as_mut(),
+                    &mut frame_decoder.stats,
+                )
+            } else if device.framing == Framing::Text {
+                perform_reads(&mut port, &raw_data_tx, &queue_lock, t_zero)
+            } else {
+                perform_binary_reads(
+                    &mut port,
+                    &raw_data_tx,
+                    &queue_lock,
+                    t_zero,
+                    &mut frame_decoder,
+                )
+            };
+            match result {
+                // Timeout is ok, just means there is no data to read
+                Err(ref e) if e.kind() == std::io::ErrorKind::TimedOut => {}
+                Err(ref e) if is_disconnect(e) => {
+                    connection_lost(&device, &device_lock, &mut last_connected_device);
+                    break 'connected_loop;
+                }
+                Err(e) => {
+                    log::error!("Error reading: {:?}", e);
+                }
+                Ok(()) => {}
+            }
+            if device.framing != Framing::Text {
+                if let Ok(mut write_guard) = framing_stats_lock.write() {
+                    *write_guard = frame_decoder.stats.clone();
+                }
+            }
 
             //std::thread::sleep(Duration::from_millis(10));
         }
         std::mem::drop(port);
+        // commands of the sequence are not sent to the next connection
+        if let Ok(mut queue) = queue_lock.write() {
+            queue.abort();
+        }
     }
 }
 
-
-fn available_devices() -> Vec<String> {
-    serialport::available_ports()
-        .unwrap()
-        .iter()
-        .map(|p| p.port_name.clone())
-        .collect()
-}
-
 fn get_device(
     devices_lock: &Arc<RwLock<Vec<String>>>,
     device_lock: &Arc<RwLock<Device>>,
     last_connected_device: &Device,
-) -> Device {
+) -> Option<Device> {
     loop {
+        // nobody else holds the lock once the pane of the device is closed
+        if Arc::strong_count(device_lock) == 1 {
+            return None;
+        }
+
         let devices = available_devices();
         if let Ok(mut write_guard) = devices_lock.write() {
             *write_guard = devices.clone();
         }
 
-        // do reconnect
-        if devices.contains(&last_connected_device.name) {
+        // do reconnect, unless another device has been selected in the meantime
+        if is_available(&last_connected_device.name, &devices) {
             if let Ok(mut device) = device_lock.write() {
-                device.name = last_connected_device.name.clone();
-                device.baud_rate = last_connected_device.baud_rate;
+                if device.name.is_empty() || device.name == last_connected_device.name {
+                    device.name = last_connected_device.name.clone();
+                    device.baud_rate = last_connected_device.baud_rate;
+                    return Some(last_connected_device.clone());
+                }
             }
-            return last_connected_device.clone();
         }
 
         if let Ok(device) = device_lock.read() {
-            if devices.contains(&device.name) {
-                return device.clone();
+            if is_available(&device.name, &devices) {
+                return Some(device.clone());
             }
         }
         std::thread::sleep(Duration::from_millis(100));
@@ -336,19 +373,28 @@ fn disconnected(
     }
 
     // other types of disconnection (e.g. unplugging, power down)
-    if !devices.contains(&device.name) {
-        if let Ok(mut write_guard) = device_lock.write() {
-            write_guard.name.clear();
-        }
-        *last_connected_device = device.clone();
-        log::error!("Device has disconnected from serial port: {}", device.name);
+    if !is_available(&device.name, devices) {
+        connection_lost(device, device_lock, last_connected_device);
         return true;
     };
     false
 }
 
+/// Remembers the device to connect again once it is available.
+fn connection_lost(
+    device: &Device,
+    device_lock: &Arc<RwLock<Device>>,
+    last_connected_device: &mut Device,
+) {
+    if let Ok(mut write_guard) = device_lock.write() {
+        write_guard.name.clear();
+    }
+    *last_connected_device = device.clone();
+    log::error!("Device has disconnected from serial port: {}", device.name);
+}
+
 fn perform_writes(
-    port: &mut BufReader<Box<dyn SerialPort>>,
+    port: &mut BufReader<Box<dyn Transport>>,
     send_rx: &Receiver<String>,
     raw_data_tx: &Sender<Packet>,
     t_zero: Instant,
@@ -383,104 +429,149 @@ fn perform_writes(
                 return;
             }
 
-            let packet = Packet {
-                relative_time: Instant::now().duration_since(t_zero).as_millis() as f64,
-                absolute_time: get_epoch_ms() as f64,
-                direction: SerialDirection::Send,
-                payload: String::from("Ctrl+R (0x03)"),
-            };
+            let packet = new_packet(
+                port,
+                t_zero,
+                SerialDirection::Send,
+                String::from("Ctrl+R (0x03)"),
+            );
             raw_data_tx
                 .send(packet)
                 .expect("failed to send raw data (Ctrl+C)");
             return;
         }
         //end of fares habed
-        if let Err(e) = serial_write(port, cmd.as_bytes()) {
+        if let Err(e) = write_command(port, raw_data_tx, t_zero, cmd) {
             log::error!("Error sending command: {e}");
-            return;
         }
-        
-        let packet = Packet {
-            relative_time: Instant::now().duration_since(t_zero).as_millis() as f64,
-            absolute_time: get_epoch_ms() as f64,
-            direction: SerialDirection::Send,
-            payload: cmd,
-        };
-        raw_data_tx
-            .send(packet)
-            .expect("failed to send raw data (cmd)");
     }
 }
 
-fn perform_reads(
-    port: &mut BufReader<Box<dyn SerialPort>>,
+fn write_command(
+    port: &mut BufReader<Box<dyn Transport>>,
+    raw_data_tx: &Sender<Packet>,
+    t_zero: Instant,
+    cmd: String,
+) -> std::io::Result<()> {
+    serial_write(port, cmd.as_bytes())?;
+    let packet = new_packet(port, t_zero, SerialDirection::Send, cmd);
+    raw_data_tx
+        .send(packet)
+        .expect("failed to send raw data (cmd)");
+    Ok(())
+}
+
+/// Writes the next command of the queue, once the previous one has been answered.
+fn perform_queued_writes(
+    port: &mut BufReader<Box<dyn Transport>>,
+    queue_lock: &Arc<RwLock<CommandQueue>>,
     raw_data_tx: &Sender<Packet>,
     t_zero: Instant,
 ) {
-    let mut buf = "".to_string();
-    match serial_read(port, &mut buf) {
-        Ok(_) => {
-            let delimiter = if buf.contains("\r\n") { "\r\n" } else { "\0\0" };
-            buf.split_terminator(delimiter).for_each(|s| {
-                let packet = Packet {
-                    relative_time: Instant::now().duration_since(t_zero).as_millis() as f64,
-                    absolute_time: get_epoch_ms() as f64,
-                    direction: SerialDirection::Receive,
-                    payload: s.to_owned(),
-                };
-                raw_data_tx.send(packet).expect("failed to send raw data");
-            });
-        }
-        // Timeout is ok, just means there is no data to read
-        Err(ref e) if e.kind() == std::io::ErrorKind::TimedOut => {}
+    let Ok(mut queue) = queue_lock.write() else {
+        return;
+    };
+    queue.check_timeout(Instant::now());
+    let Some(command) = queue.next_command() else {
+        return;
+    };
+    match write_command(port, raw_data_tx, t_zero, command.to_line()) {
+        Ok(()) => queue.written(Instant::now()),
         Err(e) => {
-            log::error!("Error reading: {:?}", e);
+            log::error!("Error sending command: {e}");
+            queue.write_failed(e.to_string());
         }
     }
 }
 
-/*
+/// Passes a received line to the queued command waiting for its reply.
+fn receive_reply(queue_lock: &Arc<RwLock<CommandQueue>>, line: &str) {
+    if let Ok(mut queue) = queue_lock.write() {
+        queue.receive(line);
+    }
+}
+
 fn perform_reads(
-    port: &mut BufReader<Box<dyn SerialPort>>,
+    port: &mut BufReader<Box<dyn Transport>>,
     raw_data_tx: &Sender<Packet>,
+    queue_lock: &Arc<RwLock<CommandQueue>>,
     t_zero: Instant,
-    table: &Table, // Add the defmt `Table` as a parameter
-) {
-    let mut buf = String::new();
-    match serial_read(port, &mut buf) {
-        Ok(_) => {
-            let bytes = buf.trim().as_bytes();
-            match table.decode(bytes) {
-                Ok((frame, _)) => {
-                    // Decode the defmt frame into a human-readable message
-                    let mut decoded_message = String::new();
-                    if let Err(e) = write!(&mut decoded_message, "{}", frame.display(true)) {
-                        log::error!("Failed to format decoded message: {:?}", e);
-                        return;
-                    }
+) -> std::io::Result<()> {
+    let mut buf = "".to_string();
+    if serial_read(port, &mut buf)? == 0 {
+        // end of a stream, e.g. a closed network connection
+        return Err(std::io::ErrorKind::UnexpectedEof.into());
+    }
+    let delimiter = if buf.contains("\r\n") { "\r\n" } else { "\0\0" };
+    buf.split_terminator(delimiter).for_each(|s| {
+        receive_reply(queue_lock, s);
+        let packet = new_packet(port, t_zero, SerialDirection::Receive, s.to_owned());
+        raw_data_tx.send(packet).expect("failed to send raw data");
+    });
+    Ok(())
+}
 
-                    // Send the decoded message as a Packet
-                    let packet = Packet {
-                        relative_time: Instant::now().duration_since(t_zero).as_millis() as f64,
-                        absolute_time: get_epoch_ms() as f64,
-                        direction: SerialDirection::Receive,
-                        payload: decoded_message,
-                    };
-                    raw_data_tx.send(packet).expect("failed to send raw data");
-                }
-                Err(DecodeError::UnexpectedEof) => {
-                    log::warn!("Incomplete defmt frame received");
-                }
-                Err(e) => {
-                    log::error!("Failed to decode defmt frame: {:?}", e);
-                }
+fn perform_binary_reads(
+    port: &mut BufReader<Box<dyn Transport>>,
+    raw_data_tx: &Sender<Packet>,
+    queue_lock: &Arc<RwLock<CommandQueue>>,
+    t_zero: Instant,
+    frame_decoder: &mut FrameDecoder,
+) -> std::io::Result<()> {
+    let bytes = port.fill_buf()?;
+    if bytes.is_empty() {
+        return Err(std::io::ErrorKind::UnexpectedEof.into());
+    }
+    let len = bytes.len();
+    let messages = frame_decoder.push(bytes);
+    port.consume(len);
+    for message in messages {
+        // CSI frames are passed on in the text format of the esp-csi CLI
+        let payload = match message {
+            Message::Csi(frame) => frame.to_string(),
+            Message::Text(text) => {
+                receive_reply(queue_lock, &text);
+                text
             }
-        }
-        // Timeout is ok, just means there is no data to read
-        Err(ref e) if e.kind() == std::io::ErrorKind::TimedOut => {}
-        Err(e) => {
-            log::error!("Error reading: {:?}", e);
-        }
+        };
+        let packet = new_packet(port, t_zero, SerialDirection::Receive, payload);
+        raw_data_tx.send(packet).expect("failed to send raw data");
     }
-}*/
+    Ok(())
+}
 
+fn perform_defmt_reads(
+    port: &mut BufReader<Box<dyn Transport>>,
+    raw_data_tx: &Sender<Packet>,
+    queue_lock: &Arc<RwLock<CommandQueue>>,
+    t_zero: Instant,
+    table: &DefmtTable,
+    decoder: &mut dyn StreamDecoder,
+    stats: &mut FramingStats,
+) -> std::io::Result<()> {
+    let bytes = port.fill_buf()?;
+    if bytes.is_empty() {
+        return Err(std::io::ErrorKind::UnexpectedEof.into());
+    }
+    let len = bytes.len();
+    decoder.received(bytes);
+    port.consume(len);
+    loop {
+        let payload = match decoder.decode() {
+            Ok(frame) => table.format(&frame),
+            // the rest of the frame has not been received yet
+            Err(DecodeError::UnexpectedEof) => break,
+            // rzCOBS skips the broken frame, decoding continues with the next read
+            Err(DecodeError::Malformed) => {
+                stats.malformed += 1;
+                log::warn!("Malformed defmt frame received");
+                break;
+            }
+        };
+        stats.frames += 1;
+        receive_reply(queue_lock, &payload);
+        let packet = new_packet(port, t_zero, SerialDirection::Receive, payload);
+        raw_data_tx.send(packet).expect("failed to send raw data");
+    }
+    Ok(())
+}
@@ -0,0 +1,268 @@
+use std::f64::consts::PI;
+use std::fmt;
+
+use crate::filter::sample_rate;
+
+/// Window function applied to every segment before the FFT.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Window {
+    Rectangular,
+    #[default]
+    Hann,
+    Hamming,
+    Blackman,
+}
+
+impl Window {
+    pub const ALL: [Window; 4] = [
+        Window::Rectangular,
+        Window::Hann,
+        Window::Hamming,
+        Window::Blackman,
+    ];
+
+    pub fn coefficients(&self, n: usize) -> Vec<f64> {
+        let denominator = n.saturating_sub(1).max(1) as f64;
+        (0..n)
+            .map(|i| {
+                let x = 2.0 * PI * i as f64 / denominator;
+                match self {
+                    Window::Rectangular => 1.0,
+                    Window::Hann => 0.5 - 0.5 * x.cos(),
+                    Window::Hamming => 0.54 - 0.46 * x.cos(),
+                    Window::Blackman => 0.42 - 0.5 * x.cos() + 0.08 * (2.0 * x).cos(),
+                }
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for Window {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Window::Rectangular => write!(f, "Rectangular"),
+            Window::Hann => write!(f, "Hann"),
+            Window::Hamming => write!(f, "Hamming"),
+            Window::Blackman => write!(f, "Blackman"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpectrumSettings {
+    pub window: Window,
+    /// Analysed time span at the end of the data in seconds.
+    pub span: f64,
+    /// First and last dataset (subcarrier) included, the spectra are averaged.
+    pub first_dataset: usize,
+    pub last_dataset: usize,
+    /// Rate of the uniform resampling in Hz, the mean sample rate is used if `None`.
+    pub resample_rate: Option<f64>,
+    /// Number of samples of every short-time Fourier transform segment.
+    pub segment: usize,
+    /// Overlap of adjacent segments in `[0, 1)`.
+    pub overlap: f64,
+}
+
+impl Default for SpectrumSettings {
+    fn default() -> Self {
+        Self {
+            window: Window::Hann,
+            span: 30.0,
+            first_dataset: 0,
+            last_dataset: usize::MAX,
+            resample_rate: None,
+            segment: 128,
+            overlap: 0.75,
+        }
+    }
+}
+
+/// Linear interpolation of `values` at timestamps `time` (in ms) onto a uniform grid
+/// with `rate` samples per second, starting at the first timestamp.
+pub fn resample(time: &[f64], values: &[f32], rate: f64) -> Vec<f32> {
+    let n = time.len().min(values.len());
+    if n < 2 || rate <= 0.0 {
+        return values[..n].to_vec();
+    }
+    let step = 1000.0 / rate;
+    let samples = ((time[n - 1] - time[0]) / step).floor() as usize + 1;
+    let mut output = Vec::with_capacity(samples);
+    let mut j = 0;
+    for i in 0..samples {
+        let t = time[0] + i as f64 * step;
+        while j + 2 < n && time[j + 1] < t {
+            j += 1;
+        }
+        let dt = time[j + 1] - time[j];
+        let frac = if dt > 0.0 {
+            ((t - time[j]) / dt).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        output.push(values[j] + (values[j + 1] - values[j]) * frac as f32);
+    }
+    output
+}
+
+/// In-place iterative radix-2 FFT, the length has to be a power of two.
+fn fft(re: &mut [f64], im: &mut [f64]) {
+    let n = re.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * PI / len as f64;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (w_im, w_re) = (angle * k as f64).sin_cos();
+                let (a, b) = (start + k, start + k + len / 2);
+                let t_re = re[b] * w_re - im[b] * w_im;
+                let t_im = re[b] * w_im + im[b] * w_re;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+/// One-sided amplitude spectrum of the mean-free, windowed `values`, zero-padded to
+/// the next power of two. Returns the amplitudes and the FFT length.
+pub fn amplitude_spectrum(values: &[f32], window: Window) -> (Vec<f32>, usize) {
+    let n = values.len().next_power_of_two().max(2);
+    let mean = values.iter().map(|v| *v as f64).sum::<f64>() / values.len().max(1) as f64;
+    let coefficients = window.coefficients(values.len());
+    let gain = coefficients.iter().sum::<f64>().max(f64::EPSILON);
+    let mut re = vec![0.0; n];
+    let mut im = vec![0.0; n];
+    for ((r, v), w) in re.iter_mut().zip(values).zip(&coefficients) {
+        *r = (*v as f64 - mean) * w;
+    }
+    fft(&mut re, &mut im);
+    let amplitudes = re[..=n / 2]
+        .iter()
+        .zip(&im[..=n / 2])
+        .enumerate()
+        .map(|(k, (r, i))| {
+            // all bins except DC and Nyquist carry the energy of the negative frequencies
+            let scale = if k == 0 || k == n / 2 { 1.0 } else { 2.0 };
+            (scale * r.hypot(*i) / gain) as f32
+        })
+        .collect();
+    (amplitudes, n)
+}
+
+/// The uniformly resampled datasets of the analysed span, their sample rate and the time
+/// of the first sample in seconds.
+pub fn uniform_series(
+    time: &[f64],
+    dataset: &[Vec<f32>],
+    settings: &SpectrumSettings,
+) -> (Vec<Vec<f32>>, f64, f64) {
+    let Some(last) = time.last() else {
+        return (vec![], 0.0, 0.0);
+    };
+    let start = time.partition_point(|t| *t < last - settings.span * 1000.0);
+    let time = &time[start..];
+    let rate = settings.resample_rate.unwrap_or_else(|| sample_rate(time));
+    let last_dataset = settings.last_dataset.min(dataset.len().saturating_sub(1));
+    let series = dataset
+        .iter()
+        .enumerate()
+        .filter(|(i, set)| {
+            (settings.first_dataset..=last_dataset).contains(i) && set.len() == start + time.len()
+        })
+        .map(|(_, set)| resample(time, &set[start..], rate))
+        .collect();
+    (
+        series,
+        rate,
+        time.first().copied().unwrap_or_default() / 1000.0,
+    )
+}
+
+/// Mean amplitude spectrum of the selected datasets as (frequency [Hz], amplitude) pairs.
+pub fn spectrum(time: &[f64], dataset: &[Vec<f32>], settings: &SpectrumSettings) -> Vec<[f64; 2]> {
+    let (series, rate, _) = uniform_series(time, dataset, settings);
+    if series.is_empty() || rate <= 0.0 {
+        return vec![];
+    }
+    let mut mean: Vec<f64> = vec![];
+    let mut fft_len = 0;
+    for set in series.iter() {
+        let (amplitudes, n) = amplitude_spectrum(set, settings.window);
+        fft_len = n;
+        mean.resize(amplitudes.len(), 0.0);
+        for (m, a) in mean.iter_mut().zip(amplitudes) {
+            *m += a as f64 / series.len() as f64;
+        }
+    }
+    mean.into_iter()
+        .enumerate()
+        .map(|(k, a)| [k as f64 * rate / fft_len as f64, a])
+        .collect()
+}
+
+/// Short-time Fourier transform of the selected datasets.
+pub struct Spectrogram {
+    /// Amplitude in dB of every frequency bin (rows) over the segments (columns).
+    pub bins: Vec<Vec<f32>>,
+    pub frequency_step: f64,
+    /// Time between adjacent segments in seconds.
+    pub time_step: f64,
+    /// Time of the center of the first segment in seconds.
+    pub start: f64,
+}
+
+pub fn spectrogram(
+    time: &[f64],
+    dataset: &[Vec<f32>],
+    settings: &SpectrumSettings,
+) -> Option<Spectrogram> {
+    let (series, rate, t0) = uniform_series(time, dataset, settings);
+    let samples = series.iter().map(|set| set.len()).min()?;
+    let segment = settings.segment.clamp(8, 4096);
+    if rate <= 0.0 || samples < segment {
+        return None;
+    }
+    let hop = ((segment as f64 * (1.0 - settings.overlap.clamp(0.0, 0.95))) as usize).max(1);
+    let segments = (samples - segment) / hop + 1;
+    let mut bins: Vec<Vec<f32>> = vec![];
+    let mut fft_len = 0;
+    for s in 0..segments {
+        let mut mean: Vec<f32> = vec![];
+        for set in series.iter() {
+            let (amplitudes, n) =
+                amplitude_spectrum(&set[s * hop..s * hop + segment], settings.window);
+            fft_len = n;
+            mean.resize(amplitudes.len(), 0.0);
+            for (m, a) in mean.iter_mut().zip(amplitudes) {
+                *m += a / series.len() as f32;
+            }
+        }
+        bins.resize(mean.len(), vec![]);
+        for (bin, m) in bins.iter_mut().zip(mean) {
+            bin.push(20.0 * m.max(1e-6).log10());
+        }
+    }
+    Some(Spectrogram {
+        bins,
+        frequency_step: rate / fft_len as f64,
+        time_step: hop as f64 / rate,
+        start: t0 + (segment as f64 / 2.0) / rate,
+    })
+}
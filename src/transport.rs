@@ -0,0 +1,164 @@
+use std::error::Error;
+use std::io::{self, Read, Write};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use serialport::SerialPort;
+
+use crate::framing::Framing;
+use crate::network::{network_endpoint, NetworkProtocol, TcpSource, UdpSource};
+use crate::replay::{ReplayControl, ReplaySource, REPLAY_DEVICE};
+use crate::serial::Device;
+
+/// Name of the virtual device in the device list that opens a pseudo terminal.
+#[cfg(unix)]
+pub const PTY_DEVICE: &str = "Pseudo terminal (virtual device)";
+
+/// Reads return an error of kind `TimedOut` if no data arrived within this time.
+pub const READ_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// A byte stream the serial thread reads from and writes to.
+pub trait Transport: Read + Write + Send {
+    /// Sets the DTR control line, sources without control lines ignore it.
+    fn write_data_terminal_ready(&mut self, _level: bool) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Sets the RTS control line, sources without control lines ignore it.
+    fn write_request_to_send(&mut self, _level: bool) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Relative and absolute time in ms of the last read data, for sources with their own clock.
+    fn timestamp(&self) -> Option<(f64, f64)> {
+        None
+    }
+
+    /// Framing of the source, overriding the one selected for the device.
+    fn framing(&self) -> Option<Framing> {
+        None
+    }
+
+    /// Message shown in the console after connecting.
+    fn greeting(&self) -> Option<String> {
+        None
+    }
+}
+
+impl Transport for Box<dyn SerialPort> {
+    fn write_data_terminal_ready(&mut self, level: bool) -> io::Result<()> {
+        SerialPort::write_data_terminal_ready(self.as_mut(), level).map_err(io::Error::from)
+    }
+
+    fn write_request_to_send(&mut self, level: bool) -> io::Result<()> {
+        SerialPort::write_request_to_send(self.as_mut(), level).map_err(io::Error::from)
+    }
+}
+
+/// Pseudo terminal for simulators and other programs that talk to a serial port.
+///
+/// The application keeps the master side, other programs open the slave side.
+#[cfg(unix)]
+pub struct PtySource {
+    master: serialport::TTYPort,
+    // reads on the master fail once the last handle of the slave is closed
+    slave: serialport::TTYPort,
+}
+
+#[cfg(unix)]
+impl PtySource {
+    pub fn open() -> Result<Self, serialport::Error> {
+        let (mut master, slave) = serialport::TTYPort::pair()?;
+        master.set_timeout(READ_TIMEOUT)?;
+        Ok(Self { master, slave })
+    }
+}
+
+#[cfg(unix)]
+impl Read for PtySource {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.master.read(buf)
+    }
+}
+
+#[cfg(unix)]
+impl Write for PtySource {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.master.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.master.flush()
+    }
+}
+
+#[cfg(unix)]
+impl Transport for PtySource {
+    fn greeting(&self) -> Option<String> {
+        self.slave
+            .name()
+            .map(|name| format!("Pseudo terminal opened at {name}"))
+    }
+}
+
+/// Names of all devices that can be connected to.
+pub fn available_devices() -> Vec<String> {
+    let mut devices: Vec<String> = serialport::available_ports()
+        .unwrap_or_default()
+        .iter()
+        .map(|p| p.port_name.clone())
+        .collect();
+    #[cfg(unix)]
+    devices.push(PTY_DEVICE.to_string());
+    devices.push(REPLAY_DEVICE.to_string());
+    devices
+}
+
+/// Opens the transport for the device selected in the device list.
+pub fn connect(
+    device: &Device,
+    replay_lock: &Arc<RwLock<ReplayControl>>,
+) -> Result<Box<dyn Transport>, Box<dyn Error>> {
+    if let Some((protocol, address)) = network_endpoint(&device.name) {
+        return match protocol {
+            NetworkProtocol::Tcp => Ok(Box::new(TcpSource::connect(address)?)),
+            NetworkProtocol::Udp => Ok(Box::new(UdpSource::bind(
+                address,
+                device.framing == Framing::Text,
+            )?)),
+        };
+    }
+    match device.name.as_str() {
+        REPLAY_DEVICE => {
+            let path = device
+                .replay_path
+                .as_ref()
+                .ok_or("no traffic log selected")?;
+            Ok(Box::new(ReplaySource::open(path, replay_lock.clone())?))
+        }
+        #[cfg(unix)]
+        PTY_DEVICE => Ok(Box::new(PtySource::open()?)),
+        name => Ok(Box::new(
+            serialport::new(name, device.baud_rate)
+                .timeout(READ_TIMEOUT)
+                .open()?,
+        )),
+    }
+}
+
+/// Network sources are not enumerated, connecting to them is always attempted.
+pub fn is_available(name: &str, devices: &[String]) -> bool {
+    devices.iter().any(|device| device == name) || network_endpoint(name).is_some()
+}
+
+/// Errors after which the transport has to be connected again.
+pub fn is_disconnect(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::BrokenPipe
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::NotConnected
+            | io::ErrorKind::UnexpectedEof
+    )
+}